@@ -0,0 +1,782 @@
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tokio::fs::{create_dir_all, remove_dir_all, remove_file};
+
+use crate::{
+    database::{DbPool, entity::settings::BackupSettings},
+    plugin::Plugins,
+    utils::{
+        file::collect_files,
+        zip::{create_zip_file, create_zip_reader, extract_zip_file, extract_zip_limited},
+    },
+};
+
+/// Current version of the backup archive format
+const BACKUP_VERSION: u32 = 1;
+
+/// Name of the manifest file within a backup archive
+const BACKUP_MANIFEST: &str = "backup.json";
+
+/// Name of the database file within the app data and backup archives
+const DATABASE_FILE: &str = "app.db";
+
+/// Journal files of the database, replaced along with the database
+const DATABASE_JOURNAL_FILES: &[&str] = &["app.db-wal", "app.db-shm"];
+
+/// Files that are part of a backup other than the database
+const BACKUP_FILES: &[&str] = &["private_key.bin", "private_key.previous.bin"];
+
+/// Directories that are part of a backup
+const BACKUP_DIRECTORIES: &[&str] = &["plugins", "icons", "uploaded_icons"];
+
+/// Directory within app data that backups are stored in
+const BACKUPS_DIRECTORY: &str = "backups";
+
+/// Directory within app data that a restore is staged in until
+/// the next startup
+const RESTORE_DIRECTORY: &str = "restore";
+
+/// Directory within app data that the current data is moved to while
+/// a restore is applied, moved back if the restore fails
+const PREVIOUS_DIRECTORY: &str = "restore-previous";
+
+/// Marker within [PREVIOUS_DIRECTORY] written once all the current data
+/// has been moved aside and the staged data starts being moved in
+const PREVIOUS_MOVED_MARKER: &str = ".moved";
+
+/// File name prefix for manually created backups
+const MANUAL_BACKUP_PREFIX: &str = "tilepad-backup-";
+
+/// File name prefix for automatic backups
+const AUTOMATIC_BACKUP_PREFIX: &str = "tilepad-auto-backup-";
+
+/// Interval between checks for whether an automatic backup is due
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 15);
+
+/// SQLite database file header
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// Maximum number of files extracted from a backup
+const MAX_BACKUP_FILES: usize = 65536;
+
+/// Maximum combined uncompressed size of the files extracted from a backup (4GB)
+const MAX_BACKUP_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+pub struct Backups {
+    db: DbPool,
+    plugins: Arc<Plugins>,
+
+    /// Path to the app data directory
+    app_data_path: PathBuf,
+
+    /// Settings for automatic backups
+    settings: RwLock<BackupSettings>,
+}
+
+/// Manifest stored within the backup archive
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub version: u32,
+    pub app_version: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Backup stored in the backups directory
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupInfo {
+    pub file_name: String,
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+    pub automatic: bool,
+}
+
+impl Backups {
+    pub fn new(
+        db: DbPool,
+        plugins: Arc<Plugins>,
+        app_data_path: PathBuf,
+        settings: BackupSettings,
+    ) -> Self {
+        Self {
+            db,
+            plugins,
+            app_data_path,
+            settings: RwLock::new(settings),
+        }
+    }
+
+    pub fn set_settings(&self, settings: BackupSettings) {
+        *self.settings.write() = settings;
+    }
+
+    fn backups_path(&self) -> PathBuf {
+        self.app_data_path.join(BACKUPS_DIRECTORY)
+    }
+
+    /// Get all the backups stored in the backups directory, newest first
+    pub async fn get_backups(&self) -> anyhow::Result<Vec<BackupInfo>> {
+        list_backups(&self.backups_path()).await
+    }
+
+    /// Create a new backup of the app data in the backups directory
+    pub async fn create_backup(&self, automatic: bool) -> anyhow::Result<BackupInfo> {
+        let backups_path = self.backups_path();
+        create_dir_all(&backups_path)
+            .await
+            .context("failed to create backups directory")?;
+
+        let created_at = Utc::now();
+        let prefix = if automatic {
+            AUTOMATIC_BACKUP_PREFIX
+        } else {
+            MANUAL_BACKUP_PREFIX
+        };
+        let file_name = format!("{prefix}{}.zip", created_at.format("%Y%m%d-%H%M%S"));
+        let backup_path = backups_path.join(&file_name);
+
+        let manifest = BackupManifest {
+            version: BACKUP_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at,
+        };
+        let manifest_path = backups_path.join(format!(".{file_name}.json"));
+        let snapshot_path = backups_path.join(format!(".{file_name}.db"));
+
+        let result = self
+            .write_backup(&manifest, &manifest_path, &snapshot_path, &backup_path)
+            .await;
+
+        // Remove the temporary files
+        _ = remove_file(&manifest_path).await;
+        _ = remove_file(&snapshot_path).await;
+
+        if let Err(cause) = result {
+            _ = remove_file(&backup_path).await;
+            return Err(cause);
+        }
+
+        let metadata = tokio::fs::metadata(&backup_path).await?;
+
+        Ok(BackupInfo {
+            file_name,
+            size: metadata.len(),
+            created_at,
+            automatic,
+        })
+    }
+
+    async fn write_backup(
+        &self,
+        manifest: &BackupManifest,
+        manifest_path: &Path,
+        snapshot_path: &Path,
+        backup_path: &Path,
+    ) -> anyhow::Result<()> {
+        let manifest = serde_json::to_vec_pretty(manifest)?;
+        tokio::fs::write(manifest_path, manifest)
+            .await
+            .context("failed to write backup manifest")?;
+
+        // Take a consistent snapshot of the database
+        let snapshot = snapshot_path
+            .to_str()
+            .context("invalid database snapshot path")?;
+        sqlx::query("VACUUM INTO ?")
+            .bind(snapshot)
+            .execute(&self.db)
+            .await
+            .context("failed to snapshot database")?;
+
+        let mut files = vec![
+            (BACKUP_MANIFEST.to_string(), manifest_path.to_path_buf()),
+            (DATABASE_FILE.to_string(), snapshot_path.to_path_buf()),
+        ];
+
+        for name in BACKUP_FILES {
+            let path = self.app_data_path.join(name);
+            if path.is_file() {
+                files.push((name.to_string(), path));
+            }
+        }
+
+        for name in BACKUP_DIRECTORIES {
+            let path = self.app_data_path.join(name);
+            for file in collect_files(&path).await? {
+                let file_name = Path::new(name)
+                    .join(&file)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                files.push((file_name, path.join(file)));
+            }
+        }
+
+        create_zip_file(backup_path, files).await
+    }
+
+    /// Read the contents of a stored backup
+    pub async fn read_backup(&self, file_name: &str) -> anyhow::Result<Vec<u8>> {
+        anyhow::ensure!(
+            Path::new(file_name)
+                .file_name()
+                .is_some_and(|name| name == file_name),
+            "invalid backup file name"
+        );
+
+        tokio::fs::read(self.backups_path().join(file_name))
+            .await
+            .context("failed to read backup")
+    }
+
+    /// Validate the backup archive `data` and stage it to replace the current
+    /// app data, plugins are stopped and the database is closed so the app
+    /// must be restarted afterwards to complete the restore
+    pub async fn restore_backup(&self, data: Vec<u8>) -> anyhow::Result<()> {
+        let manifest = extract_zip_file(
+            create_zip_reader(Cursor::new(&data)).await?,
+            BACKUP_MANIFEST,
+        )
+        .await?
+        .context("backup is missing its manifest")?;
+        let manifest: BackupManifest =
+            serde_json::from_slice(&manifest).context("backup has an invalid manifest")?;
+
+        anyhow::ensure!(
+            manifest.version <= BACKUP_VERSION,
+            "backup was created by a newer version of tilepad"
+        );
+
+        let restore_path = self.app_data_path.join(RESTORE_DIRECTORY);
+        if restore_path.exists() {
+            remove_dir_all(&restore_path)
+                .await
+                .context("failed to clear previous restore")?;
+        }
+
+        if let Err(cause) = stage_restore(&data, &restore_path).await {
+            _ = remove_dir_all(&restore_path).await;
+            return Err(cause);
+        }
+
+        tracing::info!(created_at = %manifest.created_at, "staged backup for restore");
+
+        self.plugins.stop_all_tasks().await;
+        self.db.close().await;
+
+        Ok(())
+    }
+
+    /// Background task that creates automatic backups when they are due
+    pub async fn run_scheduled_backups(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(SCHEDULE_CHECK_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let settings = self.settings.read().clone();
+            if !settings.enabled {
+                continue;
+            }
+
+            let last_backup = match self.get_backups().await {
+                Ok(backups) => backups
+                    .into_iter()
+                    .find(|backup| backup.automatic)
+                    .map(|backup| backup.created_at),
+                Err(cause) => {
+                    tracing::error!(?cause, "failed to get backups");
+                    continue;
+                }
+            };
+
+            let interval = chrono::Duration::hours(settings.interval.max(1) as i64);
+            if last_backup.is_some_and(|created_at| Utc::now() - created_at < interval) {
+                continue;
+            }
+
+            match self.create_backup(true).await {
+                Ok(backup) => {
+                    tracing::info!(file_name = %backup.file_name, "created automatic backup")
+                }
+                Err(cause) => {
+                    tracing::error!(?cause, "failed to create automatic backup");
+                    continue;
+                }
+            }
+
+            let retention = settings.retention.max(1) as usize;
+            if let Err(cause) = apply_retention(&self.backups_path(), retention).await {
+                tracing::error!(?cause, "failed to remove old automatic backups");
+            }
+        }
+    }
+}
+
+/// Get all the backups stored in `backups_path`, newest first
+async fn list_backups(backups_path: &Path) -> anyhow::Result<Vec<BackupInfo>> {
+    let mut backups = Vec::new();
+
+    if !backups_path.is_dir() {
+        return Ok(backups);
+    }
+
+    let mut entries = tokio::fs::read_dir(backups_path).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let automatic = file_name.starts_with(AUTOMATIC_BACKUP_PREFIX);
+
+        if !file_name.ends_with(".zip")
+            || !(automatic || file_name.starts_with(MANUAL_BACKUP_PREFIX))
+        {
+            continue;
+        }
+
+        let metadata = entry.metadata().await?;
+        let created_at = metadata
+            .modified()
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+
+        backups.push(BackupInfo {
+            file_name,
+            size: metadata.len(),
+            created_at,
+            automatic,
+        });
+    }
+
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(backups)
+}
+
+/// Remove the automatic backups in `backups_path` beyond the newest `retention`
+/// backups, manually created backups are never removed
+async fn apply_retention(backups_path: &Path, retention: usize) -> anyhow::Result<()> {
+    let backups = list_backups(backups_path).await?;
+
+    for backup in backups
+        .into_iter()
+        .filter(|backup| backup.automatic)
+        .skip(retention)
+    {
+        tracing::debug!(file_name = %backup.file_name, "removing old automatic backup");
+        remove_file(backups_path.join(&backup.file_name)).await?;
+    }
+
+    Ok(())
+}
+
+/// Extract the backup `data` into `restore_path` and validate its contents
+async fn stage_restore(data: &[u8], restore_path: &Path) -> anyhow::Result<()> {
+    let zip = create_zip_reader(Cursor::new(data)).await?;
+    extract_zip_limited(
+        zip,
+        restore_path,
+        MAX_BACKUP_FILES,
+        MAX_BACKUP_SIZE,
+        is_backup_entry,
+    )
+    .await?;
+    validate_restore(restore_path).await
+}
+
+/// Check if `file_name` is one of the files or within one of the
+/// directories that are stored in a backup
+fn is_backup_entry(file_name: &str) -> bool {
+    if file_name == BACKUP_MANIFEST
+        || file_name == DATABASE_FILE
+        || BACKUP_FILES.contains(&file_name)
+    {
+        return true;
+    }
+
+    let directory = match file_name.split_once('/') {
+        Some((directory, _)) => directory,
+        None => file_name,
+    };
+
+    BACKUP_DIRECTORIES.contains(&directory)
+}
+
+/// Validate the contents of a restore staged in `restore_path`
+async fn validate_restore(restore_path: &Path) -> anyhow::Result<()> {
+    let database = tokio::fs::read(restore_path.join(DATABASE_FILE))
+        .await
+        .context("backup is missing the database")?;
+    anyhow::ensure!(
+        database.starts_with(SQLITE_HEADER),
+        "backup database is not a valid sqlite database"
+    );
+
+    anyhow::ensure!(
+        restore_path.join(BACKUP_FILES[0]).is_file(),
+        "backup is missing the server key"
+    );
+
+    Ok(())
+}
+
+/// Names of the files and directories in app data that are replaced by a restore
+fn restored_entries() -> impl Iterator<Item = &'static str> {
+    std::iter::once(DATABASE_FILE)
+        .chain(DATABASE_JOURNAL_FILES.iter().copied())
+        .chain(BACKUP_FILES.iter().copied())
+        .chain(BACKUP_DIRECTORIES.iter().copied())
+}
+
+/// Apply a restore staged by [Backups::restore_backup], must be called on
+/// startup before the database and server keys are loaded.
+///
+/// The current data is moved aside before the staged data is moved in and is
+/// moved back if anything fails, including after a restore that was interrupted
+/// by the app closing. The staged restore is discarded when it cannot be applied
+/// so the error is only reported once
+pub async fn apply_pending_restore(app_data_path: &Path) -> anyhow::Result<()> {
+    let restore_path = app_data_path.join(RESTORE_DIRECTORY);
+    let previous_path = app_data_path.join(PREVIOUS_DIRECTORY);
+    let is_staged = restore_path.join(BACKUP_MANIFEST).is_file();
+
+    if previous_path.exists() {
+        if is_staged {
+            tracing::warn!("rolling back interrupted backup restore");
+
+            rollback_restore(app_data_path, &previous_path)
+                .await
+                .with_context(|| {
+                    format!(
+                        "failed to roll back interrupted backup restore, previous data is in {}",
+                        previous_path.display()
+                    )
+                })?;
+
+            _ = remove_dir_all(&restore_path).await;
+            return Err(anyhow::anyhow!(
+                "backup restore was interrupted, previous data has been kept"
+            ));
+        }
+
+        // Restore completed but the previous data was not removed
+        _ = remove_dir_all(&previous_path).await;
+    }
+
+    if !is_staged {
+        if restore_path.exists() {
+            _ = remove_dir_all(&restore_path).await;
+        }
+
+        return Ok(());
+    }
+
+    tracing::info!("applying staged backup restore");
+
+    if let Err(cause) = validate_restore(&restore_path).await {
+        _ = remove_dir_all(&restore_path).await;
+        return Err(cause.context("staged backup restore is invalid"));
+    }
+
+    create_dir_all(&previous_path)
+        .await
+        .context("failed to create directory for previous data")?;
+
+    if let Err(cause) = swap_restore(app_data_path, &restore_path, &previous_path).await {
+        if let Err(rollback_cause) = rollback_restore(app_data_path, &previous_path).await {
+            tracing::error!(?rollback_cause, "failed to roll back backup restore");
+            return Err(cause.context(format!(
+                "failed to apply backup restore, previous data is in {}",
+                previous_path.display()
+            )));
+        }
+
+        _ = remove_dir_all(&restore_path).await;
+        return Err(cause.context("failed to apply backup restore, previous data has been kept"));
+    }
+
+    // Removing the manifest marks the restore as complete
+    remove_file(restore_path.join(BACKUP_MANIFEST))
+        .await
+        .context("failed to complete backup restore")?;
+
+    for path in [&restore_path, &previous_path] {
+        if let Err(cause) = remove_dir_all(path).await {
+            tracing::warn!(?cause, ?path, "failed to clean up after backup restore");
+        }
+    }
+
+    Ok(())
+}
+
+/// Move the current data from `app_data_path` into `previous_path` then
+/// move the staged data from `restore_path` into its place
+async fn swap_restore(
+    app_data_path: &Path,
+    restore_path: &Path,
+    previous_path: &Path,
+) -> anyhow::Result<()> {
+    for name in restored_entries() {
+        let target = app_data_path.join(name);
+        if target.exists() {
+            tokio::fs::rename(&target, previous_path.join(name))
+                .await
+                .with_context(|| format!("failed to move aside {name}"))?;
+        }
+    }
+
+    tokio::fs::write(previous_path.join(PREVIOUS_MOVED_MARKER), [])
+        .await
+        .context("failed to mark previous data as moved")?;
+
+    for name in restored_entries() {
+        let staged = restore_path.join(name);
+        if staged.exists() {
+            tokio::fs::rename(&staged, app_data_path.join(name))
+                .await
+                .with_context(|| format!("failed to restore {name}"))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Move the data in `previous_path` back into `app_data_path`, replacing
+/// anything that was moved in from a staged restore
+async fn rollback_restore(app_data_path: &Path, previous_path: &Path) -> anyhow::Result<()> {
+    // Until the marker is written nothing has been moved in, entries that
+    // were not moved aside are still the current data
+    let moved = previous_path.join(PREVIOUS_MOVED_MARKER).exists();
+
+    for name in restored_entries() {
+        let target = app_data_path.join(name);
+        let previous = previous_path.join(name);
+
+        if !previous.exists() && !moved {
+            continue;
+        }
+
+        if target.is_dir() {
+            remove_dir_all(&target).await?;
+        } else if target.exists() {
+            remove_file(&target).await?;
+        }
+
+        if previous.exists() {
+            tokio::fs::rename(&previous, &target)
+                .await
+                .with_context(|| format!("failed to move back {name}"))?;
+        }
+    }
+
+    remove_dir_all(previous_path).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs::File,
+        path::Path,
+        time::{Duration, SystemTime},
+    };
+
+    use super::{apply_pending_restore, apply_retention, list_backups, stage_restore};
+    use crate::utils::zip::create_zip_bytes;
+
+    const DATABASE: &[u8] = b"SQLite format 3\0data";
+
+    fn write(path: &Path, data: &[u8]) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, data).unwrap();
+    }
+
+    fn read(path: &Path) -> Vec<u8> {
+        std::fs::read(path).unwrap()
+    }
+
+    /// Staging a backup rejects archives missing the server key or a valid database
+    /// and archives containing files that are not part of a backup
+    #[tokio::test]
+    async fn test_stage_restore_validation() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let manifest = (
+            "backup.json".to_string(),
+            br#"{"version":1,"app_version":"0.0.0","created_at":"2026-01-01T00:00:00Z"}"#.to_vec(),
+        );
+        let key = ("private_key.bin".to_string(), vec![0u8; 32]);
+
+        let valid = create_zip_bytes(vec![
+            manifest.clone(),
+            ("app.db".to_string(), DATABASE.to_vec()),
+            key.clone(),
+        ])
+        .await
+        .unwrap();
+        assert!(
+            stage_restore(&valid, &dir.path().join("valid"))
+                .await
+                .is_ok()
+        );
+
+        let missing_key = create_zip_bytes(vec![
+            manifest.clone(),
+            ("app.db".to_string(), DATABASE.to_vec()),
+        ])
+        .await
+        .unwrap();
+        assert!(
+            stage_restore(&missing_key, &dir.path().join("missing_key"))
+                .await
+                .is_err()
+        );
+
+        let invalid_database = create_zip_bytes(vec![
+            manifest.clone(),
+            ("app.db".to_string(), b"not a database".to_vec()),
+            key.clone(),
+        ])
+        .await
+        .unwrap();
+        assert!(
+            stage_restore(&invalid_database, &dir.path().join("invalid_database"))
+                .await
+                .is_err()
+        );
+
+        let unknown_file = create_zip_bytes(vec![
+            manifest,
+            ("app.db".to_string(), DATABASE.to_vec()),
+            key,
+            ("../outside.txt".to_string(), b"data".to_vec()),
+        ])
+        .await
+        .unwrap();
+        assert!(
+            stage_restore(&unknown_file, &dir.path().join("unknown_file"))
+                .await
+                .is_err()
+        );
+    }
+
+    /// Staged data replaces the current data and the staging directories are removed
+    #[tokio::test]
+    async fn test_apply_pending_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_data = dir.path();
+
+        write(&app_data.join("app.db"), b"old");
+        write(&app_data.join("app.db-wal"), b"old");
+        write(&app_data.join("private_key.bin"), b"old");
+        write(&app_data.join("plugins/old/manifest.json"), b"old");
+        write(&app_data.join("logs/app.log"), b"kept");
+
+        write(&app_data.join("restore/backup.json"), b"{}");
+        write(&app_data.join("restore/app.db"), DATABASE);
+        write(&app_data.join("restore/private_key.bin"), b"new");
+        write(&app_data.join("restore/plugins/new/manifest.json"), b"new");
+
+        apply_pending_restore(app_data).await.unwrap();
+
+        assert_eq!(read(&app_data.join("app.db")), DATABASE);
+        assert_eq!(read(&app_data.join("private_key.bin")), b"new");
+        assert!(app_data.join("plugins/new/manifest.json").is_file());
+        assert!(!app_data.join("plugins/old").exists());
+        assert!(!app_data.join("app.db-wal").exists());
+        assert_eq!(read(&app_data.join("logs/app.log")), b"kept");
+        assert!(!app_data.join("restore").exists());
+        assert!(!app_data.join("restore-previous").exists());
+
+        // Nothing happens without a staged restore
+        apply_pending_restore(app_data).await.unwrap();
+        assert_eq!(read(&app_data.join("app.db")), DATABASE);
+    }
+
+    /// Invalid staged restores are discarded without touching the current data
+    #[tokio::test]
+    async fn test_apply_pending_restore_invalid() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_data = dir.path();
+
+        write(&app_data.join("app.db"), b"old");
+        write(&app_data.join("restore/backup.json"), b"{}");
+        write(&app_data.join("restore/app.db"), b"not a database");
+
+        assert!(apply_pending_restore(app_data).await.is_err());
+        assert_eq!(read(&app_data.join("app.db")), b"old");
+        assert!(!app_data.join("restore").exists());
+    }
+
+    /// A restore interrupted part way is rolled back to the previous data
+    #[tokio::test]
+    async fn test_apply_pending_restore_interrupted() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_data = dir.path();
+
+        // Current data was moved aside and some of the staged data moved in
+        write(&app_data.join("restore-previous/app.db"), b"old");
+        write(
+            &app_data.join("restore-previous/plugins/old/manifest.json"),
+            b"old",
+        );
+        write(&app_data.join("restore-previous/.moved"), b"");
+        write(&app_data.join("app.db"), DATABASE);
+        write(&app_data.join("private_key.bin"), b"new");
+        write(&app_data.join("restore/backup.json"), b"{}");
+        write(&app_data.join("restore/plugins/new/manifest.json"), b"new");
+
+        assert!(apply_pending_restore(app_data).await.is_err());
+
+        assert_eq!(read(&app_data.join("app.db")), b"old");
+        assert!(app_data.join("plugins/old/manifest.json").is_file());
+        assert!(!app_data.join("private_key.bin").exists());
+        assert!(!app_data.join("restore").exists());
+        assert!(!app_data.join("restore-previous").exists());
+    }
+
+    /// Only automatic backups beyond the retention count are removed, oldest first
+    #[tokio::test]
+    async fn test_apply_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+
+        let files = [
+            "tilepad-auto-backup-1.zip",
+            "tilepad-auto-backup-2.zip",
+            "tilepad-backup-3.zip",
+            "tilepad-auto-backup-4.zip",
+        ];
+        for (index, name) in files.iter().enumerate() {
+            let path = dir.path().join(name);
+            write(&path, b"backup");
+
+            let age = Duration::from_secs(60 * (files.len() - index) as u64);
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(now - age)
+                .unwrap();
+        }
+
+        apply_retention(dir.path(), 1).await.unwrap();
+
+        let mut remaining: Vec<String> = list_backups(dir.path())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|backup| backup.file_name)
+            .collect();
+        remaining.sort();
+
+        assert_eq!(
+            remaining,
+            vec!["tilepad-auto-backup-4.zip", "tilepad-backup-3.zip"]
+        );
+    }
+}
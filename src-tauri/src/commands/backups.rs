@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use tauri::{AppHandle, State, ipc::InvokeResponseBody};
+
+use crate::{
+    backup::{BackupInfo, Backups},
+    commands::CmdResult,
+};
+
+/// Get a list of all stored backups
+#[tauri::command]
+pub async fn backups_get_backups(backups: State<'_, Arc<Backups>>) -> CmdResult<Vec<BackupInfo>> {
+    let backups = backups.get_backups().await?;
+    Ok(backups)
+}
+
+/// Create a new backup of the app data
+#[tauri::command]
+pub async fn backups_create_backup(backups: State<'_, Arc<Backups>>) -> CmdResult<BackupInfo> {
+    let backup = backups.create_backup(false).await?;
+    Ok(backup)
+}
+
+/// Read the contents of a stored backup
+#[tauri::command]
+pub async fn backups_download_backup(
+    backups: State<'_, Arc<Backups>>,
+    file_name: String,
+) -> CmdResult<tauri::ipc::Response> {
+    let data = backups.read_backup(&file_name).await?;
+    Ok(tauri::ipc::Response::new(InvokeResponseBody::Raw(data)))
+}
+
+/// Restore the app data from a backup archive and restart the app
+#[tauri::command]
+pub async fn backups_restore_backup(
+    app: AppHandle,
+    backups: State<'_, Arc<Backups>>,
+    data: Vec<u8>,
+) -> CmdResult<()> {
+    backups.restore_backup(data).await?;
+    app.restart();
+}
+
+/// Restore the app data from a stored backup and restart the app
+#[tauri::command]
+pub async fn backups_restore_stored_backup(
+    app: AppHandle,
+    backups: State<'_, Arc<Backups>>,
+    file_name: String,
+) -> CmdResult<()> {
+    let data = backups.read_backup(&file_name).await?;
+    backups.restore_backup(data).await?;
+    app.restart();
+}
@@ -1,17 +1,26 @@
 use std::sync::Arc;
 
+use anyhow::Context;
+use chrono::TimeDelta;
 use tauri::State;
 
 use crate::{
     database::{
         DbPool,
         entity::{
-            device::{DeviceId, DeviceModel},
-            folder::FolderId,
+            auto_approve_rule::{
+                AutoApproveRuleConfig, AutoApproveRuleId, AutoApproveRuleModel,
+                CreateAutoApproveRule, UpdateAutoApproveRule,
+            },
+            device::{DeviceConfig, DeviceId, DeviceModel},
+            device_auto_approval::DeviceAutoApprovalModel,
+            device_event::{DeviceEventModel, DeviceEventPage, DeviceEventQuery},
+            device_group::{CreateDeviceGroup, DeviceGroupId, DeviceGroupModel, UpdateDeviceGroup},
+            folder::{FolderId, FolderModel},
             profile::ProfileId,
         },
     },
-    device::{ConnectedDevice, DeviceRequest, DeviceRequestId, Devices},
+    device::{ConnectedDevice, DeviceRequest, DeviceRequestId, Devices, ServerKeyInfo},
 };
 
 use super::CmdResult;
@@ -29,6 +38,28 @@ pub async fn devices_get_devices(db: State<'_, DbPool>) -> CmdResult<Vec<DeviceM
     Ok(devices)
 }
 
+/// Update the order of the devices, `device_ids` must contain
+/// every device in the new order
+#[tauri::command]
+pub async fn devices_reorder_devices(
+    db: State<'_, DbPool>,
+    device_ids: Vec<DeviceId>,
+) -> CmdResult<Vec<DeviceModel>> {
+    let db = db.inner();
+    let devices = DeviceModel::all(db).await?;
+
+    let is_complete = device_ids.len() == devices.len()
+        && devices.iter().all(|device| device_ids.contains(&device.id));
+    if !is_complete {
+        return Err(anyhow::anyhow!("device order must contain every device").into());
+    }
+
+    DeviceModel::reorder(db, &device_ids).await?;
+
+    let devices = DeviceModel::all(db).await?;
+    Ok(devices)
+}
+
 /// Deny a specific device request
 #[tauri::command]
 pub fn devices_get_connected_devices(devices: State<'_, Arc<Devices>>) -> Vec<ConnectedDevice> {
@@ -86,3 +117,198 @@ pub async fn devices_set_device_folder(
     devices.update_device_folder(device_id, folder_id).await?;
     Ok(())
 }
+
+/// Set the configuration for a device (i.e which profiles and
+/// folders the device is allowed to access)
+#[tauri::command]
+pub async fn devices_set_device_config(
+    device_id: DeviceId,
+    config: DeviceConfig,
+    devices: State<'_, Arc<Devices>>,
+) -> CmdResult<DeviceModel> {
+    let device = devices.update_device_config(device_id, config).await?;
+    Ok(device)
+}
+
+/// Get whether pairing is open for unknown devices
+#[tauri::command]
+pub fn devices_get_pairing_open(devices: State<'_, Arc<Devices>>) -> bool {
+    devices.is_pairing_open()
+}
+
+/// Set whether pairing is open for unknown devices
+#[tauri::command]
+pub fn devices_set_pairing_open(open: bool, devices: State<'_, Arc<Devices>>) {
+    devices.set_pairing_open(open);
+}
+
+/// Get details about the current server keys
+#[tauri::command]
+pub fn devices_get_server_key(devices: State<'_, Arc<Devices>>) -> ServerKeyInfo {
+    devices.get_server_key_info()
+}
+
+/// Rotate the server key, the previous key remains valid for
+/// `transition_hours` to allow devices to pin the new key. Fails
+/// while the key from an earlier rotation has not been retired
+#[tauri::command]
+pub fn devices_rotate_server_key(
+    transition_hours: u32,
+    devices: State<'_, Arc<Devices>>,
+) -> CmdResult<ServerKeyInfo> {
+    let info = devices.rotate_server_key(TimeDelta::hours(transition_hours as i64))?;
+    Ok(info)
+}
+
+/// Get all the device auto approve rules
+#[tauri::command]
+pub async fn devices_get_auto_approve_rules(
+    db: State<'_, DbPool>,
+) -> CmdResult<Vec<AutoApproveRuleModel>> {
+    let rules = AutoApproveRuleModel::all(db.inner()).await?;
+    Ok(rules)
+}
+
+/// Create a new device auto approve rule
+#[tauri::command]
+pub async fn devices_create_auto_approve_rule(
+    db: State<'_, DbPool>,
+    create: CreateAutoApproveRule,
+) -> CmdResult<AutoApproveRuleModel> {
+    let db = db.inner();
+    validate_auto_approve_rule(db, &create.config, create.profile_id, create.folder_id).await?;
+
+    let rule = AutoApproveRuleModel::create(db, create).await?;
+    Ok(rule)
+}
+
+/// Update an existing device auto approve rule
+#[tauri::command]
+pub async fn devices_update_auto_approve_rule(
+    db: State<'_, DbPool>,
+    rule_id: AutoApproveRuleId,
+    update: UpdateAutoApproveRule,
+) -> CmdResult<AutoApproveRuleModel> {
+    let db = db.inner();
+    validate_auto_approve_rule(db, &update.config, update.profile_id, update.folder_id).await?;
+
+    let rule = AutoApproveRuleModel::get_by_id(db, rule_id)
+        .await?
+        .context("unknown rule")?;
+    let rule = rule.update(db, update).await?;
+    Ok(rule)
+}
+
+/// Delete a device auto approve rule
+#[tauri::command]
+pub async fn devices_delete_auto_approve_rule(
+    db: State<'_, DbPool>,
+    rule_id: AutoApproveRuleId,
+) -> CmdResult<()> {
+    AutoApproveRuleModel::delete(db.inner(), rule_id).await?;
+    Ok(())
+}
+
+/// Get the audit trail of devices that were automatically approved
+#[tauri::command]
+pub async fn devices_get_auto_approvals(
+    db: State<'_, DbPool>,
+) -> CmdResult<Vec<DeviceAutoApprovalModel>> {
+    let approvals = DeviceAutoApprovalModel::all(db.inner()).await?;
+    Ok(approvals)
+}
+
+/// Ensures the rule conditions are valid and the folder for the rule is
+/// a folder within the rule profile
+async fn validate_auto_approve_rule(
+    db: &DbPool,
+    config: &AutoApproveRuleConfig,
+    profile_id: ProfileId,
+    folder_id: FolderId,
+) -> anyhow::Result<()> {
+    config.validate()?;
+
+    let folder = FolderModel::get_by_id(db, folder_id)
+        .await?
+        .context("unknown folder")?;
+
+    anyhow::ensure!(
+        folder.profile_id == profile_id,
+        "folder is not within the profile"
+    );
+
+    Ok(())
+}
+
+/// Query a page from the device audit log
+#[tauri::command]
+pub async fn devices_get_device_events(
+    db: State<'_, DbPool>,
+    query: DeviceEventQuery,
+) -> CmdResult<DeviceEventPage> {
+    let page = DeviceEventModel::query(db.inner(), query).await?;
+    Ok(page)
+}
+
+/// Get all the device groups
+#[tauri::command]
+pub async fn devices_get_device_groups(db: State<'_, DbPool>) -> CmdResult<Vec<DeviceGroupModel>> {
+    let groups = DeviceGroupModel::all(db.inner()).await?;
+    Ok(groups)
+}
+
+/// Create a new device group
+#[tauri::command]
+pub async fn devices_create_device_group(
+    db: State<'_, DbPool>,
+    create: CreateDeviceGroup,
+) -> CmdResult<DeviceGroupModel> {
+    let group = DeviceGroupModel::create(db.inner(), create).await?;
+    Ok(group)
+}
+
+/// Update an existing device group
+#[tauri::command]
+pub async fn devices_update_device_group(
+    db: State<'_, DbPool>,
+    group_id: DeviceGroupId,
+    update: UpdateDeviceGroup,
+) -> CmdResult<DeviceGroupModel> {
+    let db = db.inner();
+    let group = DeviceGroupModel::get_by_id(db, group_id)
+        .await?
+        .context("unknown device group")?;
+    let group = group.update(db, update).await?;
+    Ok(group)
+}
+
+/// Delete a device group, members are removed from the group
+#[tauri::command]
+pub async fn devices_delete_device_group(
+    db: State<'_, DbPool>,
+    group_id: DeviceGroupId,
+) -> CmdResult<()> {
+    DeviceGroupModel::delete(db.inner(), group_id).await?;
+    Ok(())
+}
+
+/// Get the IDs of the devices within a group
+#[tauri::command]
+pub async fn devices_get_device_group_members(
+    db: State<'_, DbPool>,
+    group_id: DeviceGroupId,
+) -> CmdResult<Vec<DeviceId>> {
+    let members = DeviceGroupModel::get_members(db.inner(), group_id).await?;
+    Ok(members)
+}
+
+/// Set the group a device is a member of, [None] removes the device from its group
+#[tauri::command]
+pub async fn devices_set_device_group(
+    db: State<'_, DbPool>,
+    device_id: DeviceId,
+    group_id: Option<DeviceGroupId>,
+) -> CmdResult<()> {
+    DeviceGroupModel::set_device_group(db.inner(), device_id, group_id).await?;
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
 use anyhow::Context;
 use tauri::State;
@@ -11,9 +11,12 @@ use crate::{
             device::DeviceModel,
             folder::{CreateFolder, FolderConfig, FolderId, FolderModel},
             profile::ProfileId,
+            tile::TileModel,
         },
     },
-    device::Devices,
+    device::{Devices, get_relocation_folders},
+    history::{History, HistoryChange},
+    tile::Tiles,
 };
 
 /// Get all folders for the specified profile
@@ -42,7 +45,7 @@ pub async fn folders_create_folder(
     db: State<'_, DbPool>,
     create: CreateFolder,
 ) -> CmdResult<FolderModel> {
-    let folder = FolderModel::create(&db, create).await?;
+    let folder = FolderModel::create(db.inner(), create).await?;
     Ok(folder)
 }
 
@@ -51,33 +54,112 @@ pub async fn folders_create_folder(
 pub async fn folders_set_name(
     db: State<'_, DbPool>,
     devices: State<'_, Arc<Devices>>,
+    history: State<'_, Arc<History>>,
     folder_id: FolderId,
     name: String,
 ) -> CmdResult<FolderModel> {
-    let folder = FolderModel::get_by_id(db.inner(), folder_id)
+    let before = FolderModel::get_by_id(db.inner(), folder_id)
         .await?
         .context("unknown folder")?;
-    let folder = folder.set_name(&db, name).await?;
+    let folder = before.clone().set_name(&db, name).await?;
 
     devices.background_update_folder(folder.id);
+    history.record_folder(before, folder.clone()).await;
 
     Ok(folder)
 }
 
-/// Update a specific folder
+/// Update a specific folder, when `reflow` is enabled tiles that would
+/// be outside of the resized grid are moved into free space
 #[tauri::command]
 pub async fn folders_set_config(
     db: State<'_, DbPool>,
-    devices: State<'_, Arc<Devices>>,
+    tiles: State<'_, Arc<Tiles>>,
+    history: State<'_, Arc<History>>,
     folder_id: FolderId,
     config: FolderConfig,
+    reflow: bool,
 ) -> CmdResult<FolderModel> {
-    let folder = FolderModel::get_by_id(db.inner(), folder_id)
+    let db = db.inner();
+    let before = FolderModel::get_by_id(db, folder_id)
         .await?
         .context("unknown folder")?;
-    let folder = folder.set_config(&db, config).await?;
+    let tiles_before = TileModel::get_by_folder(db, folder_id).await?;
+
+    let folder = tiles
+        .update_folder_config(folder_id, config, reflow)
+        .await?;
+
+    // Tiles relocated by the reflow are undone along with the config
+    let mut changes = vec![HistoryChange::Folder {
+        before,
+        after: folder.clone(),
+    }];
+    for after in TileModel::get_by_folder(db, folder_id).await? {
+        if let Some(before) = tiles_before.iter().find(|tile| tile.id == after.id)
+            && before.position != after.position
+        {
+            changes.push(HistoryChange::tile(Some(before.clone()), Some(after)));
+        }
+    }
+    history.record(changes).await;
 
-    devices.background_update_folder(folder.id);
+    Ok(folder)
+}
+
+/// Get the tiles that would be outside of a folder if it was resized
+#[tauri::command]
+pub async fn folders_get_resize_conflicts(
+    tiles: State<'_, Arc<Tiles>>,
+    folder_id: FolderId,
+    rows: u32,
+    columns: u32,
+    pages: u32,
+) -> CmdResult<Vec<TileModel>> {
+    let tiles = tiles
+        .get_resize_conflicts(folder_id, rows, columns, pages)
+        .await?;
+
+    Ok(tiles)
+}
+
+/// Update the order of the folders within a profile, `folder_ids` must
+/// contain every folder of the profile in the new order
+#[tauri::command]
+pub async fn folders_reorder_folders(
+    db: State<'_, DbPool>,
+    profile_id: ProfileId,
+    folder_ids: Vec<FolderId>,
+) -> CmdResult<Vec<FolderModel>> {
+    let db = db.inner();
+    let folders = FolderModel::all(db, profile_id).await?;
+
+    let is_complete = folder_ids.len() == folders.len()
+        && folders.iter().all(|folder| folder_ids.contains(&folder.id));
+    if !is_complete {
+        return Err(
+            anyhow::anyhow!("folder order must contain every folder of the profile").into(),
+        );
+    }
+
+    FolderModel::reorder(db, profile_id, &folder_ids).await?;
+
+    let folders = FolderModel::all(db, profile_id).await?;
+    Ok(folders)
+}
+
+/// Set a folder as the default folder of its profile
+#[tauri::command]
+pub async fn folders_set_default(
+    db: State<'_, DbPool>,
+    folder_id: FolderId,
+) -> CmdResult<FolderModel> {
+    let db = db.inner();
+    let mut folder = FolderModel::get_by_id(db, folder_id)
+        .await?
+        .context("unknown folder")?;
+
+    folder.set_default(db).await?;
 
     Ok(folder)
 }
@@ -100,24 +182,32 @@ pub async fn folders_delete_folder(
         return Err(anyhow::anyhow!("cannot delete default folder").into());
     }
 
-    // Get the default folder for that profile
-    let default_folder = FolderModel::get_default(db, folder.profile_id)
-        .await?
-        .context("default folder is missing")?;
-
-    // First all devices using the folder must be updated to use the default folder
-    let folder_devices = DeviceModel::all_by_folder(db, folder_id).await?;
-    for device in folder_devices {
+    // Devices using the folder are moved to another folder they are allowed
+    // to access in the same transaction as the folder is deleted
+    let relocations = get_relocation_folders(
+        db,
+        DeviceModel::all_by_folder(db, folder_id).await?,
+        folder.profile_id,
+        None,
+        Some(folder_id),
+    )
+    .await?;
+
+    let mut tx = db.begin().await?;
+    let mut moved_folder_ids = HashSet::new();
+    for (device, folder) in relocations {
         device
-            .set_profile(db, default_folder.profile_id, default_folder.id)
+            .set_profile(&mut *tx, folder.profile_id, folder.id)
             .await?;
+        moved_folder_ids.insert(folder.id);
     }
+    FolderModel::delete(&mut *tx, folder_id).await?;
+    tx.commit().await?;
 
     // Update the actual device sessions
-    devices.update_folder_devices(default_folder.id).await?;
-
-    // Delete the folder itself
-    FolderModel::delete(db, folder_id).await?;
+    for folder_id in moved_folder_ids {
+        devices.update_folder_devices(folder_id).await?;
+    }
 
     Ok(())
 }
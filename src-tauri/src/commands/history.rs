@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use tauri::State;
+
+use crate::{
+    commands::CmdResult,
+    history::{History, HistoryStatus},
+};
+
+/// Get whether there are changes to undo or redo
+#[tauri::command]
+pub fn history_get_status(history: State<'_, Arc<History>>) -> HistoryStatus {
+    history.status()
+}
+
+/// Undo the most recent change
+#[tauri::command]
+pub async fn history_undo(history: State<'_, Arc<History>>) -> CmdResult<HistoryStatus> {
+    let status = history.undo().await?;
+    Ok(status)
+}
+
+/// Redo the most recently undone change
+#[tauri::command]
+pub async fn history_redo(history: State<'_, Arc<History>>) -> CmdResult<HistoryStatus> {
+    let status = history.redo().await?;
+    Ok(status)
+}
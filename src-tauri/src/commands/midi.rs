@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use tauri::State;
+
+use crate::{
+    database::{
+        DbPool,
+        entity::midi_binding::{CreateMidiBinding, MidiBindingId, MidiBindingModel},
+    },
+    midi::Midi,
+};
+
+use super::CmdResult;
+
+/// Get the names of the available MIDI input ports
+#[tauri::command]
+pub fn midi_get_input_ports(midi: State<'_, Arc<Midi>>) -> CmdResult<Vec<String>> {
+    let ports = midi.get_input_ports()?;
+    Ok(ports)
+}
+
+/// Get all MIDI bindings
+#[tauri::command]
+pub async fn midi_get_bindings(db: State<'_, DbPool>) -> CmdResult<Vec<MidiBindingModel>> {
+    let bindings = MidiBindingModel::all(db.inner()).await?;
+    Ok(bindings)
+}
+
+/// Bind a MIDI message to a tile
+#[tauri::command]
+pub async fn midi_create_binding(
+    db: State<'_, DbPool>,
+    midi: State<'_, Arc<Midi>>,
+    create: CreateMidiBinding,
+) -> CmdResult<MidiBindingModel> {
+    let binding = MidiBindingModel::create(db.inner(), create).await?;
+    midi.reload_bindings().await?;
+    Ok(binding)
+}
+
+/// Remove a MIDI binding
+#[tauri::command]
+pub async fn midi_delete_binding(
+    db: State<'_, DbPool>,
+    midi: State<'_, Arc<Midi>>,
+    binding_id: MidiBindingId,
+) -> CmdResult<()> {
+    MidiBindingModel::delete(db.inner(), binding_id).await?;
+    midi.reload_bindings().await?;
+    Ok(())
+}
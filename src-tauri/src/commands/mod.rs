@@ -1,15 +1,19 @@
 use serde::Serialize;
 
 pub mod actions;
+pub mod backups;
 pub mod devices;
 pub mod folders;
 pub mod fonts;
+pub mod history;
 pub mod icons;
+pub mod midi;
 pub mod plugins;
 pub mod profiles;
 pub mod server;
 pub mod settings;
 pub mod tiles;
+pub mod trash;
 
 type CmdResult<T> = Result<T, CmdError>;
 
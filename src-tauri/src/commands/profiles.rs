@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
 use crate::{
     commands::CmdResult,
@@ -10,10 +10,15 @@ use crate::{
             profile::{CreateProfile, ProfileId, ProfileModel},
         },
     },
-    device::Devices,
+    device::{Devices, get_relocation_folders},
+    history::History,
+    icons::Icons,
+    plugin::Plugins,
+    profile::archive::{ProfileImportResult, export_profile, import_profile},
+    tile::Tiles,
 };
 use anyhow::Context;
-use tauri::State;
+use tauri::{State, ipc::InvokeResponseBody};
 
 /// Get a list of all profiles
 #[tauri::command]
@@ -43,19 +48,76 @@ pub async fn profiles_create_profile(
     Ok(profile)
 }
 
+/// Create a copy of a profile including all of its folders and tiles
+#[tauri::command]
+pub async fn profiles_copy_profile(
+    tiles: State<'_, Arc<Tiles>>,
+    profile_id: ProfileId,
+    name: String,
+) -> CmdResult<ProfileModel> {
+    let profile = tiles.copy_profile(profile_id, name).await?;
+    Ok(profile)
+}
+
 /// Update a specific profiles name
 #[tauri::command]
 pub async fn profiles_set_name(
     db: State<'_, DbPool>,
+    history: State<'_, Arc<History>>,
     profile_id: ProfileId,
     name: String,
+) -> CmdResult<ProfileModel> {
+    let db = db.inner();
+    let before = ProfileModel::get_by_id(db, profile_id)
+        .await?
+        .context("unknown profile")?;
+
+    let profile = before.clone().set_name(db, name).await?;
+    history.record_profile(before, profile.clone()).await;
+    Ok(profile)
+}
+
+/// Update the order of the profiles, `profile_ids` must contain
+/// every profile in the new order
+#[tauri::command]
+pub async fn profiles_reorder_profiles(
+    db: State<'_, DbPool>,
+    profile_ids: Vec<ProfileId>,
+) -> CmdResult<Vec<ProfileModel>> {
+    let db = db.inner();
+    let profiles = ProfileModel::all(db).await?;
+
+    let is_complete = profile_ids.len() == profiles.len()
+        && profiles
+            .iter()
+            .all(|profile| profile_ids.contains(&profile.id));
+    if !is_complete {
+        return Err(anyhow::anyhow!("profile order must contain every profile").into());
+    }
+
+    ProfileModel::reorder(db, &profile_ids).await?;
+
+    let profiles = ProfileModel::all(db).await?;
+    Ok(profiles)
+}
+
+/// Set a profile as the default profile
+#[tauri::command]
+pub async fn profiles_set_default(
+    db: State<'_, DbPool>,
+    profile_id: ProfileId,
 ) -> CmdResult<ProfileModel> {
     let db = db.inner();
     let profile = ProfileModel::get_by_id(db, profile_id)
         .await?
         .context("unknown profile")?;
 
-    let profile = profile.set_name(db, name).await?;
+    // Devices fall back to the default folder of the default profile
+    FolderModel::get_default(db, profile.id)
+        .await?
+        .context("profile is missing a default folder")?;
+
+    let profile = profile.set_default(db).await?;
     Ok(profile)
 }
 
@@ -73,23 +135,60 @@ pub async fn profiles_delete_profile(
         .await?
         .context("default profile is missing")?;
 
-    let default_folder = FolderModel::get_default(db, default_profile.id)
-        .await?
-        .context("default folder is missing")?;
+    if default_profile.id == profile_id {
+        return Err(anyhow::anyhow!("cannot delete default profile").into());
+    }
+
+    // Devices using the profile are moved to another folder they are allowed
+    // to access in the same transaction as the profile is deleted
+    let relocations = get_relocation_folders(
+        db,
+        DeviceModel::all_by_profile(db, profile_id).await?,
+        default_profile.id,
+        Some(profile_id),
+        None,
+    )
+    .await?;
 
-    // First all devices using the profile must be updated to use the default profile
-    let profile_devices = DeviceModel::all_by_profile(db, profile_id).await?;
-    for device in profile_devices {
+    let mut tx = db.begin().await?;
+    let mut moved_folder_ids = HashSet::new();
+    for (device, folder) in relocations {
         device
-            .set_profile(db, default_profile.id, default_folder.id)
+            .set_profile(&mut *tx, folder.profile_id, folder.id)
             .await?;
+        moved_folder_ids.insert(folder.id);
     }
+    ProfileModel::delete(&mut *tx, profile_id).await?;
+    tx.commit().await?;
 
     // Update the actual device sessions
-    devices.update_folder_devices(default_folder.id).await?;
-
-    // Delete the profile itself
-    ProfileModel::delete(db, profile_id).await?;
+    for folder_id in moved_folder_ids {
+        devices.update_folder_devices(folder_id).await?;
+    }
 
     Ok(())
 }
+
+/// Export a profile to a zip archive
+#[tauri::command]
+pub async fn profiles_export_profile(
+    db: State<'_, DbPool>,
+    plugins: State<'_, Arc<Plugins>>,
+    icons: State<'_, Arc<Icons>>,
+    profile_id: ProfileId,
+) -> CmdResult<tauri::ipc::Response> {
+    let data = export_profile(db.inner(), &plugins, &icons, profile_id).await?;
+    Ok(tauri::ipc::Response::new(InvokeResponseBody::Raw(data)))
+}
+
+/// Import a profile from a zip archive
+#[tauri::command]
+pub async fn profiles_import_profile(
+    db: State<'_, DbPool>,
+    plugins: State<'_, Arc<Plugins>>,
+    icons: State<'_, Arc<Icons>>,
+    data: Vec<u8>,
+) -> CmdResult<ProfileImportResult> {
+    let result = import_profile(db.inner(), &plugins, &icons, &data).await?;
+    Ok(result)
+}
@@ -1,8 +1,15 @@
+use std::sync::Arc;
+
 use tauri::State;
 
-use crate::database::{
-    DbPool,
-    entity::settings::{SettingsConfig, SettingsModel},
+use crate::{
+    backup::Backups,
+    database::{
+        DbPool,
+        entity::settings::{SettingsConfig, SettingsModel},
+    },
+    device::Devices,
+    trash::Trash,
 };
 
 use super::CmdResult;
@@ -18,9 +25,19 @@ pub async fn settings_get_settings(db: State<'_, DbPool>) -> CmdResult<SettingsC
 #[tauri::command]
 pub async fn settings_set_settings(
     db: State<'_, DbPool>,
+    devices: State<'_, Arc<Devices>>,
+    backups: State<'_, Arc<Backups>>,
+    trash: State<'_, Arc<Trash>>,
     settings: SettingsConfig,
 ) -> CmdResult<SettingsConfig> {
     let model = SettingsModel::get_or_default(db.inner()).await?;
     let model = model.update(db.inner(), settings).await?;
+
+    devices.set_pairing_settings(model.config.pairing.clone());
+    devices.set_keep_alive_settings(model.config.keep_alive.clone());
+    devices.set_serial_settings(model.config.serial.clone());
+    backups.set_settings(model.config.backup.clone());
+    trash.set_settings(model.config.trash.clone());
+
     Ok(model.config)
 }
@@ -10,12 +10,13 @@ use crate::{
         entity::{
             folder::FolderId,
             tile::{
-                CreateTile, TileIcon, TileIconOptions, TileId, TileLabel, TileModel, TilePosition,
-                UpdateKind,
+                CreateTile, TileControl, TileIcon, TileIconOptions, TileId, TileLabel, TileModel,
+                TilePosition, TileState, UpdateKind,
             },
         },
     },
     device::Devices,
+    history::{History, HistoryChange},
     tile::Tiles,
 };
 
@@ -42,50 +43,170 @@ pub async fn tiles_get_tile(
 /// Create a new tile
 #[tauri::command]
 pub async fn tiles_create_tile(
-    db: State<'_, DbPool>,
-    devices: State<'_, Arc<Devices>>,
+    tiles: State<'_, Arc<Tiles>>,
+    history: State<'_, Arc<History>>,
     create: CreateTile,
 ) -> CmdResult<TileModel> {
-    let db = db.inner();
-    let tile = TileModel::create(db, create).await?;
-    devices.background_update_folder(tile.folder_id);
+    let tile = tiles.create_tile(create).await?;
+    history.record_tile(None, Some(tile.clone())).await;
+    Ok(tile)
+}
+
+/// Duplicate a tile within its folder
+#[tauri::command]
+pub async fn tiles_duplicate_tile(
+    tiles: State<'_, Arc<Tiles>>,
+    history: State<'_, Arc<History>>,
+    tile_id: TileId,
+) -> CmdResult<TileModel> {
+    let tile = tiles.duplicate_tile(tile_id).await?;
+    history.record_tile(None, Some(tile.clone())).await;
+    Ok(tile)
+}
+
+/// Copy a selection of tiles into a folder
+#[tauri::command]
+pub async fn tiles_copy_tiles(
+    tiles: State<'_, Arc<Tiles>>,
+    history: State<'_, Arc<History>>,
+    tile_ids: Vec<TileId>,
+    folder_id: FolderId,
+) -> CmdResult<Vec<TileModel>> {
+    let tiles = tiles.copy_tiles(tile_ids, folder_id).await?;
+    history
+        .record(
+            tiles
+                .iter()
+                .map(|tile| HistoryChange::tile(None, Some(tile.clone())))
+                .collect(),
+        )
+        .await;
+    Ok(tiles)
+}
+
+/// Move a tile into another folder
+#[tauri::command]
+pub async fn tiles_move_tile(
+    db: State<'_, DbPool>,
+    tiles: State<'_, Arc<Tiles>>,
+    history: State<'_, Arc<History>>,
+    tile_id: TileId,
+    folder_id: FolderId,
+) -> CmdResult<TileModel> {
+    let before = TileModel::get_by_id(db.inner(), tile_id).await?;
+    let tile = tiles.move_tile(tile_id, folder_id).await?;
+    history.record_tile(before, Some(tile.clone())).await;
     Ok(tile)
 }
 
 /// Update a specific tile properties
 #[tauri::command]
 pub async fn tiles_update_tile_properties(
+    db: State<'_, DbPool>,
     tiles: State<'_, Arc<Tiles>>,
+    history: State<'_, Arc<History>>,
     tile_id: TileId,
     properties: JsonObject,
     partial: bool,
 ) -> CmdResult<TileModel> {
+    let before = TileModel::get_by_id(db.inner(), tile_id).await?;
     let tile = tiles
         .update_tile_properties(tile_id, None, properties, partial)
         .await?;
+    history.record_tile(before, Some(tile.clone())).await;
     Ok(tile)
 }
 
 /// Update a specific tile label
 #[tauri::command]
 pub async fn tiles_update_tile_label(
+    db: State<'_, DbPool>,
     tiles: State<'_, Arc<Tiles>>,
+    history: State<'_, Arc<History>>,
     tile_id: TileId,
     label: TileLabel,
     kind: UpdateKind,
 ) -> CmdResult<TileModel> {
+    let before = TileModel::get_by_id(db.inner(), tile_id).await?;
     let tile = tiles.update_tile_label(tile_id, None, label, kind).await?;
+    history.record_tile(before, Some(tile.clone())).await;
+
+    Ok(tile)
+}
+
+/// Update the kind of control for a tile
+#[tauri::command]
+pub async fn tiles_update_tile_control(
+    db: State<'_, DbPool>,
+    tiles: State<'_, Arc<Tiles>>,
+    history: State<'_, Arc<History>>,
+    tile_id: TileId,
+    control: TileControl,
+) -> CmdResult<TileModel> {
+    let before = TileModel::get_by_id(db.inner(), tile_id).await?;
+    let tile = tiles.update_tile_control(tile_id, None, control).await?;
+    history.record_tile(before, Some(tile.clone())).await;
+
+    Ok(tile)
+}
+
+/// Update the states of a multi-state tile
+#[tauri::command]
+pub async fn tiles_update_tile_states(
+    db: State<'_, DbPool>,
+    tiles: State<'_, Arc<Tiles>>,
+    history: State<'_, Arc<History>>,
+    tile_id: TileId,
+    states: Vec<TileState>,
+    cycle_states: bool,
+) -> CmdResult<TileModel> {
+    let before = TileModel::get_by_id(db.inner(), tile_id).await?;
+    let tile = tiles
+        .update_tile_states(tile_id, None, states, cycle_states)
+        .await?;
+    history.record_tile(before, Some(tile.clone())).await;
 
     Ok(tile)
 }
+
+/// Set the current state of a multi-state tile, the state is not recorded
+/// in the history as undo keeps the current state of tiles
+#[tauri::command]
+pub async fn tiles_update_tile_state(
+    tiles: State<'_, Arc<Tiles>>,
+    tile_id: TileId,
+    state: u32,
+) -> CmdResult<TileModel> {
+    let tile = tiles.update_tile_state(tile_id, None, state).await?;
+
+    Ok(tile)
+}
+
+/// Set the current value of a continuous control tile, the value is not
+/// recorded in the history as undo keeps the current value of tiles
+#[tauri::command]
+pub async fn tiles_update_tile_value(
+    tiles: State<'_, Arc<Tiles>>,
+    tile_id: TileId,
+    value: f64,
+) -> CmdResult<TileModel> {
+    let tile = tiles.update_tile_value(tile_id, None, value).await?;
+
+    Ok(tile)
+}
+
 /// Update a specific tile label
 #[tauri::command]
 pub async fn tiles_update_tile_position(
+    db: State<'_, DbPool>,
     tiles: State<'_, Arc<Tiles>>,
+    history: State<'_, Arc<History>>,
     tile_id: TileId,
     position: TilePosition,
 ) -> CmdResult<TileModel> {
+    let before = TileModel::get_by_id(db.inner(), tile_id).await?;
     let tile = tiles.update_tile_position(tile_id, None, position).await?;
+    history.record_tile(before, Some(tile.clone())).await;
 
     Ok(tile)
 }
@@ -93,25 +214,33 @@ pub async fn tiles_update_tile_position(
 /// Update a specific tile icon
 #[tauri::command]
 pub async fn tiles_update_tile_icon(
+    db: State<'_, DbPool>,
     tiles: State<'_, Arc<Tiles>>,
+    history: State<'_, Arc<History>>,
     tile_id: TileId,
     icon: TileIcon,
     kind: UpdateKind,
 ) -> CmdResult<TileModel> {
+    let before = TileModel::get_by_id(db.inner(), tile_id).await?;
     let tile = tiles.update_tile_icon(tile_id, None, icon, kind).await?;
+    history.record_tile(before, Some(tile.clone())).await;
     Ok(tile)
 }
 
 /// Update a specific tile icon options
 #[tauri::command]
 pub async fn tiles_update_tile_icon_options(
+    db: State<'_, DbPool>,
     tiles: State<'_, Arc<Tiles>>,
+    history: State<'_, Arc<History>>,
     tile_id: TileId,
     icon_options: TileIconOptions,
 ) -> CmdResult<TileModel> {
+    let before = TileModel::get_by_id(db.inner(), tile_id).await?;
     let tile = tiles
         .update_tile_icon_options(tile_id, None, icon_options)
         .await?;
+    history.record_tile(before, Some(tile.clone())).await;
     Ok(tile)
 }
 
@@ -120,6 +249,7 @@ pub async fn tiles_update_tile_icon_options(
 pub async fn tiles_delete_tile(
     db: State<'_, DbPool>,
     devices: State<'_, Arc<Devices>>,
+    history: State<'_, Arc<History>>,
     tile_id: TileId,
 ) -> CmdResult<()> {
     let db = db.inner();
@@ -129,5 +259,6 @@ pub async fn tiles_delete_tile(
 
     TileModel::delete(db, tile_id).await?;
     devices.background_update_folder(tile.folder_id);
+    history.record_tile(Some(tile), None).await;
     Ok(())
 }
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use tauri::State;
+
+use crate::{
+    commands::CmdResult,
+    database::entity::{
+        folder::{FolderId, FolderModel},
+        profile::{ProfileId, ProfileModel},
+        tile::{TileId, TileModel},
+    },
+    trash::{Trash, TrashContents},
+};
+
+/// Get the profiles, folders and tiles in the trash
+#[tauri::command]
+pub async fn trash_get_trash(trash: State<'_, Arc<Trash>>) -> CmdResult<TrashContents> {
+    let contents = trash.get_trash().await?;
+    Ok(contents)
+}
+
+/// Restore a profile from the trash
+#[tauri::command]
+pub async fn trash_restore_profile(
+    trash: State<'_, Arc<Trash>>,
+    profile_id: ProfileId,
+) -> CmdResult<ProfileModel> {
+    let profile = trash.restore_profile(profile_id).await?;
+    Ok(profile)
+}
+
+/// Restore a folder from the trash
+#[tauri::command]
+pub async fn trash_restore_folder(
+    trash: State<'_, Arc<Trash>>,
+    folder_id: FolderId,
+) -> CmdResult<FolderModel> {
+    let folder = trash.restore_folder(folder_id).await?;
+    Ok(folder)
+}
+
+/// Restore a tile from the trash
+#[tauri::command]
+pub async fn trash_restore_tile(
+    trash: State<'_, Arc<Trash>>,
+    tile_id: TileId,
+) -> CmdResult<TileModel> {
+    let tile = trash.restore_tile(tile_id).await?;
+    Ok(tile)
+}
+
+/// Permanently delete everything in the trash
+#[tauri::command]
+pub async fn trash_empty_trash(trash: State<'_, Arc<Trash>>) -> CmdResult<()> {
+    trash.empty_trash().await?;
+    Ok(())
+}
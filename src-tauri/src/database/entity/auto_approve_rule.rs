@@ -0,0 +1,349 @@
+use super::{folder::FolderId, profile::ProfileId};
+use crate::database::{DbErr, DbPool, DbResult};
+use chrono::{DateTime, Utc};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+pub type AutoApproveRuleId = Uuid;
+
+/// Rule for automatically approving devices that match
+/// the rule conditions
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AutoApproveRuleModel {
+    pub id: AutoApproveRuleId,
+
+    /// Name of the rule
+    pub name: String,
+
+    /// Conditions the device must match
+    #[sqlx(json)]
+    pub config: AutoApproveRuleConfig,
+
+    /// Whether the rule is enabled
+    pub enabled: bool,
+
+    /// Profile to give approved devices
+    pub profile_id: ProfileId,
+
+    /// Folder to give approved devices
+    pub folder_id: FolderId,
+
+    pub order: u32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Conditions for an auto approve rule, a device must match all the
+/// conditions that are specified. Rules without any conditions never match.
+///
+/// Device names are chosen by the device itself so a name pattern must
+/// always be combined with a subnet or public key fingerprint
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoApproveRuleConfig {
+    /// Subnet the device must be connecting from (i.e 192.168.1.0/24)
+    pub subnet: Option<String>,
+
+    /// Pattern the device name must match, supports "*" and "?" wildcards
+    pub name_pattern: Option<String>,
+
+    /// Fingerprint of the public key the device must be using
+    pub public_key_fingerprint: Option<String>,
+}
+
+impl AutoApproveRuleConfig {
+    /// Check that the conditions are valid
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !self.is_constrained() {
+            return Err(anyhow::anyhow!(
+                "name pattern must be combined with a subnet or public key fingerprint"
+            ));
+        }
+
+        if let Some(subnet) = &self.subnet {
+            subnet
+                .parse::<IpNet>()
+                .map_err(|_| anyhow::anyhow!("invalid subnet \"{subnet}\""))?;
+        }
+
+        Ok(())
+    }
+
+    /// Check if a device matches the rule conditions, devices without an IP
+    /// address (i.e serial devices) never match rules with a subnet
+    pub fn matches(
+        &self,
+        addr: Option<IpAddr>,
+        device_name: &str,
+        public_key_fingerprint: &str,
+    ) -> bool {
+        if !self.is_constrained()
+            || (self.subnet.is_none()
+                && self.name_pattern.is_none()
+                && self.public_key_fingerprint.is_none())
+        {
+            return false;
+        }
+
+        if let Some(subnet) = &self.subnet {
+            let subnet = match subnet.parse::<IpNet>() {
+                Ok(value) => value,
+                Err(cause) => {
+                    tracing::warn!(?cause, ?subnet, "invalid auto approve rule subnet");
+                    return false;
+                }
+            };
+
+            if !addr.is_some_and(|addr| subnet.contains(&addr)) {
+                return false;
+            }
+        }
+
+        if let Some(pattern) = &self.name_pattern
+            && !matches_pattern(pattern, device_name)
+        {
+            return false;
+        }
+
+        if let Some(fingerprint) = &self.public_key_fingerprint
+            && !fingerprint.eq_ignore_ascii_case(public_key_fingerprint)
+        {
+            return false;
+        }
+
+        true
+    }
+
+    /// Whether the rule is constrained by something the device cannot choose
+    /// for itself, rules using a name pattern require a subnet or fingerprint
+    fn is_constrained(&self) -> bool {
+        self.name_pattern.is_none()
+            || self.subnet.is_some()
+            || self.public_key_fingerprint.is_some()
+    }
+}
+
+/// Case insensitive matching of `value` against a `pattern` where "*"
+/// matches any number of characters and "?" matches a single character
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let value: Vec<char> = value.to_lowercase().chars().collect();
+
+    let (mut p, mut v) = (0, 0);
+
+    // Position of the last "*" and the value position it was matched at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, v));
+                p += 1;
+            }
+            Some(char) if *char == '?' || *char == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            _ => match backtrack {
+                // Let the last "*" consume another character
+                Some((star_p, star_v)) => {
+                    backtrack = Some((star_p, star_v + 1));
+                    p = star_p + 1;
+                    v = star_v + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    // Remaining pattern can only be "*"
+    pattern[p..].iter().all(|char| *char == '*')
+}
+
+#[derive(Deserialize)]
+pub struct CreateAutoApproveRule {
+    pub name: String,
+    pub config: AutoApproveRuleConfig,
+    pub enabled: bool,
+    pub profile_id: ProfileId,
+    pub folder_id: FolderId,
+    pub order: u32,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateAutoApproveRule {
+    pub name: String,
+    pub config: AutoApproveRuleConfig,
+    pub enabled: bool,
+    pub profile_id: ProfileId,
+    pub folder_id: FolderId,
+}
+
+impl AutoApproveRuleModel {
+    pub async fn create(
+        db: &DbPool,
+        create: CreateAutoApproveRule,
+    ) -> DbResult<AutoApproveRuleModel> {
+        let model = AutoApproveRuleModel {
+            id: Uuid::new_v4(),
+            name: create.name,
+            config: create.config,
+            enabled: create.enabled,
+            profile_id: create.profile_id,
+            folder_id: create.folder_id,
+            order: create.order,
+            created_at: Utc::now(),
+        };
+
+        let config =
+            serde_json::to_value(&model.config).map_err(|err| DbErr::Encode(err.into()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO "auto_approve_rules" (
+                "id",
+                "name",
+                "config",
+                "enabled",
+                "profile_id",
+                "folder_id",
+                "order",
+                "created_at"
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        )
+        .bind(model.id)
+        .bind(model.name.clone())
+        .bind(config)
+        .bind(model.enabled)
+        .bind(model.profile_id)
+        .bind(model.folder_id)
+        .bind(model.order)
+        .bind(model.created_at)
+        .execute(db)
+        .await?;
+
+        Ok(model)
+    }
+
+    pub async fn update(
+        mut self,
+        db: &DbPool,
+        update: UpdateAutoApproveRule,
+    ) -> DbResult<AutoApproveRuleModel> {
+        let config =
+            serde_json::to_value(&update.config).map_err(|err| DbErr::Encode(err.into()))?;
+
+        sqlx::query(
+            r#"
+            UPDATE "auto_approve_rules"
+            SET "name" = ?, "config" = ?, "enabled" = ?, "profile_id" = ?, "folder_id" = ?
+            WHERE "id" = ?
+        "#,
+        )
+        .bind(&update.name)
+        .bind(config)
+        .bind(update.enabled)
+        .bind(update.profile_id)
+        .bind(update.folder_id)
+        .bind(self.id)
+        .execute(db)
+        .await?;
+
+        self.name = update.name;
+        self.config = update.config;
+        self.enabled = update.enabled;
+        self.profile_id = update.profile_id;
+        self.folder_id = update.folder_id;
+
+        Ok(self)
+    }
+
+    pub async fn get_by_id(
+        db: &DbPool,
+        id: AutoApproveRuleId,
+    ) -> DbResult<Option<AutoApproveRuleModel>> {
+        sqlx::query_as(r#"SELECT * FROM "auto_approve_rules" WHERE "id" = ?"#)
+            .bind(id)
+            .fetch_optional(db)
+            .await
+    }
+
+    pub async fn all(db: &DbPool) -> DbResult<Vec<AutoApproveRuleModel>> {
+        sqlx::query_as(r#"SELECT * FROM "auto_approve_rules" ORDER BY "order" ASC"#)
+            .fetch_all(db)
+            .await
+    }
+
+    /// Get the enabled rules whose profile and folder are not in the trash
+    pub async fn all_available(db: &DbPool) -> DbResult<Vec<AutoApproveRuleModel>> {
+        sqlx::query_as(
+            r#"
+            SELECT "rule".* FROM "auto_approve_rules" AS "rule"
+            JOIN "profiles" AS "profile" ON "profile"."id" = "rule"."profile_id"
+            JOIN "folders" AS "folder" ON "folder"."id" = "rule"."folder_id"
+            WHERE "rule"."enabled" = TRUE
+                AND "profile"."deleted_at" IS NULL
+                AND "folder"."deleted_at" IS NULL
+            ORDER BY "rule"."order" ASC
+        "#,
+        )
+        .fetch_all(db)
+        .await
+    }
+
+    pub async fn delete(db: &DbPool, id: AutoApproveRuleId) -> DbResult<()> {
+        sqlx::query(r#"DELETE FROM "auto_approve_rules" WHERE "id" = ?"#)
+            .bind(id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AutoApproveRuleConfig, matches_pattern};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("Lab Tablet *", "lab tablet 12"));
+        assert!(matches_pattern("tablet-??", "tablet-01"));
+        assert!(matches_pattern("*", ""));
+        assert!(matches_pattern("*-tab*", "lab-tablet"));
+        assert!(!matches_pattern("tablet-??", "tablet-1"));
+        assert!(!matches_pattern("lab*", "office tablet"));
+    }
+
+    #[test]
+    fn test_rule_matches() {
+        let rule = AutoApproveRuleConfig {
+            subnet: Some("192.168.1.0/24".to_string()),
+            name_pattern: Some("lab-*".to_string()),
+            public_key_fingerprint: None,
+        };
+
+        let inside = Some(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)));
+        let outside = Some(IpAddr::V4(Ipv4Addr::new(192, 168, 2, 20)));
+
+        assert!(rule.matches(inside, "lab-1", "ab"));
+        assert!(!rule.matches(outside, "lab-1", "ab"));
+        assert!(!rule.matches(inside, "office-1", "ab"));
+        assert!(!rule.matches(None, "lab-1", "ab"));
+
+        // Rules without conditions never match
+        assert!(!AutoApproveRuleConfig::default().matches(inside, "lab-1", "ab"));
+
+        // Rules matching only on the device name never match
+        let name_only = AutoApproveRuleConfig {
+            name_pattern: Some("lab-*".to_string()),
+            ..Default::default()
+        };
+        assert!(!name_only.matches(inside, "lab-1", "ab"));
+        assert!(name_only.validate().is_err());
+    }
+}
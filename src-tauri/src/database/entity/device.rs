@@ -1,8 +1,11 @@
-use super::{folder::FolderId, profile::ProfileId};
+use super::{
+    folder::{FolderId, FolderModel},
+    profile::ProfileId,
+};
 use crate::database::{DbErr, DbPool, DbResult};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::prelude::FromRow;
+use sqlx::{prelude::FromRow, sqlite::SqliteExecutor};
 use uuid::Uuid;
 
 pub type DeviceId = Uuid;
@@ -32,8 +35,36 @@ pub struct DeviceModel {
     pub last_connected_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DeviceConfig {}
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceConfig {
+    /// Profiles the device is allowed to access, [None] allows
+    /// access to all profiles
+    pub allowed_profiles: Option<Vec<ProfileId>>,
+
+    /// Folders the device is allowed to access, [None] allows
+    /// access to all folders within the allowed profiles
+    pub allowed_folders: Option<Vec<FolderId>>,
+}
+
+impl DeviceConfig {
+    /// Check whether the device is allowed to access the profile
+    pub fn can_access_profile(&self, profile_id: ProfileId) -> bool {
+        self.allowed_profiles
+            .as_ref()
+            .is_none_or(|allowed| allowed.contains(&profile_id))
+    }
+
+    /// Check whether the device is allowed to access the folder, this
+    /// requires access to the profile the folder is within
+    pub fn can_access_folder(&self, folder: &FolderModel) -> bool {
+        self.can_access_profile(folder.profile_id)
+            && self
+                .allowed_folders
+                .as_ref()
+                .is_none_or(|allowed| allowed.contains(&folder.id))
+    }
+}
 
 pub struct CreateDevice {
     pub name: String,
@@ -93,7 +124,7 @@ impl DeviceModel {
 
     pub async fn set_profile(
         mut self,
-        db: &DbPool,
+        db: impl SqliteExecutor<'_>,
         profile_id: ProfileId,
         folder_id: FolderId,
     ) -> DbResult<DeviceModel> {
@@ -110,6 +141,19 @@ impl DeviceModel {
         Ok(self)
     }
 
+    pub async fn set_config(mut self, db: &DbPool, config: DeviceConfig) -> DbResult<DeviceModel> {
+        let config_json = serde_json::to_value(&config).map_err(|err| DbErr::Encode(err.into()))?;
+
+        sqlx::query(r#"UPDATE "devices" SET "config" = ? WHERE "id" = ?"#)
+            .bind(config_json)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        self.config = config;
+        Ok(self)
+    }
+
     pub async fn set_connected_now(&mut self, db: &DbPool) -> DbResult<()> {
         let last_connected_at = Utc::now();
         sqlx::query(r#"UPDATE "devices" SET "last_connected_at" = ? WHERE "id" = ?"#)
@@ -141,7 +185,14 @@ impl DeviceModel {
     }
 
     pub async fn all(db: &DbPool) -> DbResult<Vec<DeviceModel>> {
-        sqlx::query_as(r#"SELECT * FROM "devices""#)
+        sqlx::query_as(r#"SELECT * FROM "devices" ORDER BY "order" ASC"#)
+            .fetch_all(db)
+            .await
+    }
+
+    pub async fn all_by_folder(db: &DbPool, folder_id: FolderId) -> DbResult<Vec<DeviceModel>> {
+        sqlx::query_as(r#"SELECT * FROM "devices" WHERE "folder_id" = ?"#)
+            .bind(folder_id)
             .fetch_all(db)
             .await
     }
@@ -153,11 +204,38 @@ impl DeviceModel {
             .await
     }
 
-    pub async fn all_by_folder(db: &DbPool, folder_id: FolderId) -> DbResult<Vec<DeviceModel>> {
-        sqlx::query_as(r#"SELECT * FROM "devices" WHERE "folder_id" = ?"#)
-            .bind(folder_id)
-            .fetch_all(db)
-            .await
+    /// Get all devices using a profile or folder that was moved to
+    /// the trash before `before`
+    pub async fn all_purgeable(db: &DbPool, before: DateTime<Utc>) -> DbResult<Vec<DeviceModel>> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM "devices"
+            WHERE "profile_id" IN (SELECT "id" FROM "profiles" WHERE "deleted_at" < ?1)
+                OR "folder_id" IN (
+                    SELECT "folder"."id" FROM "folders" AS "folder"
+                    JOIN "profiles" AS "profile" ON "profile"."id" = "folder"."profile_id"
+                    WHERE "folder"."deleted_at" < ?1 OR "profile"."deleted_at" < ?1
+                )
+        "#,
+        )
+        .bind(before)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Update the order of the devices to match the order of `device_ids`
+    pub async fn reorder(db: &DbPool, device_ids: &[DeviceId]) -> DbResult<()> {
+        let mut tx = db.begin().await?;
+
+        for (order, device_id) in device_ids.iter().enumerate() {
+            sqlx::query(r#"UPDATE "devices" SET "order" = ? WHERE "id" = ?"#)
+                .bind(order as u32)
+                .bind(device_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await
     }
 
     pub async fn delete(db: &DbPool, device_id: DeviceId) -> DbResult<()> {
@@ -0,0 +1,95 @@
+use super::{auto_approve_rule::AutoApproveRuleId, device::DeviceId};
+use crate::database::{DbPool, DbResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+
+/// Audit record of a device that was automatically approved
+/// by an auto approve rule
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DeviceAutoApprovalModel {
+    pub id: Uuid,
+
+    /// ID of the rule that approved the device
+    pub rule_id: AutoApproveRuleId,
+
+    /// Name of the rule at the time of approval
+    pub rule_name: String,
+
+    /// ID of the device that was created
+    pub device_id: DeviceId,
+
+    /// Name of the device at the time of approval
+    pub device_name: String,
+
+    /// Fingerprint of the device public key
+    pub public_key_fingerprint: String,
+
+    /// Address the device connected from
+    pub socket_addr: String,
+
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct CreateDeviceAutoApproval {
+    pub rule_id: AutoApproveRuleId,
+    pub rule_name: String,
+    pub device_id: DeviceId,
+    pub device_name: String,
+    pub public_key_fingerprint: String,
+    pub socket_addr: String,
+}
+
+impl DeviceAutoApprovalModel {
+    pub async fn create(
+        db: &DbPool,
+        create: CreateDeviceAutoApproval,
+    ) -> DbResult<DeviceAutoApprovalModel> {
+        let model = DeviceAutoApprovalModel {
+            id: Uuid::new_v4(),
+            rule_id: create.rule_id,
+            rule_name: create.rule_name,
+            device_id: create.device_id,
+            device_name: create.device_name,
+            public_key_fingerprint: create.public_key_fingerprint,
+            socket_addr: create.socket_addr,
+            created_at: Utc::now(),
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO "device_auto_approvals" (
+                "id",
+                "rule_id",
+                "rule_name",
+                "device_id",
+                "device_name",
+                "public_key_fingerprint",
+                "socket_addr",
+                "created_at"
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        )
+        .bind(model.id)
+        .bind(model.rule_id)
+        .bind(model.rule_name.clone())
+        .bind(model.device_id)
+        .bind(model.device_name.clone())
+        .bind(model.public_key_fingerprint.clone())
+        .bind(model.socket_addr.clone())
+        .bind(model.created_at)
+        .execute(db)
+        .await?;
+
+        Ok(model)
+    }
+
+    /// Get all approvals, most recent first
+    pub async fn all(db: &DbPool) -> DbResult<Vec<DeviceAutoApprovalModel>> {
+        sqlx::query_as(r#"SELECT * FROM "device_auto_approvals" ORDER BY "created_at" DESC"#)
+            .fetch_all(db)
+            .await
+    }
+}
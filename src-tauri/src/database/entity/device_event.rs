@@ -0,0 +1,232 @@
+use super::device::DeviceId;
+use crate::database::{DbPool, DbResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+
+/// Persisted record of something that happened to a device
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DeviceEventModel {
+    pub id: Uuid,
+
+    /// Kind of event
+    pub kind: DeviceEventKind,
+
+    /// ID of the device, [None] when the device is not yet known
+    pub device_id: Option<DeviceId>,
+
+    /// Fingerprint of the device public key when known
+    pub public_key_fingerprint: Option<String>,
+
+    /// Remote address of the device when known
+    pub socket_addr: Option<String>,
+
+    /// Additional details about the event (i.e the reason a handshake failed)
+    pub message: Option<String>,
+
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(rename_all = "snake_case")]
+pub enum DeviceEventKind {
+    /// Known device connected and authenticated
+    Connected,
+    /// Authenticated device disconnected
+    Disconnected,
+    /// Device request was approved
+    Approved,
+    /// Device was approved by an auto approve rule
+    AutoApproved,
+    /// Device request was declined
+    Declined,
+    /// Device access was revoked
+    Revoked,
+    /// Device failed to complete the handshake
+    HandshakeFailed,
+}
+
+#[derive(Default)]
+pub struct CreateDeviceEvent {
+    pub device_id: Option<DeviceId>,
+    pub public_key_fingerprint: Option<String>,
+    pub socket_addr: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Filter and pagination for querying device events
+#[derive(Debug, Deserialize)]
+pub struct DeviceEventQuery {
+    /// Only include events for a specific device
+    pub device_id: Option<DeviceId>,
+    /// Only include events at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Only include events before this time
+    pub to: Option<DateTime<Utc>>,
+    /// Page to load, starting at zero
+    pub page: u32,
+    /// Number of events per page
+    pub page_size: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeviceEventPage {
+    /// Events on the page, most recent first
+    pub events: Vec<DeviceEventModel>,
+    /// Total number of events matching the query
+    pub total: u32,
+}
+
+impl DeviceEventModel {
+    /// Maximum number of events that can be requested in a single page
+    const MAX_PAGE_SIZE: u32 = 500;
+
+    pub async fn create(
+        db: &DbPool,
+        kind: DeviceEventKind,
+        create: CreateDeviceEvent,
+    ) -> DbResult<DeviceEventModel> {
+        let model = DeviceEventModel {
+            id: Uuid::new_v4(),
+            kind,
+            device_id: create.device_id,
+            public_key_fingerprint: create.public_key_fingerprint,
+            socket_addr: create.socket_addr,
+            message: create.message,
+            created_at: Utc::now(),
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO "device_events" (
+                "id",
+                "kind",
+                "device_id",
+                "public_key_fingerprint",
+                "socket_addr",
+                "message",
+                "created_at"
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+        )
+        .bind(model.id)
+        .bind(model.kind)
+        .bind(model.device_id)
+        .bind(model.public_key_fingerprint.clone())
+        .bind(model.socket_addr.clone())
+        .bind(model.message.clone())
+        .bind(model.created_at)
+        .execute(db)
+        .await?;
+
+        Ok(model)
+    }
+
+    /// Query a page of events, most recent first
+    pub async fn query(db: &DbPool, query: DeviceEventQuery) -> DbResult<DeviceEventPage> {
+        let page_size = query.page_size.clamp(1, Self::MAX_PAGE_SIZE);
+        let offset = query.page.saturating_mul(page_size);
+
+        let events: Vec<DeviceEventModel> = sqlx::query_as(
+            r#"
+            SELECT * FROM "device_events"
+            WHERE (?1 IS NULL OR "device_id" = ?1)
+                AND (?2 IS NULL OR "created_at" >= ?2)
+                AND (?3 IS NULL OR "created_at" < ?3)
+            ORDER BY "created_at" DESC
+            LIMIT ?4 OFFSET ?5
+        "#,
+        )
+        .bind(query.device_id)
+        .bind(query.from)
+        .bind(query.to)
+        .bind(page_size)
+        .bind(offset)
+        .fetch_all(db)
+        .await?;
+
+        let (total,): (u32,) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*) FROM "device_events"
+            WHERE (?1 IS NULL OR "device_id" = ?1)
+                AND (?2 IS NULL OR "created_at" >= ?2)
+                AND (?3 IS NULL OR "created_at" < ?3)
+        "#,
+        )
+        .bind(query.device_id)
+        .bind(query.from)
+        .bind(query.to)
+        .fetch_one(db)
+        .await?;
+
+        Ok(DeviceEventPage { events, total })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CreateDeviceEvent, DeviceEventKind, DeviceEventModel, DeviceEventQuery};
+    use crate::database::mock_database;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_query_device_events() {
+        let db = mock_database().await;
+        let device_id = Uuid::new_v4();
+
+        for _ in 0..3 {
+            DeviceEventModel::create(
+                &db,
+                DeviceEventKind::Connected,
+                CreateDeviceEvent {
+                    device_id: Some(device_id),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        DeviceEventModel::create(
+            &db,
+            DeviceEventKind::HandshakeFailed,
+            CreateDeviceEvent::default(),
+        )
+        .await
+        .unwrap();
+
+        let page = DeviceEventModel::query(
+            &db,
+            DeviceEventQuery {
+                device_id: Some(device_id),
+                from: None,
+                to: None,
+                page: 0,
+                page_size: 2,
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(page.total, 3);
+        assert_eq!(page.events.len(), 2);
+
+        let page = DeviceEventModel::query(
+            &db,
+            DeviceEventQuery {
+                device_id: None,
+                from: None,
+                to: None,
+                page: 1,
+                page_size: 2,
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(page.total, 4);
+        assert_eq!(page.events.len(), 2);
+    }
+}
@@ -0,0 +1,226 @@
+use super::{device::DeviceId, folder::FolderId};
+use crate::database::{DbErr, DbPool, DbResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+
+pub type DeviceGroupId = Uuid;
+
+/// Group of devices that follow each others navigation
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DeviceGroupModel {
+    pub id: DeviceGroupId,
+
+    /// Name of the group
+    pub name: String,
+
+    /// Group configuration
+    #[sqlx(json)]
+    pub config: DeviceGroupConfig,
+
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceGroupConfig {
+    /// Folders that are paired together, when a member navigates to one
+    /// folder of a pair the other members navigate to the other folder.
+    /// Folders without a pair are mirrored as the same folder
+    pub folder_pairs: Vec<DeviceGroupFolderPair>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceGroupFolderPair {
+    pub folder_id: FolderId,
+    pub paired_folder_id: FolderId,
+}
+
+impl DeviceGroupConfig {
+    /// Get the folder other members should navigate to when
+    /// a member navigates to `folder_id`
+    pub fn get_mirror_folder(&self, folder_id: FolderId) -> FolderId {
+        self.folder_pairs
+            .iter()
+            .find_map(|pair| {
+                if pair.folder_id == folder_id {
+                    Some(pair.paired_folder_id)
+                } else if pair.paired_folder_id == folder_id {
+                    Some(pair.folder_id)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(folder_id)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateDeviceGroup {
+    pub name: String,
+    pub config: DeviceGroupConfig,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateDeviceGroup {
+    pub name: String,
+    pub config: DeviceGroupConfig,
+}
+
+impl DeviceGroupModel {
+    pub async fn create(db: &DbPool, create: CreateDeviceGroup) -> DbResult<DeviceGroupModel> {
+        let model = DeviceGroupModel {
+            id: Uuid::new_v4(),
+            name: create.name,
+            config: create.config,
+            created_at: Utc::now(),
+        };
+
+        let config =
+            serde_json::to_value(&model.config).map_err(|err| DbErr::Encode(err.into()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO "device_groups" ("id", "name", "config", "created_at")
+            VALUES (?, ?, ?, ?)
+        "#,
+        )
+        .bind(model.id)
+        .bind(model.name.clone())
+        .bind(config)
+        .bind(model.created_at)
+        .execute(db)
+        .await?;
+
+        Ok(model)
+    }
+
+    pub async fn update(
+        mut self,
+        db: &DbPool,
+        update: UpdateDeviceGroup,
+    ) -> DbResult<DeviceGroupModel> {
+        let config =
+            serde_json::to_value(&update.config).map_err(|err| DbErr::Encode(err.into()))?;
+
+        sqlx::query(r#"UPDATE "device_groups" SET "name" = ?, "config" = ? WHERE "id" = ?"#)
+            .bind(&update.name)
+            .bind(config)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        self.name = update.name;
+        self.config = update.config;
+
+        Ok(self)
+    }
+
+    pub async fn get_by_id(db: &DbPool, id: DeviceGroupId) -> DbResult<Option<DeviceGroupModel>> {
+        sqlx::query_as(r#"SELECT * FROM "device_groups" WHERE "id" = ?"#)
+            .bind(id)
+            .fetch_optional(db)
+            .await
+    }
+
+    /// Get the group a device is a member of
+    pub async fn get_by_device(
+        db: &DbPool,
+        device_id: DeviceId,
+    ) -> DbResult<Option<DeviceGroupModel>> {
+        sqlx::query_as(
+            r#"
+            SELECT "device_groups".* FROM "device_groups"
+            INNER JOIN "device_group_members"
+                ON "device_group_members"."group_id" = "device_groups"."id"
+            WHERE "device_group_members"."device_id" = ?
+        "#,
+        )
+        .bind(device_id)
+        .fetch_optional(db)
+        .await
+    }
+
+    pub async fn all(db: &DbPool) -> DbResult<Vec<DeviceGroupModel>> {
+        sqlx::query_as(r#"SELECT * FROM "device_groups" ORDER BY "created_at" ASC"#)
+            .fetch_all(db)
+            .await
+    }
+
+    pub async fn delete(db: &DbPool, id: DeviceGroupId) -> DbResult<()> {
+        sqlx::query(r#"DELETE FROM "device_groups" WHERE "id" = ?"#)
+            .bind(id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Get the IDs of all devices in the group
+    pub async fn get_members(db: &DbPool, id: DeviceGroupId) -> DbResult<Vec<DeviceId>> {
+        let members: Vec<(DeviceId,)> = sqlx::query_as(
+            r#"SELECT "device_id" FROM "device_group_members" WHERE "group_id" = ?"#,
+        )
+        .bind(id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(members.into_iter().map(|(device_id,)| device_id).collect())
+    }
+
+    /// Set the group a device is a member of, [None] removes
+    /// the device from its current group
+    pub async fn set_device_group(
+        db: &DbPool,
+        device_id: DeviceId,
+        group_id: Option<DeviceGroupId>,
+    ) -> DbResult<()> {
+        match group_id {
+            Some(group_id) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO "device_group_members" ("device_id", "group_id")
+                    VALUES (?, ?)
+                    ON CONFLICT("device_id") DO UPDATE SET "group_id" = excluded."group_id"
+                "#,
+                )
+                .bind(device_id)
+                .bind(group_id)
+                .execute(db)
+                .await?;
+            }
+            None => {
+                sqlx::query(r#"DELETE FROM "device_group_members" WHERE "device_id" = ?"#)
+                    .bind(device_id)
+                    .execute(db)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DeviceGroupConfig, DeviceGroupFolderPair};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_get_mirror_folder() {
+        let left = Uuid::new_v4();
+        let right = Uuid::new_v4();
+        let other = Uuid::new_v4();
+
+        let config = DeviceGroupConfig {
+            folder_pairs: vec![DeviceGroupFolderPair {
+                folder_id: left,
+                paired_folder_id: right,
+            }],
+        };
+
+        assert_eq!(config.get_mirror_folder(left), right);
+        assert_eq!(config.get_mirror_folder(right), left);
+        assert_eq!(config.get_mirror_folder(other), other);
+    }
+}
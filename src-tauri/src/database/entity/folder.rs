@@ -1,9 +1,15 @@
 use crate::database::{DbErr, DbPool, DbResult};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::prelude::FromRow;
+use sqlx::{SqliteConnection, prelude::FromRow, sqlite::SqliteExecutor};
+use std::collections::HashMap;
 use uuid::Uuid;
 
-use super::profile::ProfileId;
+use super::{
+    device::DeviceId,
+    profile::ProfileId,
+    tile::{TileId, TilePosition},
+};
 
 pub type FolderId = Uuid;
 
@@ -16,12 +22,19 @@ pub struct FolderModel {
     pub profile_id: ProfileId,
     pub default: bool,
     pub order: u32,
+    /// When the folder was moved to the trash
+    pub deleted_at: Option<DateTime<Utc>>,
 }
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct FolderConfig {
     pub rows: u32,
     pub columns: u32,
+    /// Number of pages of tiles within the folder
+    pub pages: u32,
+    /// Alternative layouts for specific devices or screen sizes
+    pub layouts: Vec<FolderLayout>,
 }
 
 impl Default for FolderConfig {
@@ -29,10 +42,45 @@ impl Default for FolderConfig {
         Self {
             rows: 4,
             columns: 6,
+            pages: 1,
+            layouts: Vec::new(),
         }
     }
 }
 
+/// Alternative layout of a folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderLayout {
+    /// Devices the layout is used for
+    pub target: FolderLayoutTarget,
+    pub rows: u32,
+    pub columns: u32,
+    /// Positions of the tiles within the layout, tiles without
+    /// a position are placed in the first free space
+    #[serde(default)]
+    pub positions: HashMap<TileId, TilePosition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum FolderLayoutTarget {
+    /// Layout for a specific device
+    Device { device_id: DeviceId },
+    /// Layout for devices with a screen of the size class
+    SizeClass { size_class: ScreenSizeClass },
+}
+
+/// Size class of a device screen based on its smallest dimension
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ScreenSizeClass {
+    /// Phones (Less than 600 logical pixels)
+    Small,
+    /// Tablets (Less than 1000 logical pixels)
+    Medium,
+    /// Desktops and large tablets
+    Large,
+}
+
 #[derive(Deserialize)]
 pub struct CreateFolder {
     pub name: String,
@@ -44,7 +92,10 @@ pub struct CreateFolder {
 
 impl FolderModel {
     /// Create a new profile
-    pub async fn create(db: &DbPool, create: CreateFolder) -> DbResult<FolderModel> {
+    pub async fn create(
+        db: impl SqliteExecutor<'_>,
+        create: CreateFolder,
+    ) -> DbResult<FolderModel> {
         let model = FolderModel {
             id: Uuid::new_v4(),
             name: create.name,
@@ -52,6 +103,7 @@ impl FolderModel {
             profile_id: create.profile_id,
             config: create.config,
             order: create.order,
+            deleted_at: None,
         };
 
         let config =
@@ -74,8 +126,11 @@ impl FolderModel {
         Ok(model)
     }
 
-    pub async fn get_by_id(db: &DbPool, folder_id: FolderId) -> DbResult<Option<FolderModel>> {
-        sqlx::query_as(r#"SELECT * FROM "folders" WHERE "id" = ?"#)
+    pub async fn get_by_id(
+        db: impl SqliteExecutor<'_>,
+        folder_id: FolderId,
+    ) -> DbResult<Option<FolderModel>> {
+        sqlx::query_as(r#"SELECT * FROM "folders" WHERE "id" = ? AND "deleted_at" IS NULL"#)
             .bind(folder_id)
             .fetch_optional(db)
             .await
@@ -93,7 +148,11 @@ impl FolderModel {
         Ok(self)
     }
 
-    pub async fn set_config(mut self, db: &DbPool, config: FolderConfig) -> DbResult<FolderModel> {
+    pub async fn set_config(
+        mut self,
+        db: impl SqliteExecutor<'_>,
+        config: FolderConfig,
+    ) -> DbResult<FolderModel> {
         let config_json = serde_json::to_value(&config).map_err(|err| DbErr::Encode(err.into()))?;
 
         sqlx::query(r#"UPDATE "folders" SET "config" = ? WHERE "id" = ?"#)
@@ -106,13 +165,28 @@ impl FolderModel {
         Ok(self)
     }
 
-    /// Set this profile as the default profile
-    #[allow(unused)]
+    /// Restore the name and config of the folder to the state of this model
+    pub async fn restore(&self, db: impl SqliteExecutor<'_>) -> DbResult<()> {
+        let config_json =
+            serde_json::to_value(&self.config).map_err(|err| DbErr::Encode(err.into()))?;
+
+        sqlx::query(r#"UPDATE "folders" SET "name" = ?, "config" = ? WHERE "id" = ?"#)
+            .bind(&self.name)
+            .bind(config_json)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Set this folder as the default folder of its profile
     pub async fn set_default(&mut self, db: &DbPool) -> DbResult<()> {
         sqlx::query(
-            r#"UPDATE "folders" SET "default" = CASE WHEN "id" = ? THEN TRUE ELSE FALSE END"#,
+            r#"UPDATE "folders" SET "default" = CASE WHEN "id" = ? THEN TRUE ELSE FALSE END WHERE "profile_id" = ?"#,
         )
         .bind(self.id)
+        .bind(self.profile_id)
         .execute(db)
         .await?;
 
@@ -122,15 +196,123 @@ impl FolderModel {
     }
 
     pub async fn all(db: &DbPool, profile_id: ProfileId) -> DbResult<Vec<FolderModel>> {
-        sqlx::query_as(r#"SELECT * FROM "folders" WHERE "profile_id" = ? ORDER BY "order" ASC"#)
-            .bind(profile_id)
-            .fetch_all(db)
+        sqlx::query_as(
+            r#"
+            SELECT * FROM "folders"
+            WHERE "profile_id" = ? AND "deleted_at" IS NULL
+            ORDER BY "order" ASC
+        "#,
+        )
+        .bind(profile_id)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Update the order of the folders within the profile `profile_id`
+    /// to match the order of `folder_ids`
+    pub async fn reorder(
+        db: &DbPool,
+        profile_id: ProfileId,
+        folder_ids: &[FolderId],
+    ) -> DbResult<()> {
+        let mut tx = db.begin().await?;
+
+        for (order, folder_id) in folder_ids.iter().enumerate() {
+            sqlx::query(r#"UPDATE "folders" SET "order" = ? WHERE "id" = ? AND "profile_id" = ?"#)
+                .bind(order as u32)
+                .bind(folder_id)
+                .bind(profile_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await
+    }
+
+    /// Move the folder to the trash, its tiles are moved to the trash along with it
+    pub async fn delete(db: &mut SqliteConnection, folder_id: FolderId) -> DbResult<()> {
+        let deleted_at = Utc::now();
+
+        let result = sqlx::query(
+            r#"
+            UPDATE "folders" SET "deleted_at" = ?
+            WHERE "id" = ? AND "default" = FALSE AND "deleted_at" IS NULL
+        "#,
+        )
+        .bind(deleted_at)
+        .bind(folder_id)
+        .execute(&mut *db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        sqlx::query(
+            r#"UPDATE "tiles" SET "deleted_at" = ? WHERE "folder_id" = ? AND "deleted_at" IS NULL"#,
+        )
+        .bind(deleted_at)
+        .bind(folder_id)
+        .execute(&mut *db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get all folders in the trash, folders within a profile
+    /// that is also in the trash are not included
+    pub async fn all_deleted(db: &DbPool) -> DbResult<Vec<FolderModel>> {
+        sqlx::query_as(
+            r#"
+            SELECT "folders".* FROM "folders"
+            JOIN "profiles" ON "profiles"."id" = "folders"."profile_id"
+            WHERE "folders"."deleted_at" IS NOT NULL AND "profiles"."deleted_at" IS NULL
+            ORDER BY "folders"."deleted_at" DESC
+        "#,
+        )
+        .fetch_all(db)
+        .await
+    }
+
+    /// Get a folder in the trash by ID
+    pub async fn get_deleted_by_id(
+        db: &DbPool,
+        folder_id: FolderId,
+    ) -> DbResult<Option<FolderModel>> {
+        sqlx::query_as(r#"SELECT * FROM "folders" WHERE "id" = ? AND "deleted_at" IS NOT NULL"#)
+            .bind(folder_id)
+            .fetch_optional(db)
             .await
     }
 
-    pub async fn delete(db: &DbPool, folder_id: FolderId) -> DbResult<()> {
-        sqlx::query(r#"DELETE FROM "folders" WHERE "id" = ? AND "default" = FALSE"#)
+    /// Restore a folder from the trash along with the tiles that
+    /// were moved to the trash with it
+    pub async fn restore_deleted(db: &DbPool, folder_id: FolderId) -> DbResult<()> {
+        let mut tx = db.begin().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE "tiles" SET "deleted_at" = NULL
+            WHERE "folder_id" = ?1
+                AND "deleted_at" = (SELECT "deleted_at" FROM "folders" WHERE "id" = ?1)
+        "#,
+        )
+        .bind(folder_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(r#"UPDATE "folders" SET "deleted_at" = NULL WHERE "id" = ?"#)
             .bind(folder_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await
+    }
+
+    /// Permanently delete folders that were moved to the trash before `before`
+    pub async fn purge_deleted(db: impl SqliteExecutor<'_>, before: DateTime<Utc>) -> DbResult<()> {
+        sqlx::query(r#"DELETE FROM "folders" WHERE "deleted_at" < ?"#)
+            .bind(before)
             .execute(db)
             .await?;
         Ok(())
@@ -138,9 +320,14 @@ impl FolderModel {
 
     /// Get the first profile marked as default
     pub async fn get_default(db: &DbPool, profile_id: ProfileId) -> DbResult<Option<FolderModel>> {
-        sqlx::query_as(r#"SELECT * FROM "folders" WHERE "profile_id" = ? AND "default" = TRUE"#)
-            .bind(profile_id)
-            .fetch_optional(db)
-            .await
+        sqlx::query_as(
+            r#"
+            SELECT * FROM "folders"
+            WHERE "profile_id" = ? AND "default" = TRUE AND "deleted_at" IS NULL
+        "#,
+        )
+        .bind(profile_id)
+        .fetch_optional(db)
+        .await
     }
 }
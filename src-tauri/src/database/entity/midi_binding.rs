@@ -0,0 +1,120 @@
+use super::tile::TileId;
+use crate::database::{DbErr, DbPool, DbResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+
+pub type MidiBindingId = Uuid;
+
+/// Binding between a MIDI input message and a tile
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MidiBindingModel {
+    pub id: MidiBindingId,
+
+    /// Name of the MIDI port the message comes from
+    pub port_name: String,
+
+    /// Message to match and feedback to send
+    #[sqlx(json)]
+    pub config: MidiBindingConfig,
+
+    /// Tile to execute when the message is received
+    pub tile_id: TileId,
+
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MidiBindingConfig {
+    /// Message that triggers the tile
+    pub trigger: MidiTrigger,
+
+    /// LED feedback to send back to the controller when the tile
+    /// displays an indicator, [None] to not send feedback
+    #[serde(default)]
+    pub feedback: Option<MidiFeedback>,
+}
+
+/// MIDI message that can trigger a tile, a [None] channel
+/// matches messages on any channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum MidiTrigger {
+    Note { channel: Option<u8>, note: u8 },
+    ControlChange { channel: Option<u8>, control: u8 },
+}
+
+/// Values sent as the note velocity or control value for each
+/// indicator, controllers commonly map these to LED colors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MidiFeedback {
+    pub success: u8,
+    pub error: u8,
+    pub warning: u8,
+    pub loading: u8,
+}
+
+impl Default for MidiFeedback {
+    fn default() -> Self {
+        Self {
+            success: 127,
+            error: 127,
+            warning: 127,
+            loading: 64,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateMidiBinding {
+    pub port_name: String,
+    pub config: MidiBindingConfig,
+    pub tile_id: TileId,
+}
+
+impl MidiBindingModel {
+    pub async fn create(db: &DbPool, create: CreateMidiBinding) -> DbResult<MidiBindingModel> {
+        let model = MidiBindingModel {
+            id: Uuid::new_v4(),
+            port_name: create.port_name,
+            config: create.config,
+            tile_id: create.tile_id,
+            created_at: Utc::now(),
+        };
+
+        let config =
+            serde_json::to_value(&model.config).map_err(|err| DbErr::Encode(err.into()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO "midi_bindings" ("id", "port_name", "config", "tile_id", "created_at")
+            VALUES (?, ?, ?, ?, ?)
+        "#,
+        )
+        .bind(model.id)
+        .bind(model.port_name.clone())
+        .bind(config)
+        .bind(model.tile_id)
+        .bind(model.created_at)
+        .execute(db)
+        .await?;
+
+        Ok(model)
+    }
+
+    pub async fn all(db: &DbPool) -> DbResult<Vec<MidiBindingModel>> {
+        sqlx::query_as(r#"SELECT * FROM "midi_bindings" ORDER BY "created_at" ASC"#)
+            .fetch_all(db)
+            .await
+    }
+
+    pub async fn delete(db: &DbPool, id: MidiBindingId) -> DbResult<()> {
+        sqlx::query(r#"DELETE FROM "midi_bindings" WHERE "id" = ?"#)
+            .bind(id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
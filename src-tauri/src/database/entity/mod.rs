@@ -1,5 +1,10 @@
+pub mod auto_approve_rule;
 pub mod device;
+pub mod device_auto_approval;
+pub mod device_event;
+pub mod device_group;
 pub mod folder;
+pub mod midi_binding;
 pub mod plugin_properties;
 pub mod profile;
 pub mod settings;
@@ -1,6 +1,7 @@
 use crate::database::{DbErr, DbPool, DbResult};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::prelude::FromRow;
+use sqlx::{SqliteConnection, prelude::FromRow, sqlite::SqliteExecutor};
 use uuid::Uuid;
 
 pub type ProfileId = Uuid;
@@ -13,6 +14,8 @@ pub struct ProfileModel {
     #[sqlx(json)]
     pub config: ProfileConfig,
     pub order: u32,
+    /// When the profile was moved to the trash
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -35,6 +38,7 @@ impl ProfileModel {
             default: false,
             config: create.config,
             order: create.order,
+            deleted_at: None,
         };
 
         let config =
@@ -61,8 +65,45 @@ impl ProfileModel {
         Ok(model)
     }
 
+    /// Create a copy of this profile with a new ID and `name`, the
+    /// copy is never the default profile
+    pub async fn create_copy(
+        &self,
+        db: impl SqliteExecutor<'_>,
+        name: String,
+        order: u32,
+    ) -> DbResult<ProfileModel> {
+        let model = ProfileModel {
+            id: Uuid::new_v4(),
+            name,
+            default: false,
+            config: self.config.clone(),
+            order,
+            deleted_at: None,
+        };
+
+        let config =
+            serde_json::to_value(&model.config).map_err(|err| DbErr::Encode(err.into()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO "profiles" ("id", "name", "default", "config", "order")
+            VALUES (?, ?, ?, ?, ?)
+        "#,
+        )
+        .bind(model.id)
+        .bind(model.name.clone())
+        .bind(model.default)
+        .bind(config)
+        .bind(model.order)
+        .execute(db)
+        .await?;
+
+        Ok(model)
+    }
+
     pub async fn get_by_id(db: &DbPool, id: ProfileId) -> DbResult<Option<ProfileModel>> {
-        sqlx::query_as(r#"SELECT * FROM "profiles" WHERE "id" = ?"#)
+        sqlx::query_as(r#"SELECT * FROM "profiles" WHERE "id" = ? AND "deleted_at" IS NULL"#)
             .bind(id)
             .fetch_optional(db)
             .await
@@ -81,6 +122,21 @@ impl ProfileModel {
         Ok(self)
     }
 
+    /// Restore the name and config of the profile to the state of this model
+    pub async fn restore(&self, db: impl SqliteExecutor<'_>) -> DbResult<()> {
+        let config_json =
+            serde_json::to_value(&self.config).map_err(|err| DbErr::Encode(err.into()))?;
+
+        sqlx::query(r#"UPDATE "profiles" SET "name" = ?, "config" = ? WHERE "id" = ?"#)
+            .bind(&self.name)
+            .bind(config_json)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
     /// Set this profile as the default profile
     pub async fn set_default(mut self, db: &DbPool) -> DbResult<ProfileModel> {
         sqlx::query(
@@ -97,20 +153,128 @@ impl ProfileModel {
 
     /// Get the first profile marked as default
     pub async fn get_default_profile(db: &DbPool) -> DbResult<Option<ProfileModel>> {
-        sqlx::query_as(r#"SELECT * FROM "profiles" WHERE "default" = TRUE"#)
-            .fetch_optional(db)
-            .await
+        sqlx::query_as(
+            r#"SELECT * FROM "profiles" WHERE "default" = TRUE AND "deleted_at" IS NULL"#,
+        )
+        .fetch_optional(db)
+        .await
     }
 
     pub async fn all(db: &DbPool) -> DbResult<Vec<ProfileModel>> {
-        sqlx::query_as(r#"SELECT * FROM "profiles" ORDER BY "order" ASC"#)
-            .fetch_all(db)
-            .await
+        sqlx::query_as(
+            r#"SELECT * FROM "profiles" WHERE "deleted_at" IS NULL ORDER BY "order" ASC"#,
+        )
+        .fetch_all(db)
+        .await
+    }
+
+    /// Update the order of the profiles to match the order of `profile_ids`
+    pub async fn reorder(db: &DbPool, profile_ids: &[ProfileId]) -> DbResult<()> {
+        let mut tx = db.begin().await?;
+
+        for (order, profile_id) in profile_ids.iter().enumerate() {
+            sqlx::query(r#"UPDATE "profiles" SET "order" = ? WHERE "id" = ?"#)
+                .bind(order as u32)
+                .bind(profile_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await
     }
 
-    pub async fn delete(db: &DbPool, profile_id: ProfileId) -> DbResult<()> {
-        sqlx::query(r#"DELETE FROM "profiles" WHERE "id" = ? AND "default" = FALSE"#)
+    /// Move the profile to the trash, its folders and tiles are moved
+    /// to the trash along with it
+    pub async fn delete(db: &mut SqliteConnection, profile_id: ProfileId) -> DbResult<()> {
+        let deleted_at = Utc::now();
+
+        let result = sqlx::query(
+            r#"
+            UPDATE "profiles" SET "deleted_at" = ?
+            WHERE "id" = ? AND "default" = FALSE AND "deleted_at" IS NULL
+        "#,
+        )
+        .bind(deleted_at)
+        .bind(profile_id)
+        .execute(&mut *db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE "tiles" SET "deleted_at" = ?
+            WHERE "deleted_at" IS NULL AND "folder_id" IN (
+                SELECT "id" FROM "folders" WHERE "profile_id" = ? AND "deleted_at" IS NULL
+            )
+        "#,
+        )
+        .bind(deleted_at)
+        .bind(profile_id)
+        .execute(&mut *db)
+        .await?;
+
+        sqlx::query(
+            r#"UPDATE "folders" SET "deleted_at" = ? WHERE "profile_id" = ? AND "deleted_at" IS NULL"#,
+        )
+        .bind(deleted_at)
+        .bind(profile_id)
+        .execute(&mut *db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get all profiles in the trash
+    pub async fn all_deleted(db: &DbPool) -> DbResult<Vec<ProfileModel>> {
+        sqlx::query_as(
+            r#"SELECT * FROM "profiles" WHERE "deleted_at" IS NOT NULL ORDER BY "deleted_at" DESC"#,
+        )
+        .fetch_all(db)
+        .await
+    }
+
+    /// Restore a profile from the trash along with the folders and
+    /// tiles that were moved to the trash with it
+    pub async fn restore_deleted(db: &DbPool, profile_id: ProfileId) -> DbResult<()> {
+        let mut tx = db.begin().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE "tiles" SET "deleted_at" = NULL
+            WHERE "deleted_at" = (SELECT "deleted_at" FROM "profiles" WHERE "id" = ?1)
+                AND "folder_id" IN (SELECT "id" FROM "folders" WHERE "profile_id" = ?1)
+        "#,
+        )
+        .bind(profile_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE "folders" SET "deleted_at" = NULL
+            WHERE "profile_id" = ?1
+                AND "deleted_at" = (SELECT "deleted_at" FROM "profiles" WHERE "id" = ?1)
+        "#,
+        )
+        .bind(profile_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(r#"UPDATE "profiles" SET "deleted_at" = NULL WHERE "id" = ?"#)
             .bind(profile_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await
+    }
+
+    /// Permanently delete profiles that were moved to the trash before `before`
+    pub async fn purge_deleted(db: impl SqliteExecutor<'_>, before: DateTime<Utc>) -> DbResult<()> {
+        sqlx::query(r#"DELETE FROM "profiles" WHERE "deleted_at" < ?"#)
+            .bind(before)
             .execute(db)
             .await?;
         Ok(())
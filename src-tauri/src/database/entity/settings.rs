@@ -22,6 +22,122 @@ pub struct SettingsConfig {
     pub minimize_tray: bool,
     pub start_minimized: bool,
     pub port: u16,
+    pub pairing: PairingSettings,
+    pub keep_alive: KeepAliveSettings,
+    pub serial: SerialSettings,
+    pub backup: BackupSettings,
+    pub trash: TrashSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PairingSettings {
+    /// Maximum number of pairing requests a single address can make per minute
+    pub max_requests_per_address: u32,
+    /// Maximum number of pairing requests that can be made per minute
+    pub max_requests_global: u32,
+    /// Maximum number of requests that can be awaiting approval at once
+    pub max_pending_requests: u32,
+    /// Time in seconds before a pending request is automatically declined
+    pub request_expiry: u64,
+    /// Refuse handshakes from unknown devices while pairing is closed
+    pub reject_unknown_devices: bool,
+}
+
+impl Default for PairingSettings {
+    fn default() -> Self {
+        Self {
+            max_requests_per_address: 3,
+            max_requests_global: 20,
+            max_pending_requests: 10,
+            request_expiry: 120,
+            reject_unknown_devices: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeepAliveSettings {
+    /// Time in seconds between keepalive pings sent to devices
+    pub interval: u64,
+    /// Time in seconds without hearing from a device before its
+    /// session is dropped, at least twice the interval
+    pub timeout: u64,
+}
+
+impl Default for KeepAliveSettings {
+    fn default() -> Self {
+        Self {
+            interval: 10,
+            timeout: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SerialSettings {
+    /// Serial ports to connect to devices on
+    pub ports: Vec<SerialPortSettings>,
+    /// Time in seconds between scans for newly plugged in ports
+    pub rescan_interval: u64,
+}
+
+impl Default for SerialSettings {
+    fn default() -> Self {
+        Self {
+            ports: Vec::new(),
+            rescan_interval: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SerialPortSettings {
+    /// Path to the serial port (i.e /dev/ttyACM0)
+    pub path: String,
+    /// Baud rate to open the port with
+    #[serde(default = "default_baud_rate")]
+    pub baud_rate: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    /// Whether automatic backups are enabled
+    pub enabled: bool,
+    /// Time in hours between automatic backups
+    pub interval: u64,
+    /// Number of automatic backups to keep, older backups are removed
+    pub retention: u32,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 24,
+            retention: 7,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrashSettings {
+    /// Number of days items are kept in the trash before being permanently deleted
+    pub retention: u32,
+}
+
+impl Default for TrashSettings {
+    fn default() -> Self {
+        Self { retention: 30 }
+    }
+}
+
+fn default_baud_rate() -> u32 {
+    115200
 }
 
 impl Default for SettingsConfig {
@@ -36,6 +152,11 @@ impl Default for SettingsConfig {
             minimize_tray: false,
             start_minimized: true,
             port: 8532,
+            pairing: Default::default(),
+            keep_alive: Default::default(),
+            serial: Default::default(),
+            backup: Default::default(),
+            trash: Default::default(),
         }
     }
 }
@@ -1,9 +1,11 @@
 use super::device::DeviceId;
 use super::folder::FolderId;
 use crate::database::{DbErr, DbPool, DbResult, JsonObject};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::AssertSqlSafe;
 use sqlx::prelude::FromRow;
+use sqlx::sqlite::SqliteExecutor;
 use tilepad_manifest::icons::IconPackId;
 use tilepad_manifest::plugin::{ActionId, PluginId};
 use uuid::Uuid;
@@ -36,10 +38,19 @@ pub struct TileModel {
     /// Position of the tile
     #[sqlx(json)]
     pub position: TilePosition,
+
+    /// Current value for continuous controls
+    pub value: f64,
+
+    /// When the tile was moved to the trash
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct TilePosition {
+    /// Page of the folder the tile is on
+    #[serde(default)]
+    pub page: u32,
     /// Row within the UI to display at
     pub row: u32,
     /// Column within the UI to display at
@@ -59,6 +70,16 @@ pub struct TileConfig {
     pub icon_options: TileIconOptions,
     /// Label to display on top of the tile
     pub label: TileLabel,
+    /// Kind of control the tile is
+    pub control: TileControl,
+    /// Alternative appearances of the tile, when not empty the current
+    /// state replaces the icon, label and icon options
+    pub states: Vec<TileState>,
+    /// Index of the current state
+    pub state: u32,
+    /// Advance to the next state whenever the tile is clicked
+    /// (Only applies to internal actions)
+    pub cycle_states: bool,
     /// States for whether a part of the config has been modified
     /// by the user or not
     pub user_flags: UserFlags,
@@ -74,6 +95,119 @@ pub struct UserFlags {
     pub label: bool,
 }
 
+/// Appearance of a tile while it is in a specific state
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileState {
+    pub icon: TileIcon,
+    pub label: TileLabel,
+    pub icon_options: TileIconOptions,
+}
+
+impl TileConfig {
+    /// Get the index of the state after the current state, wraps
+    /// back to the first state
+    pub fn next_state(&self) -> Option<u32> {
+        if self.states.is_empty() {
+            return None;
+        }
+
+        Some((self.state + 1) % self.states.len() as u32)
+    }
+
+    /// Get all the icons used by the tile including its states
+    pub fn icons(&self) -> impl Iterator<Item = &TileIcon> {
+        std::iter::once(&self.icon).chain(self.states.iter().map(|state| &state.icon))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileControl {
+    /// Kind of control
+    pub kind: TileControlKind,
+    /// Minimum value for continuous controls
+    pub min: f64,
+    /// Maximum value for continuous controls
+    pub max: f64,
+    /// Size of a single step, values are snapped to a multiple of
+    /// the step and encoder deltas are measured in steps
+    pub step: f64,
+}
+
+impl Default for TileControl {
+    fn default() -> Self {
+        Self {
+            kind: Default::default(),
+            min: 0.0,
+            max: 100.0,
+            step: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TileControlKind {
+    /// Tile is clicked
+    #[default]
+    Button,
+    /// Linear slider with an absolute value
+    Slider,
+    /// Rotary dial with an absolute value
+    Dial,
+    /// Rotary encoder that reports changes relative to the current value
+    Encoder,
+}
+
+/// Input from a continuous control
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum TileValueInput {
+    /// Control was moved to a specific value (Sliders and dials)
+    Absolute { value: f64 },
+    /// Control was moved by a number of steps (Encoders)
+    Delta { delta: f64 },
+}
+
+impl TileControl {
+    /// Whether the control reports continuous values rather than clicks
+    pub fn is_continuous(&self) -> bool {
+        !matches!(self.kind, TileControlKind::Button)
+    }
+
+    /// Apply an input to the `current` value, the result is snapped to
+    /// the step and clamped within the control range
+    pub fn apply_input(&self, current: f64, input: TileValueInput) -> f64 {
+        let value = match input {
+            TileValueInput::Absolute { value } => value,
+            TileValueInput::Delta { delta } => current + delta * self.step,
+        };
+
+        self.clamp_value(value)
+    }
+
+    /// Snap a value to the step and clamp it within the control range
+    pub fn clamp_value(&self, value: f64) -> f64 {
+        let (min, max) = if self.min <= self.max {
+            (self.min, self.max)
+        } else {
+            (self.max, self.min)
+        };
+
+        let value = if self.step > 0.0 && value.is_finite() {
+            min + ((value - min) / self.step).round() * self.step
+        } else {
+            value
+        };
+
+        if value.is_nan() {
+            return min;
+        }
+
+        value.clamp(min, max)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct TileIconOptions {
@@ -212,6 +346,8 @@ impl TileModel {
             plugin_id: create.plugin_id,
             action_id: create.action_id,
             position: create.position,
+            value: 0.0,
+            deleted_at: None,
         };
 
         let config =
@@ -239,6 +375,53 @@ impl TileModel {
         Ok(model)
     }
 
+    /// Create a copy of this tile with a new ID within the folder `folder_id`,
+    /// the properties and value of the tile are copied over
+    pub async fn create_copy(
+        &self,
+        db: impl SqliteExecutor<'_>,
+        folder_id: FolderId,
+        config: TileConfig,
+        position: TilePosition,
+    ) -> DbResult<TileModel> {
+        let model = TileModel {
+            id: Uuid::new_v4(),
+            config,
+            properties: self.properties.clone(),
+            folder_id,
+            plugin_id: self.plugin_id.clone(),
+            action_id: self.action_id.clone(),
+            position,
+            value: self.value,
+            deleted_at: None,
+        };
+
+        let config =
+            serde_json::to_value(&model.config).map_err(|err| DbErr::Encode(err.into()))?;
+        let position =
+            serde_json::to_value(&model.position).map_err(|err| DbErr::Encode(err.into()))?;
+        let properties = serde_json::Value::Object(model.properties.clone());
+
+        sqlx::query(
+            r#"
+            INSERT INTO "tiles" ("id", "config", "properties", "folder_id", "plugin_id", "action_id", "position", "value")
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        )
+        .bind(model.id)
+        .bind(config)
+        .bind(properties)
+        .bind(model.folder_id)
+        .bind(model.plugin_id.0.as_str())
+        .bind(model.action_id.0.as_str())
+        .bind(position)
+        .bind(model.value)
+        .execute(db)
+        .await?;
+
+        Ok(model)
+    }
+
     /// Update the properties for the tile
     pub async fn update_properties(
         mut self,
@@ -280,9 +463,21 @@ impl TileModel {
         Ok(self)
     }
 
+    /// Update the current value of a continuous control
+    pub async fn update_value(mut self, db: &DbPool, value: f64) -> DbResult<TileModel> {
+        sqlx::query(r#"UPDATE "tiles" SET "value" = ? WHERE "id" = ?"#)
+            .bind(value)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        self.value = value;
+        Ok(self)
+    }
+
     pub async fn update_position(
         mut self,
-        db: &DbPool,
+        db: impl SqliteExecutor<'_>,
         position: TilePosition,
     ) -> DbResult<TileModel> {
         let position_value =
@@ -298,6 +493,28 @@ impl TileModel {
         Ok(self)
     }
 
+    /// Move the tile into the folder `folder_id` at `position`
+    pub async fn update_folder(
+        mut self,
+        db: &DbPool,
+        folder_id: FolderId,
+        position: TilePosition,
+    ) -> DbResult<TileModel> {
+        let position_value =
+            serde_json::to_value(&position).map_err(|err| DbErr::Encode(err.into()))?;
+
+        sqlx::query(r#"UPDATE "tiles" SET "folder_id" = ?, "position" = ? WHERE "id" = ?"#)
+            .bind(folder_id)
+            .bind(position_value)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        self.folder_id = folder_id;
+        self.position = position;
+        Ok(self)
+    }
+
     /// Update the label portion of the config
     pub async fn update_label(
         self,
@@ -361,8 +578,49 @@ impl TileModel {
         self.update_config(db, new_config).await
     }
 
-    pub async fn get_by_folder(db: &DbPool, folder_id: FolderId) -> DbResult<Vec<TileModel>> {
-        sqlx::query_as(r#"SELECT * FROM "tiles" WHERE "folder_id" = ?"#)
+    /// Update the states portion of the config, the current state is
+    /// reset if it no longer exists
+    pub async fn update_states(
+        self,
+        db: &DbPool,
+        states: Vec<TileState>,
+        cycle_states: bool,
+    ) -> DbResult<TileModel> {
+        let mut new_config = self.config.clone();
+        if new_config.state as usize >= states.len() {
+            new_config.state = 0;
+        }
+        new_config.states = states;
+        new_config.cycle_states = cycle_states;
+
+        self.update_config(db, new_config).await
+    }
+
+    /// Set the current state of the tile
+    pub async fn update_state(self, db: &DbPool, state: u32) -> DbResult<TileModel> {
+        let mut new_config = self.config.clone();
+        new_config.state = state;
+
+        self.update_config(db, new_config).await
+    }
+
+    /// Update the control portion of the config, the current value
+    /// is clamped to the new control range
+    pub async fn update_control(self, db: &DbPool, control: TileControl) -> DbResult<TileModel> {
+        let value = control.clamp_value(self.value);
+
+        let mut new_config = self.config.clone();
+        new_config.control = control;
+
+        let tile = self.update_config(db, new_config).await?;
+        tile.update_value(db, value).await
+    }
+
+    pub async fn get_by_folder(
+        db: impl SqliteExecutor<'_>,
+        folder_id: FolderId,
+    ) -> DbResult<Vec<TileModel>> {
+        sqlx::query_as(r#"SELECT * FROM "tiles" WHERE "folder_id" = ? AND "deleted_at" IS NULL"#)
             .bind(folder_id)
             .fetch_all(db)
             .await
@@ -382,7 +640,7 @@ impl TileModel {
         let query = format!(
             r#"
             SELECT * FROM "tiles"
-            WHERE "plugin_id" = ? AND "folder_id" IN (
+            WHERE "plugin_id" = ? AND "deleted_at" IS NULL AND "folder_id" IN (
                 SELECT DISTINCT "folder_id" FROM "devices"
                 WHERE "id" IN ({placeholders})
             )
@@ -406,17 +664,209 @@ impl TileModel {
     }
 
     pub async fn get_by_id(db: &DbPool, tile_id: TileId) -> DbResult<Option<TileModel>> {
+        sqlx::query_as(r#"SELECT * FROM "tiles" WHERE "id" = ? AND "deleted_at" IS NULL"#)
+            .bind(tile_id)
+            .fetch_optional(db)
+            .await
+    }
+
+    /// Get a tile including tiles in the trash
+    pub async fn get_by_id_with_deleted(
+        db: impl SqliteExecutor<'_>,
+        tile_id: TileId,
+    ) -> DbResult<Option<TileModel>> {
         sqlx::query_as(r#"SELECT * FROM "tiles" WHERE "id" = ?"#)
             .bind(tile_id)
             .fetch_optional(db)
             .await
     }
 
-    pub async fn delete(db: &DbPool, tile_id: TileId) -> DbResult<()> {
+    /// Get all tiles including the tiles in the trash
+    pub async fn all_with_deleted(db: &DbPool) -> DbResult<Vec<TileModel>> {
+        sqlx::query_as(r#"SELECT * FROM "tiles""#)
+            .fetch_all(db)
+            .await
+    }
+
+    /// Restore the tile to the state of this model, the tile is created again
+    /// if it no longer exists and taken out of the trash if it was deleted
+    pub async fn restore(&self, db: impl SqliteExecutor<'_>) -> DbResult<()> {
+        let config = serde_json::to_value(&self.config).map_err(|err| DbErr::Encode(err.into()))?;
+        let position =
+            serde_json::to_value(&self.position).map_err(|err| DbErr::Encode(err.into()))?;
+        let properties = serde_json::Value::Object(self.properties.clone());
+
+        sqlx::query(
+            r#"
+            INSERT INTO "tiles" ("id", "config", "properties", "folder_id", "plugin_id", "action_id", "position", "value")
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT ("id") DO UPDATE SET
+                "config" = "excluded"."config",
+                "properties" = "excluded"."properties",
+                "folder_id" = "excluded"."folder_id",
+                "position" = "excluded"."position",
+                "value" = "excluded"."value",
+                "deleted_at" = NULL
+        "#,
+        )
+        .bind(self.id)
+        .bind(config)
+        .bind(properties)
+        .bind(self.folder_id)
+        .bind(self.plugin_id.0.as_str())
+        .bind(self.action_id.0.as_str())
+        .bind(position)
+        .bind(self.value)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Move the tile to the trash
+    pub async fn delete(db: impl SqliteExecutor<'_>, tile_id: TileId) -> DbResult<()> {
+        sqlx::query(
+            r#"UPDATE "tiles" SET "deleted_at" = ? WHERE "id" = ? AND "deleted_at" IS NULL"#,
+        )
+        .bind(Utc::now())
+        .bind(tile_id)
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Permanently delete the tile
+    pub async fn purge(db: impl SqliteExecutor<'_>, tile_id: TileId) -> DbResult<()> {
         sqlx::query(r#"DELETE FROM "tiles" WHERE "id" = ?"#)
             .bind(tile_id)
             .execute(db)
             .await?;
         Ok(())
     }
+
+    /// Get all tiles in the trash, tiles within a folder or profile
+    /// that is also in the trash are not included
+    pub async fn all_deleted(db: &DbPool) -> DbResult<Vec<TileModel>> {
+        sqlx::query_as(
+            r#"
+            SELECT "tiles".* FROM "tiles"
+            JOIN "folders" ON "folders"."id" = "tiles"."folder_id"
+            JOIN "profiles" ON "profiles"."id" = "folders"."profile_id"
+            WHERE "tiles"."deleted_at" IS NOT NULL
+                AND "folders"."deleted_at" IS NULL
+                AND "profiles"."deleted_at" IS NULL
+            ORDER BY "tiles"."deleted_at" DESC
+        "#,
+        )
+        .fetch_all(db)
+        .await
+    }
+
+    /// Get a tile in the trash by ID
+    pub async fn get_deleted_by_id(db: &DbPool, tile_id: TileId) -> DbResult<Option<TileModel>> {
+        sqlx::query_as(r#"SELECT * FROM "tiles" WHERE "id" = ? AND "deleted_at" IS NOT NULL"#)
+            .bind(tile_id)
+            .fetch_optional(db)
+            .await
+    }
+
+    /// Restore the tile from the trash at `position`
+    pub async fn restore_deleted(
+        mut self,
+        db: &DbPool,
+        position: TilePosition,
+    ) -> DbResult<TileModel> {
+        let position_json =
+            serde_json::to_value(&position).map_err(|err| DbErr::Encode(err.into()))?;
+
+        sqlx::query(r#"UPDATE "tiles" SET "deleted_at" = NULL, "position" = ? WHERE "id" = ?"#)
+            .bind(position_json)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        self.deleted_at = None;
+        self.position = position;
+        Ok(self)
+    }
+
+    /// Get all tiles that will be permanently deleted when purging the trash of
+    /// everything deleted before `before`, includes the tiles within purged
+    /// folders and profiles
+    pub async fn get_purgeable(db: &DbPool, before: DateTime<Utc>) -> DbResult<Vec<TileModel>> {
+        sqlx::query_as(
+            r#"
+            SELECT "tiles".* FROM "tiles"
+            JOIN "folders" ON "folders"."id" = "tiles"."folder_id"
+            JOIN "profiles" ON "profiles"."id" = "folders"."profile_id"
+            WHERE "tiles"."deleted_at" < ?1
+                OR "folders"."deleted_at" < ?1
+                OR "profiles"."deleted_at" < ?1
+        "#,
+        )
+        .bind(before)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Permanently delete tiles that were moved to the trash before `before`
+    pub async fn purge_deleted(db: impl SqliteExecutor<'_>, before: DateTime<Utc>) -> DbResult<()> {
+        sqlx::query(r#"DELETE FROM "tiles" WHERE "deleted_at" < ?"#)
+            .bind(before)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Create a tile at `position` for use in tests, the tile is not stored
+#[cfg(test)]
+pub fn mock_tile(position: TilePosition) -> TileModel {
+    TileModel {
+        id: Uuid::new_v4(),
+        config: Default::default(),
+        properties: Default::default(),
+        folder_id: Uuid::new_v4(),
+        plugin_id: PluginId::try_from("com.example.test".to_string()).unwrap(),
+        action_id: ActionId::try_from("test".to_string()).unwrap(),
+        position,
+        value: 0.0,
+        deleted_at: None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TileControl, TileControlKind, TileValueInput};
+
+    #[test]
+    fn test_apply_input() {
+        let control = TileControl {
+            kind: TileControlKind::Encoder,
+            min: 0.0,
+            max: 100.0,
+            step: 5.0,
+        };
+
+        assert_eq!(
+            control.apply_input(50.0, TileValueInput::Delta { delta: 2.0 }),
+            60.0
+        );
+        assert_eq!(
+            control.apply_input(95.0, TileValueInput::Delta { delta: 3.0 }),
+            100.0
+        );
+        assert_eq!(
+            control.apply_input(50.0, TileValueInput::Absolute { value: 42.0 }),
+            40.0
+        );
+        assert_eq!(
+            control.apply_input(50.0, TileValueInput::Absolute { value: -10.0 }),
+            0.0
+        );
+        assert_eq!(
+            control.apply_input(50.0, TileValueInput::Absolute { value: f64::NAN }),
+            0.0
+        );
+    }
 }
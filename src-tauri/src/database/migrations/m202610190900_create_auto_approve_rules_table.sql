@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS "auto_approve_rules" (
+	"id"	uuid_text NOT NULL,
+	"name"	varchar NOT NULL,
+	"config"	jsonb_text NOT NULL,
+	"enabled"	boolean NOT NULL,
+	"profile_id"	uuid_text NOT NULL,
+	"folder_id"	uuid_text NOT NULL,
+	"order"	integer NOT NULL DEFAULT 0,
+	"created_at"	datetime_text NOT NULL,
+	PRIMARY KEY("id"),
+	FOREIGN KEY("folder_id") REFERENCES "folders"("id") ON DELETE CASCADE ON UPDATE CASCADE,
+	FOREIGN KEY("profile_id") REFERENCES "profiles"("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
\ No newline at end of file
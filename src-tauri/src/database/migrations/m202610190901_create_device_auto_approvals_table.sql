@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS "device_auto_approvals" (
+	"id"	uuid_text NOT NULL,
+	"rule_id"	uuid_text NOT NULL,
+	"rule_name"	varchar NOT NULL,
+	"device_id"	uuid_text NOT NULL,
+	"device_name"	varchar NOT NULL,
+	"public_key_fingerprint"	varchar NOT NULL,
+	"socket_addr"	varchar NOT NULL,
+	"created_at"	datetime_text NOT NULL,
+	PRIMARY KEY("id")
+);
\ No newline at end of file
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS "device_events" (
+	"id"	uuid_text NOT NULL,
+	"kind"	varchar NOT NULL,
+	"device_id"	uuid_text,
+	"public_key_fingerprint"	varchar,
+	"socket_addr"	varchar,
+	"message"	varchar,
+	"created_at"	datetime_text NOT NULL,
+	PRIMARY KEY("id")
+);
\ No newline at end of file
@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS "device_events_created_at" ON "device_events" ("created_at");
\ No newline at end of file
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS "device_groups" (
+	"id"	uuid_text NOT NULL,
+	"name"	varchar NOT NULL,
+	"config"	jsonb_text NOT NULL,
+	"created_at"	datetime_text NOT NULL,
+	PRIMARY KEY("id")
+);
\ No newline at end of file
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS "device_group_members" (
+	"device_id"	uuid_text NOT NULL,
+	"group_id"	uuid_text NOT NULL,
+	PRIMARY KEY("device_id"),
+	FOREIGN KEY("device_id") REFERENCES "devices"("id") ON DELETE CASCADE ON UPDATE CASCADE,
+	FOREIGN KEY("group_id") REFERENCES "device_groups"("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
\ No newline at end of file
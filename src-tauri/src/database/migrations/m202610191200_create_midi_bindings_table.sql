@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS "midi_bindings" (
+	"id"	uuid_text NOT NULL,
+	"port_name"	varchar NOT NULL,
+	"config"	jsonb_text NOT NULL,
+	"tile_id"	uuid_text NOT NULL,
+	"created_at"	datetime_text NOT NULL,
+	PRIMARY KEY("id"),
+	FOREIGN KEY("tile_id") REFERENCES "tiles"("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE "tiles" ADD COLUMN "value" real NOT NULL DEFAULT 0;
\ No newline at end of file
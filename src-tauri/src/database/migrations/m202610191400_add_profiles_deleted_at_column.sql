@@ -0,0 +1 @@
+ALTER TABLE "profiles" ADD COLUMN "deleted_at" datetime_text NULL;
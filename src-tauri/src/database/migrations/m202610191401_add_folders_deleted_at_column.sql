@@ -0,0 +1 @@
+ALTER TABLE "folders" ADD COLUMN "deleted_at" datetime_text NULL;
@@ -0,0 +1 @@
+ALTER TABLE "tiles" ADD COLUMN "deleted_at" datetime_text NULL;
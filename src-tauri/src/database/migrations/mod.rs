@@ -29,6 +29,50 @@ fn migrations() -> Vec<SqlMigration> {
             "m202504281419_create_settings_table",
             include_str!("m202504281419_create_settings_table.sql"),
         ),
+        SqlMigration::new(
+            "m202610190900_create_auto_approve_rules_table",
+            include_str!("m202610190900_create_auto_approve_rules_table.sql"),
+        ),
+        SqlMigration::new(
+            "m202610190901_create_device_auto_approvals_table",
+            include_str!("m202610190901_create_device_auto_approvals_table.sql"),
+        ),
+        SqlMigration::new(
+            "m202610191000_create_device_events_table",
+            include_str!("m202610191000_create_device_events_table.sql"),
+        ),
+        SqlMigration::new(
+            "m202610191001_create_device_events_created_at_index",
+            include_str!("m202610191001_create_device_events_created_at_index.sql"),
+        ),
+        SqlMigration::new(
+            "m202610191100_create_device_groups_table",
+            include_str!("m202610191100_create_device_groups_table.sql"),
+        ),
+        SqlMigration::new(
+            "m202610191101_create_device_group_members_table",
+            include_str!("m202610191101_create_device_group_members_table.sql"),
+        ),
+        SqlMigration::new(
+            "m202610191200_create_midi_bindings_table",
+            include_str!("m202610191200_create_midi_bindings_table.sql"),
+        ),
+        SqlMigration::new(
+            "m202610191300_add_tiles_value_column",
+            include_str!("m202610191300_add_tiles_value_column.sql"),
+        ),
+        SqlMigration::new(
+            "m202610191400_add_profiles_deleted_at_column",
+            include_str!("m202610191400_add_profiles_deleted_at_column.sql"),
+        ),
+        SqlMigration::new(
+            "m202610191401_add_folders_deleted_at_column",
+            include_str!("m202610191401_add_folders_deleted_at_column.sql"),
+        ),
+        SqlMigration::new(
+            "m202610191402_add_tiles_deleted_at_column",
+            include_str!("m202610191402_add_tiles_deleted_at_column.sql"),
+        ),
     ]
 }
 
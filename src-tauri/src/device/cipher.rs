@@ -0,0 +1,260 @@
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce, aead::Aead};
+use hkdf::Hkdf;
+use parking_lot::Mutex;
+use sha2::Sha256;
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+use thiserror::Error;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+/// Info string used when deriving session keys
+const SESSION_KEY_INFO: &[u8] = b"tilepad device session v2";
+
+pub type SessionCipherRef = Arc<SessionCipher>;
+
+/// Cipher for a single device session.
+///
+/// Keys are derived from both an ephemeral key exchange (For forward secrecy)
+/// and a static key exchange (For authenticating both sides). Each direction
+/// uses its own key.
+///
+/// Nonces contain a little-endian message counter in the first 8 bytes, the
+/// remaining bytes are zero. Received counters must always increase, messages
+/// that reuse a previous counter are rejected as replays.
+pub struct SessionCipher {
+    /// Cipher for messages sent to the client
+    send_cipher: XChaCha20Poly1305,
+    /// Cipher for messages received from the client
+    recv_cipher: XChaCha20Poly1305,
+    /// Counter of the last message sent
+    send_counter: AtomicU64,
+    /// Counter of the last message received
+    recv_counter: Mutex<u64>,
+}
+
+#[derive(Debug, Error)]
+pub enum SessionCipherError {
+    #[error("failed to derive session keys")]
+    DeriveKeys,
+
+    #[error("failed to encrypt message")]
+    Encrypt,
+
+    #[error("failed to decrypt message")]
+    Decrypt,
+
+    #[error("message counter was reused")]
+    Replayed,
+}
+
+/// Public keys of both parties in the handshake
+pub struct HandshakeKeys {
+    pub server_public_key: PublicKey,
+    pub server_ephemeral_public_key: PublicKey,
+    pub client_public_key: PublicKey,
+    pub client_ephemeral_public_key: PublicKey,
+}
+
+impl SessionCipher {
+    /// Derives the session cipher for the server side of a handshake
+    pub fn derive_server(
+        server_private_key: &StaticSecret,
+        server_ephemeral_key: EphemeralSecret,
+        keys: &HandshakeKeys,
+    ) -> Result<SessionCipher, SessionCipherError> {
+        let ephemeral_secret =
+            server_ephemeral_key.diffie_hellman(&keys.client_ephemeral_public_key);
+        let static_secret = server_private_key.diffie_hellman(&keys.client_public_key);
+
+        // Handshake contributed no randomness (Low order client keys)
+        if !ephemeral_secret.was_contributory() || !static_secret.was_contributory() {
+            return Err(SessionCipherError::DeriveKeys);
+        }
+
+        let mut input_key = [0u8; 64];
+        input_key[..32].copy_from_slice(ephemeral_secret.as_bytes());
+        input_key[32..].copy_from_slice(static_secret.as_bytes());
+
+        // Bind the keys to the public keys used in the handshake
+        let mut transcript = [0u8; 128];
+        transcript[..32].copy_from_slice(keys.client_public_key.as_bytes());
+        transcript[32..64].copy_from_slice(keys.client_ephemeral_public_key.as_bytes());
+        transcript[64..96].copy_from_slice(keys.server_public_key.as_bytes());
+        transcript[96..].copy_from_slice(keys.server_ephemeral_public_key.as_bytes());
+
+        let hkdf = Hkdf::<Sha256>::new(Some(&transcript[..]), &input_key);
+
+        // First half is the client to server key, second is server to client
+        let mut output_key = [0u8; 64];
+        hkdf.expand(SESSION_KEY_INFO, &mut output_key)
+            .map_err(|_| SessionCipherError::DeriveKeys)?;
+
+        let recv_cipher = XChaCha20Poly1305::new_from_slice(&output_key[..32])
+            .map_err(|_| SessionCipherError::DeriveKeys)?;
+        let send_cipher = XChaCha20Poly1305::new_from_slice(&output_key[32..])
+            .map_err(|_| SessionCipherError::DeriveKeys)?;
+
+        Ok(SessionCipher {
+            send_cipher,
+            recv_cipher,
+            send_counter: AtomicU64::new(0),
+            recv_counter: Mutex::new(0),
+        })
+    }
+
+    /// Encrypt a message to send, returns the encrypted message and its nonce
+    pub fn encrypt(&self, message: &[u8]) -> Result<(Vec<u8>, [u8; 24]), SessionCipherError> {
+        let counter = self.send_counter.fetch_add(1, Ordering::SeqCst) + 1;
+        let nonce = counter_nonce(counter);
+
+        let encrypted = self
+            .send_cipher
+            .encrypt(&XNonce::from(nonce), message)
+            .map_err(|_| SessionCipherError::Encrypt)?;
+
+        Ok((encrypted, nonce))
+    }
+
+    /// Decrypt a received message, rejects messages whose counter is
+    /// not greater than the last received message
+    pub fn decrypt(&self, message: &[u8], nonce: [u8; 24]) -> Result<Vec<u8>, SessionCipherError> {
+        let counter = nonce_counter(&nonce).ok_or(SessionCipherError::Decrypt)?;
+        let recv_counter = &mut *self.recv_counter.lock();
+
+        if counter <= *recv_counter {
+            return Err(SessionCipherError::Replayed);
+        }
+
+        let decrypted = self
+            .recv_cipher
+            .decrypt(&XNonce::from(nonce), message)
+            .map_err(|_| SessionCipherError::Decrypt)?;
+
+        // Counter is only advanced for authentic messages
+        *recv_counter = counter;
+
+        Ok(decrypted)
+    }
+}
+
+/// Create a nonce for the provided message counter
+fn counter_nonce(counter: u64) -> [u8; 24] {
+    let mut nonce = [0u8; 24];
+    nonce[..8].copy_from_slice(&counter.to_le_bytes());
+    nonce
+}
+
+/// Get the message counter from a nonce, [None] if the nonce
+/// is not a valid counter nonce
+fn nonce_counter(nonce: &[u8; 24]) -> Option<u64> {
+    if nonce[8..].iter().any(|value| *value != 0) {
+        return None;
+    }
+
+    let mut counter = [0u8; 8];
+    counter.copy_from_slice(&nonce[..8]);
+    Some(u64::from_le_bytes(counter))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HandshakeKeys, SessionCipher, SessionCipherError, counter_nonce};
+    use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce, aead::Aead};
+    use hkdf::Hkdf;
+    use sha2::Sha256;
+    use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+    /// Create the server cipher and the client to server key the
+    /// way a client would derive it
+    fn create_session() -> (SessionCipher, XChaCha20Poly1305) {
+        let server_key = StaticSecret::random();
+        let server_ephemeral_key = EphemeralSecret::random();
+        let client_key = StaticSecret::random();
+        let client_ephemeral_key = StaticSecret::random();
+
+        let keys = HandshakeKeys {
+            server_public_key: PublicKey::from(&server_key),
+            server_ephemeral_public_key: PublicKey::from(&server_ephemeral_key),
+            client_public_key: PublicKey::from(&client_key),
+            client_ephemeral_public_key: PublicKey::from(&client_ephemeral_key),
+        };
+
+        // Client side derivation
+        let mut input_key = [0u8; 64];
+        input_key[..32].copy_from_slice(
+            client_ephemeral_key
+                .diffie_hellman(&keys.server_ephemeral_public_key)
+                .as_bytes(),
+        );
+        input_key[32..].copy_from_slice(
+            client_key
+                .diffie_hellman(&keys.server_public_key)
+                .as_bytes(),
+        );
+
+        let mut transcript = Vec::new();
+        transcript.extend_from_slice(keys.client_public_key.as_bytes());
+        transcript.extend_from_slice(keys.client_ephemeral_public_key.as_bytes());
+        transcript.extend_from_slice(keys.server_public_key.as_bytes());
+        transcript.extend_from_slice(keys.server_ephemeral_public_key.as_bytes());
+
+        let mut output_key = [0u8; 64];
+        Hkdf::<Sha256>::new(Some(&transcript[..]), &input_key)
+            .expand(b"tilepad device session v2", &mut output_key)
+            .unwrap();
+
+        let client_send = XChaCha20Poly1305::new_from_slice(&output_key[..32]).unwrap();
+
+        let server =
+            SessionCipher::derive_server(&server_key, server_ephemeral_key, &keys).unwrap();
+
+        (server, client_send)
+    }
+
+    #[test]
+    fn test_decrypt_client_message() {
+        let (server, client) = create_session();
+
+        let nonce = counter_nonce(1);
+        let message = client
+            .encrypt(&XNonce::from(nonce), b"hello".as_slice())
+            .unwrap();
+
+        assert_eq!(server.decrypt(&message, nonce).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_reject_replayed_message() {
+        let (server, client) = create_session();
+
+        let nonce = counter_nonce(1);
+        let message = client
+            .encrypt(&XNonce::from(nonce), b"hello".as_slice())
+            .unwrap();
+
+        server.decrypt(&message, nonce).unwrap();
+
+        assert!(matches!(
+            server.decrypt(&message, nonce),
+            Err(SessionCipherError::Replayed)
+        ));
+    }
+
+    #[test]
+    fn test_sessions_use_different_keys() {
+        let (server_a, _) = create_session();
+        let (_, client_b) = create_session();
+
+        let nonce = counter_nonce(1);
+        let message = client_b
+            .encrypt(&XNonce::from(nonce), b"hello".as_slice())
+            .unwrap();
+
+        assert!(matches!(
+            server_a.decrypt(&message, nonce),
+            Err(SessionCipherError::Decrypt)
+        ));
+    }
+}
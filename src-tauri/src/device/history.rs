@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+
+use crate::database::entity::{
+    device::DeviceModel,
+    folder::{FolderId, FolderModel},
+    profile::ProfileId,
+};
+
+/// Maximum number of folders remembered for each device
+const MAX_FOLDER_HISTORY: usize = 32;
+
+/// Navigation history for a device
+#[derive(Default)]
+pub struct NavigationHistory {
+    /// Folders previously visited within the current profile, most recent last
+    folders: VecDeque<FolderId>,
+
+    /// Profile the device was using before the current profile
+    previous_profile: Option<ProfileId>,
+}
+
+impl NavigationHistory {
+    /// Record `device` navigating from its current folder to `folder`. Moving
+    /// to another profile clears the folder history as folders from the
+    /// previous profile are not reachable through back navigation
+    pub fn record(&mut self, device: &DeviceModel, folder: &FolderModel) {
+        if device.profile_id != folder.profile_id {
+            self.folders.clear();
+            self.previous_profile = Some(device.profile_id);
+            return;
+        }
+
+        if device.folder_id == folder.id {
+            return;
+        }
+
+        self.folders.push_back(device.folder_id);
+        if self.folders.len() > MAX_FOLDER_HISTORY {
+            self.folders.pop_front();
+        }
+    }
+
+    /// Take the most recently visited folder
+    pub fn pop_folder(&mut self) -> Option<FolderId> {
+        self.folders.pop_back()
+    }
+
+    /// Get the profile the device was using before its current profile
+    pub fn previous_profile(&self) -> Option<ProfileId> {
+        self.previous_profile
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use super::{MAX_FOLDER_HISTORY, NavigationHistory};
+    use crate::database::entity::{device::DeviceModel, folder::FolderModel};
+
+    fn device(profile_id: Uuid, folder_id: Uuid) -> DeviceModel {
+        DeviceModel {
+            id: Uuid::new_v4(),
+            name: "Test".to_string(),
+            public_key: Vec::new(),
+            config: Default::default(),
+            profile_id,
+            folder_id,
+            order: 0,
+            created_at: Utc::now(),
+            last_connected_at: Utc::now(),
+        }
+    }
+
+    fn folder(profile_id: Uuid) -> FolderModel {
+        FolderModel {
+            id: Uuid::new_v4(),
+            name: "Test".to_string(),
+            config: Default::default(),
+            profile_id,
+            default: false,
+            order: 0,
+            deleted_at: None,
+        }
+    }
+
+    /// Folder history is capped and cleared when switching profile
+    #[test]
+    fn test_history_capped_and_reset() {
+        let profile_id = Uuid::new_v4();
+        let mut history = NavigationHistory::default();
+
+        let mut current = Uuid::new_v4();
+        for _ in 0..MAX_FOLDER_HISTORY + 5 {
+            let next = folder(profile_id);
+            history.record(&device(profile_id, current), &next);
+            current = next.id;
+        }
+
+        assert_eq!(history.folders.len(), MAX_FOLDER_HISTORY);
+
+        history.record(&device(profile_id, current), &folder(Uuid::new_v4()));
+
+        assert!(history.pop_folder().is_none());
+        assert_eq!(history.previous_profile(), Some(profile_id));
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::entity::{
+        device::DeviceId,
+        folder::{FolderLayout, FolderLayoutTarget, FolderModel, ScreenSizeClass},
+        tile::TileModel,
+    },
+    tile::grid::TileGrid,
+};
+
+/// Screen reported by a device
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DeviceScreen {
+    /// Width of the screen in logical pixels
+    pub width: u32,
+    /// Height of the screen in logical pixels
+    pub height: u32,
+}
+
+impl DeviceScreen {
+    /// Get the size class of the screen based on its smallest dimension
+    pub fn size_class(&self) -> ScreenSizeClass {
+        match self.width.min(self.height) {
+            0..600 => ScreenSizeClass::Small,
+            600..1000 => ScreenSizeClass::Medium,
+            _ => ScreenSizeClass::Large,
+        }
+    }
+}
+
+impl ScreenSizeClass {
+    /// Maximum number of columns to use when reflowing a
+    /// folder without a layout for this size class
+    fn max_columns(&self) -> Option<u32> {
+        match self {
+            ScreenSizeClass::Small => Some(4),
+            ScreenSizeClass::Medium => Some(6),
+            ScreenSizeClass::Large => None,
+        }
+    }
+}
+
+/// Resolve the layout of a folder for a specific device, a layout defined for
+/// the device is preferred followed by a layout for the size class of its screen.
+///
+/// When no layout is defined the folder is reflowed to fit the size class of
+/// the screen, devices that have not reported a screen get the folder as is
+pub fn resolve_layout(
+    mut folder: FolderModel,
+    mut tiles: Vec<TileModel>,
+    device_id: DeviceId,
+    screen: Option<DeviceScreen>,
+) -> (FolderModel, Vec<TileModel>) {
+    let size_class = screen.map(|screen| screen.size_class());
+
+    let layout = folder
+        .config
+        .layouts
+        .iter()
+        .find(|layout| {
+            matches!(layout.target, FolderLayoutTarget::Device { device_id: id } if id == device_id)
+        })
+        .or_else(|| {
+            let size_class = size_class?;
+            folder.config.layouts.iter().find(|layout| {
+                matches!(layout.target, FolderLayoutTarget::SizeClass { size_class: class } if class == size_class)
+            })
+        })
+        .cloned();
+
+    match layout {
+        Some(layout) => apply_layout(&mut folder, &mut tiles, layout),
+        None => {
+            let max_columns = size_class.and_then(|size_class| size_class.max_columns());
+
+            if let Some(max_columns) = max_columns
+                && folder.config.columns > max_columns
+            {
+                folder.config.columns = max_columns;
+                folder.config.rows = reflow_tiles(&mut tiles, max_columns, folder.config.rows);
+            }
+        }
+    }
+
+    // Variants are only needed by the server
+    folder.config.layouts.clear();
+
+    (folder, tiles)
+}
+
+/// Apply a layout to the folder and tiles, tiles without a position
+/// in the layout are placed into the first free space on their page
+fn apply_layout(folder: &mut FolderModel, tiles: &mut [TileModel], layout: FolderLayout) {
+    let columns = layout.columns.max(1);
+    let mut grids = PageGrids::new(columns);
+    let mut unplaced = Vec::new();
+
+    for (index, tile) in tiles.iter_mut().enumerate() {
+        match layout.positions.get(&tile.id) {
+            Some(position) => {
+                tile.position = position.clone();
+                grids.get(tile.position.page).fill(&tile.position);
+            }
+            None => unplaced.push(index),
+        }
+    }
+
+    for index in unplaced {
+        let tile = &mut tiles[index];
+        tile.position = grids.get(tile.position.page).place(&tile.position);
+    }
+
+    folder.config.columns = columns;
+    folder.config.rows = layout.rows.max(grids.rows());
+}
+
+/// Reflow tiles into a grid with `columns` columns maintaining their reading
+/// order within each page, returns the number of rows needed (At least `min_rows`)
+fn reflow_tiles(tiles: &mut [TileModel], columns: u32, min_rows: u32) -> u32 {
+    let mut order: Vec<usize> = (0..tiles.len()).collect();
+    order.sort_by_key(|index| {
+        let position = &tiles[*index].position;
+        (position.page, position.row, position.column)
+    });
+
+    let mut grids = PageGrids::new(columns);
+
+    for index in order {
+        let tile = &mut tiles[index];
+        tile.position = grids.get(tile.position.page).place(&tile.position);
+    }
+
+    grids.rows().max(min_rows)
+}
+
+/// Separate grid for each page of a folder
+struct PageGrids {
+    columns: u32,
+    grids: HashMap<u32, TileGrid>,
+}
+
+impl PageGrids {
+    fn new(columns: u32) -> Self {
+        Self {
+            columns,
+            grids: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, page: u32) -> &mut TileGrid {
+        let columns = self.columns;
+        self.grids
+            .entry(page)
+            .or_insert_with(|| TileGrid::new(columns))
+    }
+
+    /// Rows needed to fit the largest page
+    fn rows(&self) -> u32 {
+        self.grids.values().map(TileGrid::rows).max().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use uuid::Uuid;
+
+    use super::{DeviceScreen, resolve_layout};
+    use crate::database::entity::{
+        folder::{FolderConfig, FolderLayout, FolderLayoutTarget, FolderModel, ScreenSizeClass},
+        tile::{TileModel, TilePosition, mock_tile},
+    };
+
+    const PHONE: DeviceScreen = DeviceScreen {
+        width: 400,
+        height: 800,
+    };
+
+    fn folder(rows: u32, columns: u32, layouts: Vec<FolderLayout>) -> FolderModel {
+        FolderModel {
+            id: Uuid::new_v4(),
+            name: "Test".to_string(),
+            config: FolderConfig {
+                rows,
+                columns,
+                pages: 1,
+                layouts,
+            },
+            profile_id: Uuid::new_v4(),
+            default: true,
+            order: 0,
+            deleted_at: None,
+        }
+    }
+
+    fn tile(row: u32, column: u32) -> TileModel {
+        mock_tile(TilePosition {
+            page: 0,
+            row,
+            column,
+            row_span: 1,
+            column_span: 1,
+        })
+    }
+
+    fn cell(tile: &TileModel) -> (u32, u32) {
+        (tile.position.row, tile.position.column)
+    }
+
+    /// Folders are sent unchanged to devices without a screen
+    #[test]
+    fn test_no_screen_unchanged() {
+        let tiles = vec![tile(0, 5)];
+        let (folder, tiles) = resolve_layout(folder(4, 6, Vec::new()), tiles, Uuid::new_v4(), None);
+
+        assert_eq!(folder.config.columns, 6);
+        assert_eq!(cell(&tiles[0]), (0, 5));
+    }
+
+    /// Folders wider than a small screen are reflowed in reading order
+    #[test]
+    fn test_reflow_small_screen() {
+        let tiles = vec![tile(1, 0), tile(0, 5), tile(0, 0)];
+        let (folder, tiles) =
+            resolve_layout(folder(2, 6, Vec::new()), tiles, Uuid::new_v4(), Some(PHONE));
+
+        assert_eq!(folder.config.columns, 4);
+        assert_eq!(cell(&tiles[2]), (0, 0));
+        assert_eq!(cell(&tiles[1]), (0, 1));
+        assert_eq!(cell(&tiles[0]), (0, 2));
+    }
+
+    /// Device layouts are preferred over size class layouts and tiles
+    /// missing from the layout are placed in free space
+    #[test]
+    fn test_device_layout_preferred() {
+        let device_id = Uuid::new_v4();
+        let tiles = vec![tile(0, 0), tile(0, 1)];
+
+        let layouts = vec![
+            FolderLayout {
+                target: FolderLayoutTarget::SizeClass {
+                    size_class: ScreenSizeClass::Small,
+                },
+                rows: 6,
+                columns: 2,
+                positions: HashMap::new(),
+            },
+            FolderLayout {
+                target: FolderLayoutTarget::Device { device_id },
+                rows: 3,
+                columns: 3,
+                positions: HashMap::from([(
+                    tiles[1].id,
+                    TilePosition {
+                        page: 0,
+                        row: 0,
+                        column: 0,
+                        row_span: 1,
+                        column_span: 1,
+                    },
+                )]),
+            },
+        ];
+
+        let (folder, tiles) = resolve_layout(folder(4, 6, layouts), tiles, device_id, Some(PHONE));
+
+        assert_eq!(folder.config.rows, 3);
+        assert_eq!(folder.config.columns, 3);
+        assert!(folder.config.layouts.is_empty());
+        assert_eq!(cell(&tiles[1]), (0, 0));
+        assert_eq!(cell(&tiles[0]), (0, 1));
+    }
+}
@@ -2,32 +2,99 @@ use crate::{
     database::{
         DbPool,
         entity::{
+            auto_approve_rule::AutoApproveRuleModel,
             device::{CreateDevice, DeviceConfig, DeviceId, DeviceModel},
+            device_auto_approval::{CreateDeviceAutoApproval, DeviceAutoApprovalModel},
+            device_event::{CreateDeviceEvent, DeviceEventKind, DeviceEventModel},
+            device_group::DeviceGroupModel,
             folder::{FolderId, FolderModel},
             profile::{ProfileId, ProfileModel},
-            tile::{TileId, TileModel},
+            settings::{KeepAliveSettings, PairingSettings, SerialSettings},
+            tile::{TileId, TileModel, TileValueInput},
         },
     },
-    device::protocol::DeviceIndicator,
+    device::protocol::{DeviceIndicator, ServerDeviceMessageEncrypted},
     events::{
         AppEvent, AppEventSender, DeviceAppEvent, DeviceRequestAppEvent, DisplayContext,
         TileInteractionContext,
     },
+    midi::{MIDI_DEVICE_ID, Midi},
     plugin::Plugins,
-    utils::encryption::ServerKeyPair,
+    utils::encryption::{ServerKeyPair, ServerKeys, key_rotation_proof, public_key_fingerprint},
 };
 use anyhow::Context;
-use parking_lot::RwLock;
+use chrono::{DateTime, TimeDelta, Utc};
+use history::NavigationHistory;
+use pairing::{DeviceRequestError, PairingLimiter};
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
-use session::{DeviceSessionId, DeviceSessionRef};
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use session::{DeviceSessionId, DeviceSessionRef, SessionKeepAlive};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    net::{IpAddr, SocketAddr},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 use uuid::Uuid;
+use x25519_dalek::PublicKey;
 
+pub mod cipher;
+pub mod history;
+pub mod layout;
+pub mod pairing;
 pub mod protocol;
+pub mod serial;
 pub mod session;
 
+/// Interval between checks for expired device requests
+const REQUEST_EXPIRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Interval between checks for a previous server key that should be retired
+const KEY_RETIREMENT_INTERVAL: Duration = Duration::from_secs(60);
+
 pub type DeviceRequestId = Uuid;
 
+/// Address a device session is connected from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DeviceAddr {
+    /// Device connected over the network
+    Socket(SocketAddr),
+    /// Device connected over a serial port at the provided path
+    Serial(String),
+}
+
+impl DeviceAddr {
+    /// Get the IP address of network devices
+    pub fn ip(&self) -> Option<IpAddr> {
+        match self {
+            DeviceAddr::Socket(addr) => Some(addr.ip()),
+            DeviceAddr::Serial(_) => None,
+        }
+    }
+}
+
+impl Display for DeviceAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceAddr::Socket(addr) => addr.fmt(f),
+            DeviceAddr::Serial(path) => write!(f, "serial:{path}"),
+        }
+    }
+}
+
+impl Serialize for DeviceAddr {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
 /// Store for device sessions and requests
 pub struct Devices {
     /// Sender for app events
@@ -39,20 +106,66 @@ pub struct Devices {
     /// Access to the plugins registry
     plugins: Arc<Plugins>,
 
+    /// Access to MIDI input for LED feedback
+    midi: Arc<Midi>,
+
     /// Current device socket sessions
     sessions: RwLock<HashMap<DeviceSessionId, DeviceSessionRef>>,
 
+    /// Sessions that are authenticated as each device
+    device_sessions: RwLock<HashMap<DeviceId, HashSet<DeviceSessionId>>>,
+
+    /// Page of its folder that each device is currently viewing
+    device_pages: RwLock<HashMap<DeviceId, u32>>,
+
+    /// Navigation history of each device
+    navigation_history: Mutex<HashMap<DeviceId, NavigationHistory>>,
+
     /// Current requests for authorization from devices
     requests: RwLock<Vec<DeviceRequest>>,
 
-    /// Server key pair
-    server_key_pair: ServerKeyPair,
+    /// Server keys
+    server_keys: RwLock<ServerKeys>,
+
+    /// Settings for device pairing
+    pairing_settings: RwLock<PairingSettings>,
+
+    /// Rate limiter for pairing requests
+    pairing_limiter: Mutex<PairingLimiter>,
+
+    /// Whether pairing is currently open for unknown devices
+    pairing_open: AtomicBool,
+
+    /// Settings for device session keepalive
+    keep_alive_settings: RwLock<KeepAliveSettings>,
+
+    /// Settings for serial devices
+    serial_settings: RwLock<SerialSettings>,
+
+    /// Paths of serial ports that currently have a session
+    serial_ports: Mutex<HashSet<String>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ConnectedDevice {
     pub device_id: DeviceId,
     pub session_id: DeviceSessionId,
+    /// Address of the session socket
+    pub socket_addr: DeviceAddr,
+    /// Last time anything was received from the device
+    pub last_seen: Option<DateTime<Utc>>,
+    /// Round trip time to the device in milliseconds
+    pub rtt_ms: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerKeyInfo {
+    /// Fingerprint of the current server public key
+    pub public_key_fingerprint: String,
+    /// Fingerprint of the previous server public key during a transition window
+    pub previous_public_key_fingerprint: Option<String>,
+    /// When the previous server key will be retired
+    pub previous_retire_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Clone, Serialize)]
@@ -60,13 +173,15 @@ pub struct DeviceRequest {
     /// Unique ID for the request itself
     id: DeviceRequestId,
     /// Address of the connecting device
-    socket_addr: SocketAddr,
+    socket_addr: DeviceAddr,
     /// ID of the session the request is for
     session_id: DeviceSessionId,
     /// Name of the device requesting approval
     device_name: String,
     /// Client public key
     client_public_key: [u8; 32],
+    /// When the request was created
+    created_at: DateTime<Utc>,
 }
 
 impl Devices {
@@ -74,19 +189,170 @@ impl Devices {
         event_tx: AppEventSender,
         db: DbPool,
         plugins: Arc<Plugins>,
-        server_key_pair: ServerKeyPair,
+        midi: Arc<Midi>,
+        server_keys: ServerKeys,
+        pairing_settings: PairingSettings,
+        keep_alive_settings: KeepAliveSettings,
+        serial_settings: SerialSettings,
     ) -> Self {
         Self {
             event_tx,
             db,
             plugins,
-            server_key_pair,
+            midi,
+            server_keys: RwLock::new(server_keys),
+            pairing_settings: RwLock::new(pairing_settings),
+            keep_alive_settings: RwLock::new(keep_alive_settings),
+            serial_settings: RwLock::new(serial_settings),
 
             sessions: Default::default(),
+            device_sessions: Default::default(),
+            device_pages: Default::default(),
+            navigation_history: Default::default(),
             requests: Default::default(),
+            pairing_limiter: Default::default(),
+            pairing_open: Default::default(),
+            serial_ports: Default::default(),
         }
     }
 
+    /// Update the settings used for device pairing
+    pub fn set_pairing_settings(&self, settings: PairingSettings) {
+        *self.pairing_settings.write() = settings;
+    }
+
+    /// Update the settings used for keepalive, only applies
+    /// to new device sessions
+    pub fn set_keep_alive_settings(&self, settings: KeepAliveSettings) {
+        *self.keep_alive_settings.write() = settings;
+    }
+
+    /// Get the keepalive configuration for a new device session
+    pub fn get_keep_alive(&self) -> SessionKeepAlive {
+        let settings = &*self.keep_alive_settings.read();
+        let interval = settings.interval.max(1);
+        let timeout = settings.timeout.max(interval * 2);
+
+        SessionKeepAlive {
+            interval: Duration::from_secs(interval),
+            timeout: Duration::from_secs(timeout),
+        }
+    }
+
+    /// Set whether pairing is open to unknown devices
+    pub fn set_pairing_open(&self, open: bool) {
+        self.pairing_open.store(open, Ordering::SeqCst);
+    }
+
+    /// Check whether pairing is open to unknown devices
+    pub fn is_pairing_open(&self) -> bool {
+        self.pairing_open.load(Ordering::SeqCst)
+    }
+
+    /// Get the server key pair a handshake should use, `public_key` is
+    /// the server key the device has pinned if it has one
+    pub fn get_server_key_pair(&self, public_key: Option<&[u8; 32]>) -> ServerKeyPair {
+        self.server_keys
+            .read()
+            .get_key_pair(public_key, Utc::now())
+            .clone()
+    }
+
+    /// Get details about the current server keys
+    pub fn get_server_key_info(&self) -> ServerKeyInfo {
+        let keys = &*self.server_keys.read();
+
+        ServerKeyInfo {
+            public_key_fingerprint: public_key_fingerprint(keys.current.public_key.as_bytes()),
+            previous_public_key_fingerprint: keys
+                .previous
+                .as_ref()
+                .map(|previous| public_key_fingerprint(previous.key_pair.public_key.as_bytes())),
+            previous_retire_at: keys.previous.as_ref().map(|previous| previous.retire_at),
+        }
+    }
+
+    /// Replace the server key with a new key, the current key remains valid
+    /// for handshakes until the end of the transition `window`. Connected
+    /// devices are sent proof of the new key so they can pin it
+    pub fn rotate_server_key(&self, window: TimeDelta) -> anyhow::Result<ServerKeyInfo> {
+        self.server_keys
+            .write()
+            .rotate(window, Utc::now())
+            .context("failed to rotate server key")?;
+
+        tracing::info!("rotated server key");
+
+        let sessions: Vec<DeviceSessionRef> = self.sessions.read().values().cloned().collect();
+        for session in sessions {
+            session.on_server_key_rotated();
+        }
+
+        Ok(self.get_server_key_info())
+    }
+
+    /// Create the message proving possession of the current server key to a device
+    /// whose session was established using `server_public_key`, [None] if the session
+    /// is already using the current key
+    pub fn create_server_key_rotation(
+        &self,
+        server_public_key: &[u8; 32],
+        client_public_key: &[u8; 32],
+    ) -> Option<ServerDeviceMessageEncrypted> {
+        let keys = &*self.server_keys.read();
+
+        if keys.current.public_key.as_bytes() == server_public_key {
+            return None;
+        }
+
+        let proof = key_rotation_proof(
+            &keys.current.private_key,
+            &PublicKey::from(*server_public_key),
+            &PublicKey::from(*client_public_key),
+        );
+
+        let retire_at = keys
+            .previous
+            .as_ref()
+            .filter(|previous| previous.key_pair.public_key.as_bytes() == server_public_key)
+            .map(|previous| previous.retire_at)
+            .unwrap_or_else(Utc::now);
+
+        Some(ServerDeviceMessageEncrypted::ServerKeyRotated {
+            server_public_key: keys.current.public_key.to_bytes(),
+            proof,
+            retire_at,
+        })
+    }
+
+    /// Background task to retire the previous server key once its
+    /// transition window has ended
+    pub async fn run_key_retirement(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(KEY_RETIREMENT_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            match self.server_keys.write().retire_previous(Utc::now()) {
+                Ok(true) => tracing::info!("retired previous server key"),
+                Ok(false) => {}
+                Err(cause) => tracing::error!(?cause, "failed to retire previous server key"),
+            }
+        }
+    }
+
+    /// Record an event in the persistent device audit log, the
+    /// event is stored in the background
+    pub fn record_event(&self, kind: DeviceEventKind, create: CreateDeviceEvent) {
+        let db = self.db.clone();
+
+        tokio::spawn(async move {
+            if let Err(cause) = DeviceEventModel::create(&db, kind, create).await {
+                tracing::error!(?cause, ?kind, "failed to record device event");
+            }
+        });
+    }
+
     /// Insert a new session
     pub fn insert_session(&self, session_id: DeviceSessionId, session_ref: DeviceSessionRef) {
         self.sessions.write().insert(session_id, session_ref);
@@ -104,9 +370,13 @@ impl Devices {
             .iter()
             .filter_map(|(session_id, session_ref)| {
                 let device_id = session_ref.get_device_id()?;
+                let presence = session_ref.presence();
                 Some(ConnectedDevice {
                     device_id,
                     session_id: *session_id,
+                    socket_addr: session_ref.socket_addr(),
+                    last_seen: presence.last_seen,
+                    rtt_ms: presence.rtt_ms,
                 })
             })
             .collect()
@@ -114,22 +384,66 @@ impl Devices {
 
     /// Get all device IDs that have active sessions
     pub fn get_connected_device_ids(&self) -> Vec<DeviceId> {
-        self.sessions
-            .read()
-            .values()
-            .filter_map(|session_ref| {
-                let device_id = session_ref.get_device_id()?;
-                Some(device_id)
-            })
+        self.device_sessions.read().keys().copied().collect()
+    }
+
+    /// Track a session that has authenticated as `device_id`
+    pub fn add_device_session(&self, device_id: DeviceId, session_id: DeviceSessionId) {
+        self.device_sessions
+            .write()
+            .entry(device_id)
+            .or_default()
+            .insert(session_id);
+    }
+
+    /// Stop tracking a session for `device_id`
+    fn remove_device_session(&self, device_id: DeviceId, session_id: DeviceSessionId) {
+        let device_sessions = &mut *self.device_sessions.write();
+        if let Some(sessions) = device_sessions.get_mut(&device_id) {
+            sessions.remove(&session_id);
+            if sessions.is_empty() {
+                device_sessions.remove(&device_id);
+            }
+        }
+    }
+
+    /// Get all sessions that are authenticated as `device_id`
+    pub fn get_sessions_by_device(&self, device_id: DeviceId) -> Vec<DeviceSessionRef> {
+        let device_sessions = self.device_sessions.read();
+        let session_ids = match device_sessions.get(&device_id) {
+            Some(value) => value,
+            None => return Vec::new(),
+        };
+
+        let sessions = self.sessions.read();
+        session_ids
+            .iter()
+            .filter_map(|session_id| sessions.get(session_id).cloned())
             .collect()
     }
 
     /// Remove a session
-    pub fn remove_session(&self, session_id: DeviceSessionId, device_id: Option<DeviceId>) {
+    pub fn remove_session(
+        &self,
+        session_id: DeviceSessionId,
+        socket_addr: &DeviceAddr,
+        device_id: Option<DeviceId>,
+    ) {
         self.sessions.write().remove(&session_id);
         self.remove_session_device_requests(session_id);
 
         if let Some(device_id) = device_id {
+            self.remove_device_session(device_id, session_id);
+
+            self.record_event(
+                DeviceEventKind::Disconnected,
+                CreateDeviceEvent {
+                    device_id: Some(device_id),
+                    socket_addr: Some(socket_addr.to_string()),
+                    ..Default::default()
+                },
+            );
+
             _ = self
                 .event_tx
                 .send(AppEvent::Device(DeviceAppEvent::Disconnected { device_id }));
@@ -164,30 +478,110 @@ impl Devices {
         Some(request)
     }
 
-    /// Add a new device request
+    /// Add a new device request, fails if pairing is closed or the
+    /// request would exceed the pairing limits
     pub fn add_device_request(
         &self,
         session_id: DeviceSessionId,
-        socket_addr: SocketAddr,
+        socket_addr: DeviceAddr,
         device_name: String,
         client_public_key: [u8; 32],
-    ) {
+    ) -> Result<(), DeviceRequestError> {
         self.remove_session_device_requests(session_id);
 
+        let settings = { self.pairing_settings.read().clone() };
+
+        if settings.reject_unknown_devices && !self.is_pairing_open() {
+            return Err(DeviceRequestError::PairingClosed);
+        }
+
         let request_id = Uuid::new_v4();
-        self.requests.write().push(DeviceRequest {
-            id: request_id,
-            socket_addr,
-            session_id,
-            device_name,
-            client_public_key,
-        });
+
+        {
+            // Check the limit and push under the same lock so concurrent
+            // requests cannot exceed the limit
+            let requests = &mut *self.requests.write();
+
+            if requests.len() >= settings.max_pending_requests as usize {
+                return Err(DeviceRequestError::TooManyPending);
+            }
+
+            self.pairing_limiter
+                .lock()
+                .try_acquire(&settings, socket_addr.ip(), Instant::now())?;
+
+            requests.push(DeviceRequest {
+                id: request_id,
+                socket_addr,
+                session_id,
+                device_name,
+                client_public_key,
+                created_at: Utc::now(),
+            });
+        }
 
         _ = self
             .event_tx
             .send(AppEvent::DeviceRequest(DeviceRequestAppEvent::Added {
                 request_id,
             }));
+
+        Ok(())
+    }
+
+    /// Background task that declines device requests once they
+    /// have been pending for longer than the request expiry time
+    pub async fn run_request_expiry(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(REQUEST_EXPIRY_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            self.expire_device_requests();
+        }
+    }
+
+    /// Decline all device requests that have expired
+    pub fn expire_device_requests(&self) {
+        let expiry = { self.pairing_settings.read().request_expiry };
+        let expiry = TimeDelta::seconds(expiry as i64);
+        let now = Utc::now();
+
+        let mut expired = Vec::new();
+
+        self.requests.write().retain(|request| {
+            if now - request.created_at < expiry {
+                return true;
+            }
+
+            expired.push(request.clone());
+            false
+        });
+
+        for request in expired {
+            tracing::debug!(request_id = ?request.id, "device request expired");
+
+            if let Some(session) = self.get_session(&request.session_id) {
+                session.decline();
+            }
+
+            self.record_event(
+                DeviceEventKind::Declined,
+                CreateDeviceEvent {
+                    public_key_fingerprint: Some(public_key_fingerprint(
+                        &request.client_public_key,
+                    )),
+                    socket_addr: Some(request.socket_addr.to_string()),
+                    message: Some("request expired".to_string()),
+                    ..Default::default()
+                },
+            );
+
+            _ = self
+                .event_tx
+                .send(AppEvent::DeviceRequest(DeviceRequestAppEvent::Removed {
+                    request_id: request.id,
+                }));
+        }
     }
 
     pub fn get_device_requests(&self) -> Vec<DeviceRequest> {
@@ -216,7 +610,7 @@ impl Devices {
             CreateDevice {
                 name: request.device_name,
                 public_key: request.client_public_key.to_vec(),
-                config: DeviceConfig {},
+                config: DeviceConfig::default(),
                 profile_id: default_folder.profile_id,
                 folder_id: default_folder.id,
             },
@@ -225,6 +619,16 @@ impl Devices {
 
         session.on_approved(device.id);
 
+        self.record_event(
+            DeviceEventKind::Approved,
+            CreateDeviceEvent {
+                device_id: Some(device.id),
+                public_key_fingerprint: Some(public_key_fingerprint(&request.client_public_key)),
+                socket_addr: Some(request.socket_addr.to_string()),
+                ..Default::default()
+            },
+        );
+
         // Notify frontend
         _ = self
             .event_tx
@@ -241,6 +645,75 @@ impl Devices {
         Ok(())
     }
 
+    /// Attempt to automatically approve an unknown device using the auto approve
+    /// rules, creates a new device in the database when a rule matches
+    pub async fn try_auto_approve_device(
+        &self,
+        socket_addr: &DeviceAddr,
+        device_name: &str,
+        client_public_key: &[u8; 32],
+    ) -> anyhow::Result<Option<DeviceId>> {
+        let db = &self.db;
+        let fingerprint = public_key_fingerprint(client_public_key);
+        // Rules targeting a profile or folder in the trash are skipped
+        let rules = AutoApproveRuleModel::all_available(db).await?;
+
+        let rule = match rules.into_iter().find(|rule| {
+            rule.config
+                .matches(socket_addr.ip(), device_name, &fingerprint)
+        }) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        let device = DeviceModel::create(
+            db,
+            CreateDevice {
+                name: device_name.to_string(),
+                public_key: client_public_key.to_vec(),
+                config: DeviceConfig::default(),
+                profile_id: rule.profile_id,
+                folder_id: rule.folder_id,
+            },
+        )
+        .await?;
+
+        self.record_event(
+            DeviceEventKind::AutoApproved,
+            CreateDeviceEvent {
+                device_id: Some(device.id),
+                public_key_fingerprint: Some(fingerprint.clone()),
+                socket_addr: Some(socket_addr.to_string()),
+                message: Some(format!("approved by rule \"{}\"", rule.name)),
+            },
+        );
+
+        // Record the approval in the audit trail
+        DeviceAutoApprovalModel::create(
+            db,
+            CreateDeviceAutoApproval {
+                rule_id: rule.id,
+                rule_name: rule.name,
+                device_id: device.id,
+                device_name: device.name,
+                public_key_fingerprint: fingerprint,
+                socket_addr: socket_addr.to_string(),
+            },
+        )
+        .await?;
+
+        tracing::info!(device_id = ?device.id, rule_id = ?rule.id, "device auto approved");
+
+        // Notify frontend
+        _ = self
+            .event_tx
+            .send(AppEvent::Device(DeviceAppEvent::Authenticated {
+                device_id: device.id,
+            }));
+
+        Ok(Some(device.id))
+    }
+
     // Decline a device request
     pub fn decline_device_request(&self, request_id: DeviceRequestId) -> anyhow::Result<()> {
         let request = self
@@ -252,6 +725,15 @@ impl Devices {
 
         session.decline();
 
+        self.record_event(
+            DeviceEventKind::Declined,
+            CreateDeviceEvent {
+                public_key_fingerprint: Some(public_key_fingerprint(&request.client_public_key)),
+                socket_addr: Some(request.socket_addr.to_string()),
+                ..Default::default()
+            },
+        );
+
         _ = self
             .event_tx
             .send(AppEvent::DeviceRequest(DeviceRequestAppEvent::Decline {
@@ -261,21 +743,10 @@ impl Devices {
         Ok(())
     }
 
-    /// Find a session from its device ID
-    pub fn get_session_by_device(&self, device_id: DeviceId) -> Option<DeviceSessionRef> {
-        self.sessions.read().values().find_map(|session_ref| {
-            let session_device_id = session_ref.get_device_id()?;
-            if session_device_id != device_id {
-                return None;
-            }
-
-            Some(session_ref.clone())
-        })
-    }
-
     /// Attempt to authenticate a session using a access token
     pub async fn attempt_authenticate_device(
         &self,
+        socket_addr: &DeviceAddr,
         public_key: &[u8],
     ) -> anyhow::Result<Option<DeviceId>> {
         let mut device = match DeviceModel::get_by_public_key(&self.db, public_key).await? {
@@ -286,6 +757,16 @@ impl Devices {
         // Update last connected
         device.set_connected_now(&self.db).await?;
 
+        self.record_event(
+            DeviceEventKind::Connected,
+            CreateDeviceEvent {
+                device_id: Some(device.id),
+                public_key_fingerprint: Some(public_key_fingerprint(public_key)),
+                socket_addr: Some(socket_addr.to_string()),
+                ..Default::default()
+            },
+        );
+
         // Notify frontend
         _ = self
             .event_tx
@@ -300,18 +781,36 @@ impl Devices {
     pub async fn revoke_device(&self, device_id: DeviceId) -> anyhow::Result<()> {
         DeviceModel::delete(&self.db, device_id).await?;
 
+        self.record_event(
+            DeviceEventKind::Revoked,
+            CreateDeviceEvent {
+                device_id: Some(device_id),
+                socket_addr: self
+                    .get_sessions_by_device(device_id)
+                    .first()
+                    .map(|session| session.socket_addr().to_string()),
+                ..Default::default()
+            },
+        );
+
         _ = self
             .event_tx
             .send(AppEvent::Device(DeviceAppEvent::Revoked { device_id }));
 
-        // Tell the session its been revoked
-        if let Some(session) = self.get_session_by_device(device_id) {
+        // Tell the sessions they have been revoked
+        for session in self.get_sessions_by_device(device_id) {
             session.revoke();
         }
 
+        self.device_sessions.write().remove(&device_id);
+        self.device_pages.write().remove(&device_id);
+        self.navigation_history.lock().remove(&device_id);
+
         Ok(())
     }
 
+    /// Get the current folder and tiles for a device, the layout of the
+    /// folder is resolved for the provided device screen
     pub async fn request_device_tiles(
         &self,
         device_id: DeviceId,
@@ -329,6 +828,44 @@ impl Devices {
         Ok((folder, tiles))
     }
 
+    /// Get the page of its folder that a device is currently viewing
+    pub fn get_device_page(&self, device_id: DeviceId, folder: &FolderModel) -> u32 {
+        let page = self
+            .device_pages
+            .read()
+            .get(&device_id)
+            .copied()
+            .unwrap_or_default();
+
+        page.min(folder.config.pages.max(1) - 1)
+    }
+
+    /// Move a device `offset` pages through its current folder, the page
+    /// is kept within the pages of the folder
+    pub async fn change_device_page(&self, device_id: DeviceId, offset: i32) -> anyhow::Result<()> {
+        let (folder, tiles) = self.request_device_tiles(device_id).await?;
+
+        let current = self.get_device_page(device_id, &folder);
+        let last = folder.config.pages.max(1) - 1;
+        let page = current.saturating_add_signed(offset).min(last);
+
+        if page == current {
+            return Ok(());
+        }
+
+        self.device_pages.write().insert(device_id, page);
+
+        // Inform plugins of the tiles now visible on the device
+        self.plugins
+            .set_device_tiles(device_id, &get_page_tiles(&tiles, page))?;
+
+        for session in self.get_sessions_by_device(device_id) {
+            session.on_tiles(tiles.clone(), folder.clone());
+        }
+
+        Ok(())
+    }
+
     pub async fn update_device_profile(
         &self,
         device_id: DeviceId,
@@ -338,16 +875,30 @@ impl Devices {
         let device = DeviceModel::get_by_id(db, device_id)
             .await?
             .context("device not found")?;
-        let folder = FolderModel::get_default(db, profile_id)
+
+        anyhow::ensure!(
+            device.config.can_access_profile(profile_id),
+            "device is not allowed to access this profile"
+        );
+
+        let folder = get_profile_entry_folder(db, &device.config, profile_id, None)
             .await?
             .context("unknown folder")?;
         let tiles = TileModel::get_by_folder(db, folder.id).await?;
 
+        self.record_navigation(&device, &folder);
+
         // Update the profile on the device
-        device.set_profile(db, profile_id, folder.id).await?;
+        self.apply_device_folder(device, folder.clone(), tiles)
+            .await?;
 
-        if let Some(session) = self.get_session_by_device(device_id) {
-            session.on_tiles(tiles, folder);
+        // Failing to mirror does not fail the navigation itself
+        if let Err(cause) = self.mirror_device_group(device_id, folder).await {
+            tracing::error!(
+                ?cause,
+                ?device_id,
+                "failed to mirror device group navigation"
+            );
         }
 
         Ok(())
@@ -365,21 +916,229 @@ impl Devices {
         let folder = FolderModel::get_by_id(db, folder_id)
             .await?
             .context("unknown folder")?;
+
+        anyhow::ensure!(
+            device.config.can_access_folder(&folder),
+            "device is not allowed to access this folder"
+        );
+
+        let tiles = TileModel::get_by_folder(db, folder.id).await?;
+
+        self.record_navigation(&device, &folder);
+
+        self.apply_device_folder(device, folder.clone(), tiles)
+            .await?;
+
+        // Failing to mirror does not fail the navigation itself
+        if let Err(cause) = self.mirror_device_group(device_id, folder).await {
+            tracing::error!(
+                ?cause,
+                ?device_id,
+                "failed to mirror device group navigation"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Record a device navigating to `folder` in its navigation history
+    fn record_navigation(&self, device: &DeviceModel, folder: &FolderModel) {
+        self.navigation_history
+            .lock()
+            .entry(device.id)
+            .or_default()
+            .record(device, folder);
+    }
+
+    /// Navigate a device back to the last folder it visited within its current
+    /// profile, folders that no longer exist or that the device can no longer
+    /// access are skipped
+    pub async fn navigate_device_back(&self, device_id: DeviceId) -> anyhow::Result<()> {
+        let db = &self.db;
+        let device = DeviceModel::get_by_id(db, device_id)
+            .await?
+            .context("device not found")?;
+
+        let folder = loop {
+            let folder_id = self
+                .navigation_history
+                .lock()
+                .get_mut(&device_id)
+                .and_then(|history| history.pop_folder())
+                .context("no previous folder to navigate back to")?;
+
+            if let Some(folder) = FolderModel::get_by_id(db, folder_id).await?
+                && folder.profile_id == device.profile_id
+                && device.config.can_access_folder(&folder)
+            {
+                break folder;
+            }
+        };
+
         let tiles = TileModel::get_by_folder(db, folder.id).await?;
 
-        device.set_profile(db, folder.profile_id, folder_id).await?;
+        // Going back is not recorded so repeated back navigation
+        // continues further through the history
+        self.apply_device_folder(device, folder.clone(), tiles)
+            .await?;
+
+        // Failing to mirror does not fail the navigation itself
+        if let Err(cause) = self.mirror_device_group(device_id, folder).await {
+            tracing::error!(
+                ?cause,
+                ?device_id,
+                "failed to mirror device group navigation"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Navigate a device to the default folder of its current profile
+    pub async fn navigate_device_home(&self, device_id: DeviceId) -> anyhow::Result<()> {
+        let db = &self.db;
+        let device = DeviceModel::get_by_id(db, device_id)
+            .await?
+            .context("device not found")?;
+
+        let folder = get_profile_entry_folder(db, &device.config, device.profile_id, None)
+            .await?
+            .context("unknown folder")?;
+
+        if folder.id == device.folder_id {
+            return Ok(());
+        }
+
+        self.update_device_folder(device_id, folder.id).await
+    }
+
+    /// Navigate a device to the profile it was using before its current profile
+    pub async fn navigate_device_previous_profile(
+        &self,
+        device_id: DeviceId,
+    ) -> anyhow::Result<()> {
+        let profile_id = self
+            .navigation_history
+            .lock()
+            .get(&device_id)
+            .and_then(|history| history.previous_profile())
+            .context("no previous profile to navigate to")?;
+
+        self.update_device_profile(device_id, profile_id).await
+    }
+
+    /// Move a device to `folder`, informs plugins and the device
+    /// sessions of the new tiles
+    async fn apply_device_folder(
+        &self,
+        device: DeviceModel,
+        folder: FolderModel,
+        tiles: Vec<TileModel>,
+    ) -> anyhow::Result<DeviceModel> {
+        let device = device
+            .set_profile(&self.db, folder.profile_id, folder.id)
+            .await?;
+
+        // New folders are always opened on the first page
+        self.device_pages.write().remove(&device.id);
 
         // Inform plugins of the new tile sets
-        self.plugins.set_device_tiles(device_id, &tiles)?;
+        self.plugins
+            .set_device_tiles(device.id, &get_page_tiles(&tiles, 0))?;
 
         // Inform the device of its new tile set
-        if let Some(session) = self.get_session_by_device(device_id) {
-            session.on_tiles(tiles, folder);
+        for session in self.get_sessions_by_device(device.id) {
+            session.on_tiles(tiles.clone(), folder.clone());
+        }
+
+        Ok(device)
+    }
+
+    /// Mirror a device navigating to `folder` onto the other members of its
+    /// group, members navigate to the paired folder when one is configured
+    async fn mirror_device_group(
+        &self,
+        device_id: DeviceId,
+        folder: FolderModel,
+    ) -> anyhow::Result<()> {
+        let db = &self.db;
+        let group = match DeviceGroupModel::get_by_device(db, device_id).await? {
+            Some(value) => value,
+            None => return Ok(()),
+        };
+
+        let mirror_folder_id = group.config.get_mirror_folder(folder.id);
+        let mirror_folder = if mirror_folder_id == folder.id {
+            folder
+        } else {
+            match FolderModel::get_by_id(db, mirror_folder_id).await? {
+                Some(value) => value,
+                None => {
+                    tracing::warn!(?mirror_folder_id, "device group paired folder not found");
+                    folder
+                }
+            }
+        };
+
+        let tiles = TileModel::get_by_folder(db, mirror_folder.id).await?;
+        let members = DeviceGroupModel::get_members(db, group.id).await?;
+
+        for member_id in members {
+            if member_id == device_id {
+                continue;
+            }
+
+            let member = match DeviceModel::get_by_id(db, member_id).await? {
+                Some(value) => value,
+                None => continue,
+            };
+
+            // Member is already there or cannot follow
+            if member.folder_id == mirror_folder.id
+                || !member.config.can_access_folder(&mirror_folder)
+            {
+                continue;
+            }
+
+            self.apply_device_folder(member, mirror_folder.clone(), tiles.clone())
+                .await?;
         }
 
         Ok(())
     }
 
+    /// Update the configuration for a device, moves the device to an accessible
+    /// folder if its current folder is no longer allowed by the new config
+    pub async fn update_device_config(
+        &self,
+        device_id: DeviceId,
+        config: DeviceConfig,
+    ) -> anyhow::Result<DeviceModel> {
+        let db = &self.db;
+        let device = DeviceModel::get_by_id(db, device_id)
+            .await?
+            .context("device not found")?;
+        let current_folder = FolderModel::get_by_id(db, device.folder_id)
+            .await?
+            .context("unknown folder")?;
+
+        // Device can still access its current folder
+        if config.can_access_folder(&current_folder) {
+            let device = device.set_config(db, config).await?;
+            return Ok(device);
+        }
+
+        let folder = get_accessible_folder(db, &config)
+            .await?
+            .context("device must be allowed to access at least one folder")?;
+
+        let device = device.set_config(db, config).await?;
+        let tiles = TileModel::get_by_folder(db, folder.id).await?;
+        let device = self.apply_device_folder(device, folder, tiles).await?;
+
+        Ok(device)
+    }
+
     /// Spawn a background task to update the provided folder on all devices
     pub fn background_update_folder(self: &Arc<Self>, folder_id: FolderId) {
         let devices = self.clone();
@@ -407,7 +1166,7 @@ impl Devices {
 
         devices
             .iter()
-            .filter_map(|device| self.get_session_by_device(device.id))
+            .flat_map(|device| self.get_sessions_by_device(device.id))
             .for_each(|session| {
                 session.on_tiles(tiles.clone(), folder.clone());
             });
@@ -421,8 +1180,8 @@ impl Devices {
         ctx: DisplayContext,
         message: serde_json::Value,
     ) -> anyhow::Result<()> {
-        if let Some(session) = self.get_session_by_device(ctx.device_id) {
-            session.on_plugin_message(ctx, message);
+        for session in self.get_sessions_by_device(ctx.device_id) {
+            session.on_plugin_message(ctx.clone(), message.clone());
         }
 
         Ok(())
@@ -437,6 +1196,59 @@ impl Devices {
             .await?
             .context("tile instance not found")?;
 
+        let context = TileInteractionContext {
+            device_id,
+            plugin_id: tile.plugin_id.clone(),
+            action_id: tile.action_id.clone(),
+            tile_id,
+        };
+
+        self.plugins
+            .handle_action(self, context, tile.properties.clone())
+            .await?;
+
+        // Internal actions are not aware of states so cycle them automatically
+        if tile.config.cycle_states
+            && self.plugins.is_internal_plugin(&tile.plugin_id)
+            && let Some(state) = tile.config.next_state()
+        {
+            let tile = tile.update_state(&self.db, state).await?;
+            self.update_tile_state_devices(tile.folder_id, tile.id, state)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Handle a device moving a continuous control, the new value is stored
+    /// on the tile, pushed to all devices and passed to the plugin
+    pub async fn device_change_tile_value(
+        &self,
+        device_id: DeviceId,
+        tile_id: TileId,
+        input: TileValueInput,
+    ) -> anyhow::Result<()> {
+        let tile = TileModel::get_by_id(&self.db, tile_id)
+            .await?
+            .context("tile instance not found")?;
+
+        anyhow::ensure!(
+            tile.config.control.is_continuous(),
+            "tile is not a continuous control"
+        );
+
+        let previous = tile.value;
+        let value = tile.config.control.apply_input(previous, input);
+        let tile = tile.update_value(&self.db, value).await?;
+
+        self.update_tile_value_devices(tile.folder_id, tile.id, value)
+            .await?;
+
+        // Value did not change (i.e already at the end of the range)
+        if value == previous {
+            return Ok(());
+        }
+
         let context = TileInteractionContext {
             device_id,
             plugin_id: tile.plugin_id,
@@ -445,12 +1257,62 @@ impl Devices {
         };
 
         self.plugins
-            .handle_action(self, context, tile.properties)
+            .handle_value_changed(
+                self,
+                context,
+                tile.properties,
+                &tile.config.control,
+                value,
+                value - previous,
+            )
             .await?;
 
         Ok(())
     }
 
+    /// Get the sessions of all devices using the provided `folder_id` folder
+    async fn get_folder_sessions(
+        &self,
+        folder_id: FolderId,
+    ) -> anyhow::Result<Vec<DeviceSessionRef>> {
+        let devices = DeviceModel::all_by_folder(&self.db, folder_id).await?;
+
+        Ok(devices
+            .iter()
+            .flat_map(|device| self.get_sessions_by_device(device.id))
+            .collect())
+    }
+
+    /// Push the current value of a tile to all devices using
+    /// the provided `folder_id` folder
+    pub async fn update_tile_value_devices(
+        &self,
+        folder_id: FolderId,
+        tile_id: TileId,
+        value: f64,
+    ) -> anyhow::Result<()> {
+        for session in self.get_folder_sessions(folder_id).await? {
+            session.on_tile_value(tile_id, value);
+        }
+
+        Ok(())
+    }
+
+    /// Push the current state of a tile to all devices using
+    /// the provided `folder_id` folder
+    pub async fn update_tile_state_devices(
+        &self,
+        folder_id: FolderId,
+        tile_id: TileId,
+        state: u32,
+    ) -> anyhow::Result<()> {
+        for session in self.get_folder_sessions(folder_id).await? {
+            session.on_tile_state(tile_id, state);
+        }
+
+        Ok(())
+    }
+
     pub fn display_tile_indicator(
         &self,
         device_id: DeviceId,
@@ -458,8 +1320,134 @@ impl Devices {
         indicator: DeviceIndicator,
         duration: u32,
     ) {
-        if let Some(session) = self.get_session_by_device(device_id) {
+        if device_id == MIDI_DEVICE_ID {
+            self.midi
+                .display_tile_indicator(tile_id, indicator, duration);
+            return;
+        }
+
+        for session in self.get_sessions_by_device(device_id) {
             session.on_tile_indicator(tile_id, indicator, duration);
         }
     }
 }
+
+/// Get the tiles from `tiles` that are on the folder page `page`
+fn get_page_tiles(tiles: &[TileModel], page: u32) -> Vec<TileModel> {
+    tiles
+        .iter()
+        .filter(|tile| tile.position.page == page)
+        .cloned()
+        .collect()
+}
+
+/// Get the folder a device should be placed in when switching to the provided
+/// profile, this is the default folder unless the device is not allowed to access
+/// it in which case its the first folder it is allowed to access. The folder
+/// `exclude_folder` is never chosen
+async fn get_profile_entry_folder(
+    db: &DbPool,
+    config: &DeviceConfig,
+    profile_id: ProfileId,
+    exclude_folder: Option<FolderId>,
+) -> anyhow::Result<Option<FolderModel>> {
+    let is_available = |folder: &FolderModel| {
+        Some(folder.id) != exclude_folder && config.can_access_folder(folder)
+    };
+
+    if let Some(folder) = FolderModel::get_default(db, profile_id).await?
+        && is_available(&folder)
+    {
+        return Ok(Some(folder));
+    }
+
+    let folders = FolderModel::all(db, profile_id).await?;
+    Ok(folders.into_iter().find(is_available))
+}
+
+/// Get the folder to move a device to when the profile `exclude_profile` or the
+/// folder `exclude_folder` is deleted, prefers the entry folder of `profile_id`
+/// followed by the folders of the default profile
+pub async fn get_relocation_folder(
+    db: &DbPool,
+    config: &DeviceConfig,
+    profile_id: ProfileId,
+    exclude_profile: Option<ProfileId>,
+    exclude_folder: Option<FolderId>,
+) -> anyhow::Result<Option<FolderModel>> {
+    let mut profiles = ProfileModel::all(db).await?;
+
+    // Check the preferred profile first then the default profile
+    profiles.sort_by_key(|profile| (profile.id != profile_id, !profile.default));
+
+    for profile in profiles {
+        if Some(profile.id) == exclude_profile || !config.can_access_profile(profile.id) {
+            continue;
+        }
+
+        if let Some(folder) =
+            get_profile_entry_folder(db, config, profile.id, exclude_folder).await?
+        {
+            return Ok(Some(folder));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Get the folders to move `devices` to when the profile `exclude_profile` or the
+/// folder `exclude_folder` is deleted, fails if any of the devices are not allowed
+/// to access another folder
+pub async fn get_relocation_folders(
+    db: &DbPool,
+    devices: Vec<DeviceModel>,
+    profile_id: ProfileId,
+    exclude_profile: Option<ProfileId>,
+    exclude_folder: Option<FolderId>,
+) -> anyhow::Result<Vec<(DeviceModel, FolderModel)>> {
+    let mut relocations = Vec::with_capacity(devices.len());
+
+    for device in devices {
+        let folder = get_relocation_folder(
+            db,
+            &device.config,
+            profile_id,
+            exclude_profile,
+            exclude_folder,
+        )
+        .await?
+        .with_context(|| {
+            format!(
+                "device \"{}\" is not allowed to access any other folder",
+                device.name
+            )
+        })?;
+        relocations.push((device, folder));
+    }
+
+    Ok(relocations)
+}
+
+/// Find any folder the device is allowed to access, prefers
+/// folders within the default profile
+async fn get_accessible_folder(
+    db: &DbPool,
+    config: &DeviceConfig,
+) -> anyhow::Result<Option<FolderModel>> {
+    let mut profiles = ProfileModel::all(db).await?;
+
+    // Check the default profile first
+    profiles.sort_by_key(|profile| !profile.default);
+
+    for profile in profiles {
+        if !config.can_access_profile(profile.id) {
+            continue;
+        }
+
+        if let Some(folder) = get_profile_entry_folder(db, config, profile.id, None).await? {
+            return Ok(Some(folder));
+        }
+    }
+
+    Ok(None)
+}
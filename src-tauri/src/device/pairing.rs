@@ -0,0 +1,152 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+use crate::database::entity::settings::PairingSettings;
+
+/// Window that request rate limits are counted over
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Error)]
+pub enum DeviceRequestError {
+    #[error("pairing is currently closed")]
+    PairingClosed,
+
+    #[error("too many pairing requests from this address")]
+    AddressRateLimited,
+
+    #[error("too many pairing requests")]
+    RateLimited,
+
+    #[error("too many pending pairing requests")]
+    TooManyPending,
+}
+
+/// Tracks pairing request attempts for rate limiting
+#[derive(Default)]
+pub struct PairingLimiter {
+    /// Times of recent requests for each address
+    by_address: HashMap<IpAddr, VecDeque<Instant>>,
+
+    /// Times of all recent requests
+    global: VecDeque<Instant>,
+}
+
+impl PairingLimiter {
+    /// Check if a new request from `addr` is allowed by the rate limits in
+    /// `settings`, records the attempt when its allowed. Devices without an
+    /// address (i.e serial devices) are only subject to the global limit
+    pub fn try_acquire(
+        &mut self,
+        settings: &PairingSettings,
+        addr: Option<IpAddr>,
+        now: Instant,
+    ) -> Result<(), DeviceRequestError> {
+        self.prune(now);
+
+        if let Some(addr) = addr
+            && self.by_address.get(&addr).is_some_and(|attempts| {
+                attempts.len() >= settings.max_requests_per_address as usize
+            })
+        {
+            return Err(DeviceRequestError::AddressRateLimited);
+        }
+
+        if self.global.len() >= settings.max_requests_global as usize {
+            return Err(DeviceRequestError::RateLimited);
+        }
+
+        if let Some(addr) = addr {
+            self.by_address.entry(addr).or_default().push_back(now);
+        }
+
+        self.global.push_back(now);
+
+        Ok(())
+    }
+
+    /// Remove attempts that are outside of the rate limit window
+    fn prune(&mut self, now: Instant) {
+        let is_expired = |time: &Instant| now.duration_since(*time) >= RATE_LIMIT_WINDOW;
+
+        while self.global.front().is_some_and(is_expired) {
+            self.global.pop_front();
+        }
+
+        self.by_address.retain(|_, attempts| {
+            while attempts.front().is_some_and(is_expired) {
+                attempts.pop_front();
+            }
+
+            !attempts.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        time::{Duration, Instant},
+    };
+
+    use super::{DeviceRequestError, PairingLimiter, RATE_LIMIT_WINDOW};
+    use crate::database::entity::settings::PairingSettings;
+
+    #[test]
+    fn test_address_rate_limit() {
+        let settings = PairingSettings {
+            max_requests_per_address: 2,
+            max_requests_global: 10,
+            ..Default::default()
+        };
+
+        let mut limiter = PairingLimiter::default();
+        let addr_a = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 2));
+        let addr_b = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 3));
+        let now = Instant::now();
+
+        limiter.try_acquire(&settings, Some(addr_a), now).unwrap();
+        limiter.try_acquire(&settings, Some(addr_a), now).unwrap();
+
+        assert!(matches!(
+            limiter.try_acquire(&settings, Some(addr_a), now),
+            Err(DeviceRequestError::AddressRateLimited)
+        ));
+
+        // Other addresses are not affected
+        limiter.try_acquire(&settings, Some(addr_b), now).unwrap();
+
+        // Attempts outside the window are forgotten
+        let later = now + RATE_LIMIT_WINDOW + Duration::from_secs(1);
+        limiter.try_acquire(&settings, Some(addr_a), later).unwrap();
+    }
+
+    #[test]
+    fn test_global_rate_limit() {
+        let settings = PairingSettings {
+            max_requests_per_address: 10,
+            max_requests_global: 2,
+            ..Default::default()
+        };
+
+        let mut limiter = PairingLimiter::default();
+        let now = Instant::now();
+
+        limiter
+            .try_acquire(&settings, Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))), now)
+            .unwrap();
+        limiter
+            .try_acquire(&settings, Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))), now)
+            .unwrap();
+
+        assert!(matches!(
+            limiter.try_acquire(&settings, Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3))), now),
+            Err(DeviceRequestError::RateLimited)
+        ));
+    }
+}
@@ -1,22 +1,51 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
-    database::entity::{folder::FolderModel, tile::TileModel},
+    database::entity::{
+        folder::FolderModel,
+        tile::{TileModel, TileValueInput},
+    },
+    device::layout::DeviceScreen,
     events::DisplayContext,
 };
 
+/// Version of the device protocol implemented by the server, version 2
+/// replaced the static key exchange with a per-session ephemeral key exchange
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Protocol version assumed for clients that do not specify a version
+fn legacy_protocol_version() -> u32 {
+    1
+}
+
 /// Device message coming from the client side
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type")]
 pub enum ClientDeviceMessage {
     /// Authenticate using a device access token
     InitiateHandshake {
+        /// Version of the protocol the client implements, clients
+        /// older than version 2 do not send a version
+        #[serde(default = "legacy_protocol_version")]
+        protocol_version: u32,
+
         /// Name of the device
         name: String,
 
         /// Client public key to authenticate using
         public_key: [u8; 32],
+
+        /// Single use public key generated by the client for this session,
+        /// required from protocol version 2
+        #[serde(default)]
+        ephemeral_public_key: Option<[u8; 32]>,
+
+        /// Server public key the client has pinned, used to select the
+        /// server key during a key rotation transition window
+        #[serde(default)]
+        server_public_key: Option<[u8; 32]>,
     },
 
     /// Response to an authentication challenge
@@ -32,9 +61,17 @@ pub enum ClientDeviceMessage {
         /// Encrypted message
         message: Vec<u8>,
 
-        /// Nonce for the message
+        /// Nonce for the message, contains the little-endian message
+        /// counter which must increase with every message
         nonce: [u8; 24],
     },
+
+    /// Keepalive ping from the client, the server responds with a
+    /// [ServerDeviceMessage::Pong] containing the same sequence
+    Ping { sequence: u64 },
+
+    /// Response to a [ServerDeviceMessage::Ping]
+    Pong { sequence: u64 },
 }
 
 /// Device message coming from the server side
@@ -45,9 +82,15 @@ pub enum ServerDeviceMessage {
     /// a challenge to ensure the client actually owns the private key to the
     /// public key it specified
     AuthenticateChallenge {
+        /// Version of the protocol used by the server
+        protocol_version: u32,
+
         /// Public key of the server
         server_public_key: [u8; 32],
 
+        /// Single use public key generated by the server for this session
+        server_ephemeral_public_key: [u8; 32],
+
         /// Encrypted challenge
         challenge: Vec<u8>,
 
@@ -65,13 +108,25 @@ pub enum ServerDeviceMessage {
 
     /// Error occurred
     Error { message: String },
+
+    /// Keepalive ping from the server, the client must respond with a
+    /// [ClientDeviceMessage::Pong] containing the same sequence before
+    /// the keepalive timeout or the session is closed
+    Ping { sequence: u64 },
+
+    /// Response to a [ClientDeviceMessage::Ping]
+    Pong { sequence: u64 },
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type")]
 pub enum ClientDeviceMessageEncrypted {
     /// Request the current tiles
-    RequestTiles,
+    RequestTiles {
+        /// Screen of the device, used to pick the layout of the folder
+        #[serde(default)]
+        screen: Option<DeviceScreen>,
+    },
 
     /// User has clicked a tile
     TileClicked {
@@ -79,6 +134,14 @@ pub enum ClientDeviceMessageEncrypted {
         tile_id: Uuid,
     },
 
+    /// User has moved a continuous control (Slider, dial or encoder)
+    ValueChanged {
+        /// ID of the tile that was moved
+        tile_id: Uuid,
+        /// Input from the control
+        input: TileValueInput,
+    },
+
     /// Got a message from a display
     RecvFromDisplay {
         ctx: DisplayContext,
@@ -107,10 +170,41 @@ pub enum ServerDeviceMessageEncrypted {
     /// Device is authenticated
     Authenticated { device_id: Uuid },
 
+    /// Server key has been rotated, the device should pin the new key
+    /// after verifying the proof
+    ServerKeyRotated {
+        /// New public key of the server
+        server_public_key: [u8; 32],
+        /// Proof of possession of the new key, derived using HKDF-SHA256 from
+        /// DH(new server key, client key) with the client, previous server and
+        /// new server public keys as the salt
+        proof: [u8; 32],
+        /// When the previous server key will stop being accepted
+        retire_at: DateTime<Utc>,
+    },
+
     /// Update the current tiles list
     Tiles {
         tiles: Vec<TileModel>,
         folder: FolderModel,
+        /// Page of the folder the tiles are from
+        page: u32,
+    },
+
+    /// Current value of a continuous control tile has changed
+    ValueChanged {
+        /// ID of the tile
+        tile_id: Uuid,
+        /// New value of the tile
+        value: f64,
+    },
+
+    /// Current state of a multi-state tile has changed
+    StateChanged {
+        /// ID of the tile
+        tile_id: Uuid,
+        /// Index of the new state
+        state: u32,
     },
 
     /// Got a message from the plugin
@@ -131,7 +225,7 @@ pub enum ServerDeviceMessageEncrypted {
     },
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum DeviceIndicator {
     Error,
     Success,
@@ -0,0 +1,174 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+use tokio_serial::{SerialPortBuilderExt, SerialPortInfo, SerialStream};
+
+use crate::{
+    database::entity::settings::{SerialPortSettings, SerialSettings},
+    utils::{framed_msgpack::framed_msgpack, ws_msgpack::WsMpPresence},
+};
+
+use super::{
+    DeviceAddr, Devices,
+    protocol::{ClientDeviceMessage, ServerDeviceMessage},
+    session::DeviceSession,
+};
+
+impl Devices {
+    /// Update the settings used for serial devices, ports that are
+    /// already open remain open until they disconnect
+    pub fn set_serial_settings(&self, settings: SerialSettings) {
+        *self.serial_settings.write() = settings;
+    }
+
+    /// Background task that periodically scans for configured serial
+    /// ports that have been plugged in
+    pub async fn run_serial_rescan(self: Arc<Self>) {
+        loop {
+            self.rescan_serial_ports();
+
+            let interval = { self.serial_settings.read().rescan_interval };
+            tokio::time::sleep(Duration::from_secs(interval.max(1))).await;
+        }
+    }
+
+    /// Open any configured serial ports that are present but not yet open
+    pub fn rescan_serial_ports(self: &Arc<Self>) {
+        let ports = { self.serial_settings.read().ports.clone() };
+        if ports.is_empty() {
+            return;
+        }
+
+        let available = tokio_serial::available_ports().unwrap_or_default();
+
+        for port in ports {
+            if self.serial_ports.lock().contains(&port.path)
+                || !is_serial_port_present(&port.path, &available)
+            {
+                continue;
+            }
+
+            self.start_serial_session(port);
+        }
+    }
+
+    /// Open a serial port and start a device session over it, the port is
+    /// released when the device disconnects so it can be found by a later scan.
+    ///
+    /// The session handles keepalive and the presence timeout the same way
+    /// as websocket devices
+    fn start_serial_session(self: &Arc<Self>, port: SerialPortSettings) {
+        let stream = match open_serial_port(&port.path, port.baud_rate) {
+            Ok(value) => value,
+            Err(cause) => {
+                tracing::warn!(?cause, path = ?port.path, "failed to open serial port");
+                return;
+            }
+        };
+
+        tracing::debug!(path = ?port.path, "opened serial device port");
+
+        self.serial_ports.lock().insert(port.path.clone());
+
+        let presence = Arc::new(WsMpPresence::default());
+        let (future, rx, tx) =
+            framed_msgpack::<_, ServerDeviceMessage, ClientDeviceMessage>(stream);
+
+        DeviceSession::start_with_channel(
+            self.clone(),
+            DeviceAddr::Serial(port.path.clone()),
+            presence,
+            rx,
+            tx,
+        );
+
+        let devices = self.clone();
+        tokio::spawn(async move {
+            if let Err(cause) = future.await {
+                tracing::warn!(?cause, path = ?port.path, "serial device disconnected");
+            }
+
+            devices.serial_ports.lock().remove(&port.path);
+        });
+    }
+}
+
+/// Open the serial port at `path` configured for raw binary IO
+pub fn open_serial_port(path: &str, baud_rate: u32) -> std::io::Result<SerialStream> {
+    let stream = tokio_serial::new(path, baud_rate).open_native_async()?;
+    Ok(stream)
+}
+
+/// Check if a serial port is currently present, ports are matched by path
+/// where the platform exposes them as files (Unix) and by name otherwise
+/// (Windows COM ports)
+fn is_serial_port_present(path: &str, available: &[SerialPortInfo]) -> bool {
+    Path::new(path).exists() || available.iter().any(|port| port.port_name == path)
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use std::time::Duration;
+
+    use futures::{SinkExt, StreamExt};
+    use serde::{Deserialize, Serialize};
+    use tokio::time::timeout;
+    use tokio_serial::SerialStream;
+    use tokio_util::{
+        bytes::Bytes,
+        codec::{Framed, LengthDelimitedCodec},
+    };
+
+    use crate::utils::framed_msgpack::framed_msgpack;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct TestMessage {
+        value: u32,
+    }
+
+    fn encode(message: &TestMessage) -> Bytes {
+        Bytes::from(rmp_serde::to_vec_named(message).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_serial_message_round_trip() {
+        let (pty, stream) = SerialStream::pair().unwrap();
+
+        let (future, mut rx, tx) = framed_msgpack::<_, TestMessage, TestMessage>(stream);
+        tokio::spawn(future);
+
+        let mut device = Framed::new(pty, LengthDelimitedCodec::new());
+
+        // Device to server
+        device
+            .send(encode(&TestMessage { value: 1 }))
+            .await
+            .unwrap();
+        let message = timeout(TIMEOUT, rx.recv()).await.unwrap().unwrap();
+        assert_eq!(message, TestMessage { value: 1 });
+
+        // Server to device
+        tx.send(TestMessage { value: 2 }).unwrap();
+        let frame = timeout(TIMEOUT, device.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let message: TestMessage = rmp_serde::from_slice(&frame).unwrap();
+        assert_eq!(message, TestMessage { value: 2 });
+    }
+
+    #[tokio::test]
+    async fn test_serial_device_disconnect() {
+        let (pty, stream) = SerialStream::pair().unwrap();
+
+        let (future, _rx, _tx) = framed_msgpack::<_, TestMessage, TestMessage>(stream);
+        let handle = tokio::spawn(future);
+
+        // Closing the controlling side is the same as unplugging the device
+        drop(pty);
+
+        timeout(TIMEOUT, handle).await.unwrap().unwrap().ok();
+    }
+}
@@ -1,19 +1,23 @@
-use std::{io::ErrorKind, net::SocketAddr, sync::Arc};
+use std::{
+    io::ErrorKind,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use axum::extract::ws::WebSocket;
-use chacha20poly1305::{
-    AeadCore, KeyInit, XChaCha20Poly1305, XNonce,
-    aead::{Aead, OsRng, rand_core::RngCore},
-};
+use chacha20poly1305::aead::{OsRng, rand_core::RngCore};
 use parking_lot::RwLock;
 use tauri::async_runtime::spawn;
+use tokio::time::MissedTickBehavior;
 use tracing::error;
 use uuid::Uuid;
-use x25519_dalek::PublicKey;
+use x25519_dalek::{EphemeralSecret, PublicKey};
 
 use crate::{
     database::entity::{
         device::DeviceId,
+        device_event::{CreateDeviceEvent, DeviceEventKind},
         folder::FolderModel,
         tile::{TileId, TileModel},
     },
@@ -22,29 +26,61 @@ use crate::{
     },
     events::DisplayContext,
     utils::{
+        encryption::public_key_fingerprint,
         error::try_cast_error,
-        ws_msgpack::{WebSocketMpFuture, WsMpTx},
+        ws_msgpack::{WebSocketMpFuture, WsMpPresence, WsMpPresenceState, WsMpRx, WsMpTx},
     },
 };
 
 use super::{
-    Devices,
-    protocol::{ClientDeviceMessage, ServerDeviceMessage},
+    DeviceAddr, Devices,
+    cipher::{HandshakeKeys, SessionCipher, SessionCipherError, SessionCipherRef},
+    layout::{DeviceScreen, resolve_layout},
+    protocol::{ClientDeviceMessage, PROTOCOL_VERSION, ServerDeviceMessage},
 };
 
 pub type DeviceSessionId = Uuid;
 pub type DeviceSessionRef = Arc<DeviceSession>;
 
+/// Configuration for session keepalive
+#[derive(Debug, Clone, Copy)]
+pub struct SessionKeepAlive {
+    /// Interval between keepalive pings
+    pub interval: Duration,
+    /// Duration without receiving anything before the
+    /// session is considered dead
+    pub timeout: Duration,
+}
+
+/// Liveness of a session, tracked using the protocol ping and pong messages
+/// so it works for any transport
+struct KeepAliveState {
+    /// Duration without receiving anything before timing out
+    timeout: Duration,
+    /// Last time anything was received
+    last_received: Instant,
+    /// Sequence number for the next ping
+    next_sequence: u64,
+    /// Sequence number and send time of the last unanswered ping
+    last_ping: Option<(u64, Instant)>,
+}
+
 pub struct DeviceSession {
     /// Unique ID of the session
     id: DeviceSessionId,
 
     /// Address of the device session socket
-    socket_addr: SocketAddr,
+    socket_addr: DeviceAddr,
 
     /// Session state
     state: RwLock<DeviceSessionState>,
 
+    /// Presence details from the session keepalive
+    presence: Arc<WsMpPresence>,
+
+    /// Screen reported by the device, used to pick folder layouts
+    screen: RwLock<Option<DeviceScreen>>,
+
     /// Channel to send messages to the session
     tx: WsMpTx<ServerDeviceMessage>,
 
@@ -71,30 +107,40 @@ enum DeviceSessionState {
 #[derive(Clone)]
 struct DeviceSessionChallengeState {
     /// Cipher for encrypted communication
-    cipher: XChaCha20Poly1305,
+    cipher: SessionCipherRef,
     /// Challenge bytes
     challenge: Vec<u8>,
     /// Name of the client
     client_name: String,
     /// Client public key
     client_public_key: [u8; 32],
+    /// Server public key used for the handshake
+    server_public_key: [u8; 32],
 }
 
 #[derive(Clone)]
 struct DeviceSessionAwaitingApprovalState {
     /// Cipher for encrypted communication
-    cipher: XChaCha20Poly1305,
+    cipher: SessionCipherRef,
+    /// Client public key
+    client_public_key: [u8; 32],
+    /// Server public key used for the handshake
+    server_public_key: [u8; 32],
 }
 #[derive(Clone)]
 struct DeviceSessionAuthenticatedState {
     /// Cipher for encrypted communication
-    cipher: XChaCha20Poly1305,
+    cipher: SessionCipherRef,
     device_id: DeviceId,
+    /// Client public key
+    client_public_key: [u8; 32],
+    /// Server public key used for the handshake
+    server_public_key: [u8; 32],
 }
 
 impl DeviceSession {
     pub fn start(devices: Arc<Devices>, socket_addr: SocketAddr, socket: WebSocket) {
-        let id = Uuid::new_v4();
+        let presence = Arc::new(WsMpPresence::default());
 
         // Create and spawn a future for the websocket
         let (ws_future, ws_rx, ws_tx) =
@@ -114,10 +160,31 @@ impl DeviceSession {
             }
         });
 
+        Self::start_with_channel(
+            devices,
+            DeviceAddr::Socket(socket_addr),
+            presence,
+            ws_rx,
+            ws_tx,
+        );
+    }
+
+    /// Start a session using channels to an already running transport, the
+    /// session ends when the transport drops its sender
+    pub fn start_with_channel(
+        devices: Arc<Devices>,
+        socket_addr: DeviceAddr,
+        presence: Arc<WsMpPresence>,
+        ws_rx: WsMpRx<ClientDeviceMessage>,
+        ws_tx: WsMpTx<ServerDeviceMessage>,
+    ) {
+        let id = Uuid::new_v4();
         let session = Arc::new(DeviceSession {
             id,
             socket_addr,
             state: Default::default(),
+            presence,
+            screen: Default::default(),
             tx: ws_tx,
             devices,
         });
@@ -128,18 +195,66 @@ impl DeviceSession {
 
             let mut ws_rx = ws_rx;
 
+            let config = session.devices.get_keep_alive();
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + config.interval,
+                config.interval,
+            );
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            let mut keep_alive = KeepAliveState::new(config.timeout);
+            session.presence.on_seen();
+
             // Process messages from the session
-            while let Some(msg) = ws_rx.recv().await {
-                session.handle_message(msg).await;
+            loop {
+                tokio::select! {
+                    msg = ws_rx.recv() => {
+                        let msg = match msg {
+                            Some(value) => value,
+                            // Transport has closed
+                            None => break,
+                        };
+
+                        keep_alive.on_received();
+                        session.presence.on_seen();
+                        session.handle_message(msg, &mut keep_alive).await;
+                    }
+
+                    _ = interval.tick() => {
+                        // Device stopped responding to keepalive pings
+                        if keep_alive.is_timed_out() {
+                            tracing::warn!(
+                                socket_addr = ?session.socket_addr,
+                                "device connection timed out"
+                            );
+                            break;
+                        }
+
+                        let sequence = keep_alive.next_ping();
+                        session.send_message(ServerDeviceMessage::Ping { sequence });
+                    }
+                }
             }
 
             let device_id = session.get_device_id();
 
             // Remove the session thats no longer running
-            session.devices.remove_session(session.id, device_id);
+            session
+                .devices
+                .remove_session(session.id, &session.socket_addr, device_id);
         });
     }
 
+    /// Get the address of the device session socket
+    pub fn socket_addr(&self) -> DeviceAddr {
+        self.socket_addr.clone()
+    }
+
+    /// Get the presence details for the session
+    pub fn presence(&self) -> WsMpPresenceState {
+        self.presence.get()
+    }
+
     /// Get the current device ID
     pub fn get_device_id(&self) -> Option<DeviceId> {
         match &*self.state.read() {
@@ -173,9 +288,7 @@ impl DeviceSession {
             }
         };
 
-        // Encrypt challenge
-        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
-        let encrypted_message = match cipher.encrypt(&nonce, encoded_message.as_slice()) {
+        let (encrypted_message, nonce) = match cipher.encrypt(&encoded_message) {
             Ok(value) => value,
             Err(err) => {
                 tracing::error!(?err, "failed to encrypt message");
@@ -189,7 +302,7 @@ impl DeviceSession {
         self.tx
             .send(ServerDeviceMessage::EncryptedMessage {
                 message: encrypted_message,
-                nonce: nonce.into(),
+                nonce,
             })
             .is_ok()
     }
@@ -207,20 +320,41 @@ impl DeviceSession {
     pub fn on_approved(&self, device_id: DeviceId) {
         {
             let state = &mut *self.state.write();
-            let cipher = match state {
-                DeviceSessionState::AwaitingApproval(state) => state.cipher.clone(),
+            let awaiting = match state {
+                DeviceSessionState::AwaitingApproval(state) => state.clone(),
                 _ => return,
             };
 
             // Authenticate the device session
             *state = DeviceSessionState::Authenticated(DeviceSessionAuthenticatedState {
-                cipher,
+                cipher: awaiting.cipher,
                 device_id,
+                client_public_key: awaiting.client_public_key,
+                server_public_key: awaiting.server_public_key,
             });
         };
 
+        self.devices.add_device_session(device_id, self.id);
+
         self.send_encrypted_message(ServerDeviceMessageEncrypted::Approved { device_id });
         self.send_encrypted_message(ServerDeviceMessageEncrypted::Authenticated { device_id });
+        self.on_server_key_rotated();
+    }
+
+    /// Sends proof of the current server key when the session was
+    /// established using a previous server key, allowing the device
+    /// to pin the new key without pairing again
+    pub fn on_server_key_rotated(&self) {
+        let message = match &*self.state.read() {
+            DeviceSessionState::Authenticated(state) => self
+                .devices
+                .create_server_key_rotation(&state.server_public_key, &state.client_public_key),
+            _ => None,
+        };
+
+        if let Some(message) = message {
+            self.send_encrypted_message(message);
+        }
     }
 
     pub fn on_plugin_message(&self, ctx: DisplayContext, message: serde_json::Value) {
@@ -235,12 +369,51 @@ impl DeviceSession {
         });
     }
 
+    pub fn on_tile_value(&self, tile_id: TileId, value: f64) {
+        self.send_encrypted_message(ServerDeviceMessageEncrypted::ValueChanged { tile_id, value });
+    }
+
+    pub fn on_tile_state(&self, tile_id: TileId, state: u32) {
+        self.send_encrypted_message(ServerDeviceMessageEncrypted::StateChanged { tile_id, state });
+    }
+
     pub fn on_tiles(&self, tiles: Vec<TileModel>, folder: FolderModel) {
-        self.send_encrypted_message(ServerDeviceMessageEncrypted::Tiles { tiles, folder });
+        let device_id = match self.get_device_id() {
+            Some(value) => value,
+            None => return,
+        };
+
+        let screen = *self.screen.read();
+        let (folder, mut tiles) = resolve_layout(folder, tiles, device_id, screen);
+
+        // Devices only receive the tiles on the page they are viewing
+        let page = self.devices.get_device_page(device_id, &folder);
+        tiles.retain(|tile| tile.position.page == page);
+
+        self.send_encrypted_message(ServerDeviceMessageEncrypted::Tiles {
+            tiles,
+            folder,
+            page,
+        });
     }
 
     /// Handle messages from the socket
-    async fn handle_message(&self, message: ClientDeviceMessage) {
+    async fn handle_message(&self, message: ClientDeviceMessage, keep_alive: &mut KeepAliveState) {
+        // Keepalive messages are accepted in any state
+        match message {
+            ClientDeviceMessage::Ping { sequence } => {
+                self.send_message(ServerDeviceMessage::Pong { sequence });
+                return;
+            }
+            ClientDeviceMessage::Pong { sequence } => {
+                if let Some(rtt) = keep_alive.on_pong(sequence) {
+                    self.presence.on_rtt(rtt);
+                }
+                return;
+            }
+            _ => {}
+        }
+
         let state = { self.state.read().clone() };
 
         match state {
@@ -259,8 +432,34 @@ impl DeviceSession {
 
     fn handle_message_initial(&self, message: ClientDeviceMessage) {
         match message {
-            ClientDeviceMessage::InitiateHandshake { name, public_key } => {
-                self.handle_initiate_handshake(name, public_key)
+            ClientDeviceMessage::InitiateHandshake {
+                protocol_version,
+                name,
+                public_key,
+                ephemeral_public_key,
+                server_public_key,
+            } => {
+                // Clients from before the ephemeral key exchange cannot derive
+                // the session keys, reject them with an explicit error
+                let ephemeral_public_key = match ephemeral_public_key {
+                    Some(value) if protocol_version >= PROTOCOL_VERSION => value,
+                    _ => {
+                        let message = format!(
+                            "unsupported protocol version {protocol_version}, \
+                            server requires version {PROTOCOL_VERSION}"
+                        );
+                        self.record_handshake_failure(&public_key, &message);
+                        self.send_message(ServerDeviceMessage::Error { message });
+                        return;
+                    }
+                };
+
+                self.handle_initiate_handshake(
+                    name,
+                    public_key,
+                    ephemeral_public_key,
+                    server_public_key,
+                )
             }
 
             _ => tracing::warn!(?message, "got unexpected message from unauthorized device"),
@@ -268,20 +467,36 @@ impl DeviceSession {
     }
 
     /// Handles the initiation of a handshake
-    fn handle_initiate_handshake(&self, name: String, public_key: [u8; 32]) {
-        // Perform ECDH key exchange
-        let client_public_key = PublicKey::from(public_key);
-        let shared_secret = self
-            .devices
-            .server_key_pair
-            .private_key
-            .diffie_hellman(&client_public_key);
+    fn handle_initiate_handshake(
+        &self,
+        name: String,
+        public_key: [u8; 32],
+        ephemeral_public_key: [u8; 32],
+        server_public_key: Option<[u8; 32]>,
+    ) {
+        let server_key_pair = self.devices.get_server_key_pair(server_public_key.as_ref());
 
-        // Create cipher
-        let cipher = match XChaCha20Poly1305::new_from_slice(shared_secret.as_bytes()) {
-            Ok(value) => value,
+        // Generate a single use key for this session
+        let server_ephemeral_key = EphemeralSecret::random();
+        let server_ephemeral_public_key = PublicKey::from(&server_ephemeral_key);
+
+        let keys = HandshakeKeys {
+            server_public_key: server_key_pair.public_key,
+            server_ephemeral_public_key,
+            client_public_key: PublicKey::from(public_key),
+            client_ephemeral_public_key: PublicKey::from(ephemeral_public_key),
+        };
+
+        // Derive the session cipher
+        let cipher = match SessionCipher::derive_server(
+            &server_key_pair.private_key,
+            server_ephemeral_key,
+            &keys,
+        ) {
+            Ok(value) => Arc::new(value),
             Err(err) => {
                 tracing::error!(?err, "failed to create cipher");
+                self.record_handshake_failure(&public_key, "failed to create cipher");
                 self.send_message(ServerDeviceMessage::Error {
                     message: "failed to create cipher".to_string(),
                 });
@@ -310,18 +525,34 @@ impl DeviceSession {
                 cipher,
                 challenge,
                 client_name: name,
-                client_public_key: client_public_key.to_bytes(),
+                client_public_key: public_key,
+                server_public_key: keys.server_public_key.to_bytes(),
             });
         }
 
         // Notify device of challenge
         self.send_message(ServerDeviceMessage::AuthenticateChallenge {
-            server_public_key: self.devices.server_key_pair.public_key.to_bytes(),
+            protocol_version: PROTOCOL_VERSION,
+            server_public_key: keys.server_public_key.to_bytes(),
+            server_ephemeral_public_key: server_ephemeral_public_key.to_bytes(),
             challenge: encrypted_challenge,
             nonce,
         });
     }
 
+    /// Record a failed handshake in the device audit log
+    fn record_handshake_failure(&self, client_public_key: &[u8; 32], message: &str) {
+        self.devices.record_event(
+            DeviceEventKind::HandshakeFailed,
+            CreateDeviceEvent {
+                public_key_fingerprint: Some(public_key_fingerprint(client_public_key)),
+                socket_addr: Some(self.socket_addr.to_string()),
+                message: Some(message.to_string()),
+                ..Default::default()
+            },
+        );
+    }
+
     async fn handle_challenge_response(
         &self,
         state: DeviceSessionChallengeState,
@@ -329,14 +560,14 @@ impl DeviceSession {
         client_challenge: Vec<u8>,
         client_nonce: [u8; 24],
     ) {
-        let client_nonce = XNonce::from(client_nonce);
-        let client_challenge = match state
-            .cipher
-            .decrypt(&client_nonce, client_challenge.as_slice())
-        {
+        let client_challenge = match state.cipher.decrypt(&client_challenge, client_nonce) {
             Ok(value) => value,
             Err(err) => {
                 tracing::error!(?err, "failed to decrypt challenge");
+                self.record_handshake_failure(
+                    &state.client_public_key,
+                    "failed to decrypt challenge",
+                );
                 self.send_message(ServerDeviceMessage::Error {
                     message: "failed to decrypt challenge".to_string(),
                 });
@@ -347,6 +578,7 @@ impl DeviceSession {
         // Challenge didn't match
         if client_challenge != state.challenge {
             tracing::error!("incorrect challenge");
+            self.record_handshake_failure(&state.client_public_key, "challenge does not match");
             self.send_message(ServerDeviceMessage::Error {
                 message: "challenge does not match".to_string(),
             });
@@ -355,7 +587,7 @@ impl DeviceSession {
 
         match self
             .devices
-            .attempt_authenticate_device(&state.client_public_key)
+            .attempt_authenticate_device(&self.socket_addr, &state.client_public_key)
             .await
         {
             // Public key is known and authenticated with an existing device
@@ -366,29 +598,92 @@ impl DeviceSession {
                         DeviceSessionState::Authenticated(DeviceSessionAuthenticatedState {
                             cipher: state.cipher,
                             device_id,
+                            client_public_key: state.client_public_key,
+                            server_public_key: state.server_public_key,
                         });
                 };
 
+                self.devices.add_device_session(device_id, self.id);
+
                 self.send_encrypted_message(ServerDeviceMessageEncrypted::Authenticated {
                     device_id,
                 });
+                self.on_server_key_rotated();
             }
-            // Public key is not known or approved yet add approval request
+            // Public key is not known or approved yet, check auto approval
             Ok(None) => {
+                match self
+                    .devices
+                    .try_auto_approve_device(
+                        &self.socket_addr,
+                        &state.client_name,
+                        &state.client_public_key,
+                    )
+                    .await
+                {
+                    // Device was automatically approved
+                    Ok(Some(device_id)) => {
+                        {
+                            // Authenticate the device session
+                            *self.state.write() = DeviceSessionState::Authenticated(
+                                DeviceSessionAuthenticatedState {
+                                    cipher: state.cipher,
+                                    device_id,
+                                    client_public_key: state.client_public_key,
+                                    server_public_key: state.server_public_key,
+                                },
+                            );
+                        };
+
+                        self.devices.add_device_session(device_id, self.id);
+
+                        self.send_encrypted_message(ServerDeviceMessageEncrypted::Approved {
+                            device_id,
+                        });
+                        self.send_encrypted_message(ServerDeviceMessageEncrypted::Authenticated {
+                            device_id,
+                        });
+                        self.on_server_key_rotated();
+                        return;
+                    }
+                    // No rules matched, request approval
+                    Ok(None) => {}
+                    Err(cause) => {
+                        tracing::error!(?cause, "failed to auto approve device");
+                    }
+                }
+
                 {
                     // Awaiting approval
                     *self.state.write() =
                         DeviceSessionState::AwaitingApproval(DeviceSessionAwaitingApprovalState {
                             cipher: state.cipher,
+                            client_public_key: state.client_public_key,
+                            server_public_key: state.server_public_key,
                         });
                 }
 
-                self.devices.add_device_request(
+                if let Err(cause) = self.devices.add_device_request(
                     self.id,
-                    self.socket_addr,
+                    self.socket_addr.clone(),
                     state.client_name,
                     state.client_public_key,
-                );
+                ) {
+                    tracing::warn!(
+                        ?cause,
+                        socket_addr = ?self.socket_addr,
+                        "rejected device request"
+                    );
+
+                    // Reset the session state
+                    *self.state.write() = Default::default();
+
+                    self.record_handshake_failure(&state.client_public_key, &cause.to_string());
+                    self.send_message(ServerDeviceMessage::Error {
+                        message: cause.to_string(),
+                    });
+                    return;
+                }
 
                 self.send_encrypted_message(ServerDeviceMessageEncrypted::ApprovalRequested);
             }
@@ -433,9 +728,18 @@ impl DeviceSession {
             }
         };
 
-        let nonce = XNonce::from(nonce);
-        let message = match state.cipher.decrypt(&nonce, message.as_slice()) {
+        let message = match state.cipher.decrypt(&message, nonce) {
             Ok(value) => value,
+            Err(SessionCipherError::Replayed) => {
+                tracing::warn!(
+                    socket_addr = ?self.socket_addr,
+                    "rejected replayed device message"
+                );
+                self.send_message(ServerDeviceMessage::Error {
+                    message: "message was replayed".to_string(),
+                });
+                return;
+            }
             Err(err) => {
                 tracing::error!(?err, "failed to decrypt message");
                 self.send_message(ServerDeviceMessage::Error {
@@ -466,7 +770,11 @@ impl DeviceSession {
         message: ClientDeviceMessageEncrypted,
     ) {
         match message {
-            ClientDeviceMessageEncrypted::RequestTiles => {
+            ClientDeviceMessageEncrypted::RequestTiles { screen } => {
+                if screen.is_some() {
+                    *self.screen.write() = screen;
+                }
+
                 // Get the current folder the device is using
                 let (folder, tiles) = match self.devices.request_device_tiles(device_id).await {
                     Ok(value) => value,
@@ -477,7 +785,7 @@ impl DeviceSession {
                 };
 
                 // Send the tiles to the device
-                self.send_encrypted_message(ServerDeviceMessageEncrypted::Tiles { tiles, folder });
+                self.on_tiles(tiles, folder);
             }
 
             ClientDeviceMessageEncrypted::TileClicked { tile_id } => {
@@ -490,6 +798,19 @@ impl DeviceSession {
                 });
             }
 
+            ClientDeviceMessageEncrypted::ValueChanged { tile_id, input } => {
+                let devices = self.devices.clone();
+
+                _ = tokio::spawn(async move {
+                    if let Err(cause) = devices
+                        .device_change_tile_value(device_id, tile_id, input)
+                        .await
+                    {
+                        tracing::error!(?cause, "failed to change tile value");
+                    }
+                });
+            }
+
             ClientDeviceMessageEncrypted::RecvFromDisplay { ctx, message } => {
                 let plugins = self.devices.plugins.clone();
 
@@ -503,26 +824,64 @@ impl DeviceSession {
     }
 }
 
+impl KeepAliveState {
+    fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            last_received: Instant::now(),
+            next_sequence: 0,
+            last_ping: None,
+        }
+    }
+
+    /// Handle a message being received from the session
+    fn on_received(&mut self) {
+        self.last_received = Instant::now();
+    }
+
+    /// Handle a pong from the session, returns the round trip time
+    /// when the pong answers the last ping
+    fn on_pong(&mut self, sequence: u64) -> Option<Duration> {
+        match self.last_ping {
+            Some((last_sequence, sent_at)) if last_sequence == sequence => {
+                self.last_ping = None;
+                Some(sent_at.elapsed())
+            }
+            _ => None,
+        }
+    }
+
+    /// Check whether nothing has been received within the timeout
+    fn is_timed_out(&self) -> bool {
+        self.last_received.elapsed() >= self.timeout
+    }
+
+    /// Get the sequence number for the next ping
+    fn next_ping(&mut self) -> u64 {
+        let sequence = self.next_sequence;
+        self.next_sequence = sequence.wrapping_add(1);
+        self.last_ping = Some((sequence, Instant::now()));
+        sequence
+    }
+}
+
 struct EncryptedChallenge {
     challenge: Vec<u8>,
     encrypted_challenge: Vec<u8>,
     nonce: [u8; 24],
 }
 
-fn generate_encrypted_challenge(cipher: &XChaCha20Poly1305) -> anyhow::Result<EncryptedChallenge> {
+fn generate_encrypted_challenge(cipher: &SessionCipher) -> anyhow::Result<EncryptedChallenge> {
     // Generate a random challenge bytes
     let mut challenge = [0u8; 128];
     OsRng.fill_bytes(&mut challenge);
 
     // Encrypt challenge
-    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
-    let encrypted_challenge = cipher
-        .encrypt(&nonce, challenge.as_slice())
-        .map_err(|_| anyhow::anyhow!("failed to encrypt challenge"))?;
+    let (encrypted_challenge, nonce) = cipher.encrypt(&challenge)?;
 
     Ok(EncryptedChallenge {
         challenge: challenge.to_vec(),
         encrypted_challenge,
-        nonce: nonce.into(),
+        nonce,
     })
 }
@@ -0,0 +1,483 @@
+use std::{
+    collections::{BTreeSet, HashSet, VecDeque},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use parking_lot::Mutex;
+use serde::Serialize;
+use sqlx::SqliteConnection;
+
+use crate::{
+    database::{
+        DbPool,
+        entity::{
+            folder::{FolderId, FolderModel},
+            profile::ProfileModel,
+            tile::{TileIcon, TileId, TileModel},
+        },
+    },
+    device::Devices,
+    icons::Icons,
+    tile::grid::FolderGrid,
+};
+
+/// Maximum number of entries kept in the undo history
+const MAX_HISTORY: usize = 100;
+
+/// Bounded history of reversible edits made through the app
+pub struct History {
+    /// Access to the database
+    db: DbPool,
+
+    /// Access to icons
+    icons: Arc<Icons>,
+
+    /// Access to devices
+    devices: Arc<Devices>,
+
+    /// Current undo and redo stacks
+    state: Mutex<HistoryState>,
+}
+
+#[derive(Default)]
+struct HistoryState {
+    /// Entries that can be undone, most recent last
+    undo: VecDeque<HistoryEntry>,
+    /// Entries that can be redone, most recent last
+    redo: Vec<HistoryEntry>,
+}
+
+/// Group of changes that are undone and redone together
+pub type HistoryEntry = Vec<HistoryChange>;
+
+/// Reversible change to the database
+#[derive(Debug, Clone)]
+pub enum HistoryChange {
+    /// Tile changed from `before` to `after`, [None] when
+    /// the tile did not exist
+    Tile {
+        before: Option<TileModel>,
+        after: Option<TileModel>,
+    },
+    /// Name or config of a folder changed
+    Folder {
+        before: FolderModel,
+        after: FolderModel,
+    },
+    /// Name or config of a profile changed
+    Profile {
+        before: ProfileModel,
+        after: ProfileModel,
+    },
+}
+
+/// Whether there are changes available to undo or redo
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct HistoryStatus {
+    pub can_undo: bool,
+    pub can_redo: bool,
+}
+
+impl HistoryChange {
+    pub fn tile(before: Option<TileModel>, after: Option<TileModel>) -> Self {
+        Self::Tile { before, after }
+    }
+
+    /// Uploaded icons used by the `before` side of the change that
+    /// are no longer used on the `after` side
+    fn replaced_icons(before: &Option<TileModel>, after: &Option<TileModel>) -> Vec<TileIcon> {
+        let before = match before {
+            Some(value) => value,
+            None => return Vec::new(),
+        };
+
+        before
+            .config
+            .icons()
+            .filter(|icon| matches!(icon, TileIcon::Uploaded { .. }))
+            .filter(|icon| {
+                after
+                    .as_ref()
+                    .is_none_or(|after| !after.config.icons().any(|other| other == *icon))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+impl History {
+    pub fn new(db: DbPool, icons: Arc<Icons>, devices: Arc<Devices>) -> Self {
+        Self {
+            db,
+            icons,
+            devices,
+            state: Default::default(),
+        }
+    }
+
+    pub fn status(&self) -> HistoryStatus {
+        let state = self.state.lock();
+        HistoryStatus {
+            can_undo: !state.undo.is_empty(),
+            can_redo: !state.redo.is_empty(),
+        }
+    }
+
+    /// Record a new entry in the history, any entries that could
+    /// be redone are discarded
+    pub async fn record(&self, entry: HistoryEntry) {
+        if entry.is_empty() {
+            return;
+        }
+
+        let (evicted, discarded) = {
+            let mut state = self.state.lock();
+            state.undo.push_back(entry);
+
+            let evicted = if state.undo.len() > MAX_HISTORY {
+                state.undo.pop_front()
+            } else {
+                None
+            };
+
+            (evicted, std::mem::take(&mut state.redo))
+        };
+
+        self.remove_undo_icons(evicted).await;
+        self.remove_redo_icons(discarded).await;
+    }
+
+    /// Remove the entries that change any of the tiles `tile_ids`, used when
+    /// the tiles are purged from the trash and can no longer be restored
+    pub async fn forget_tiles(&self, tile_ids: &HashSet<TileId>) {
+        let changes_tiles = |entry: &HistoryEntry| {
+            entry.iter().any(|change| match change {
+                HistoryChange::Tile { before, after } => before
+                    .iter()
+                    .chain(after)
+                    .any(|tile| tile_ids.contains(&tile.id)),
+                _ => false,
+            })
+        };
+
+        let (undo, redo) = {
+            let state = &mut *self.state.lock();
+
+            let (undo, kept): (VecDeque<_>, VecDeque<_>) = std::mem::take(&mut state.undo)
+                .into_iter()
+                .partition(changes_tiles);
+            state.undo = kept;
+
+            let (redo, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut state.redo)
+                .into_iter()
+                .partition(changes_tiles);
+            state.redo = kept;
+
+            (undo, redo)
+        };
+
+        self.remove_undo_icons(undo).await;
+        self.remove_redo_icons(redo).await;
+    }
+
+    /// Record a single tile change
+    pub async fn record_tile(&self, before: Option<TileModel>, after: Option<TileModel>) {
+        self.record(vec![HistoryChange::tile(before, after)]).await;
+    }
+
+    /// Record a change to a folder
+    pub async fn record_folder(&self, before: FolderModel, after: FolderModel) {
+        self.record(vec![HistoryChange::Folder { before, after }])
+            .await;
+    }
+
+    /// Record a change to a profile
+    pub async fn record_profile(&self, before: ProfileModel, after: ProfileModel) {
+        self.record(vec![HistoryChange::Profile { before, after }])
+            .await;
+    }
+
+    /// Undo the most recent entry, the entry stays on the undo
+    /// stack if it could not be applied
+    pub async fn undo(&self) -> anyhow::Result<HistoryStatus> {
+        let entry = self.state.lock().undo.pop_back();
+        let entry = match entry {
+            Some(value) => value,
+            None => return Ok(self.status()),
+        };
+
+        if let Err(cause) = self.apply(&entry, false).await {
+            self.state.lock().undo.push_back(entry);
+            return Err(cause);
+        }
+
+        self.state.lock().redo.push(entry);
+
+        Ok(self.status())
+    }
+
+    /// Redo the most recently undone entry, the entry stays on the
+    /// redo stack if it could not be applied
+    pub async fn redo(&self) -> anyhow::Result<HistoryStatus> {
+        let entry = self.state.lock().redo.pop();
+        let entry = match entry {
+            Some(value) => value,
+            None => return Ok(self.status()),
+        };
+
+        if let Err(cause) = self.apply(&entry, true).await {
+            self.state.lock().redo.push(entry);
+            return Err(cause);
+        }
+
+        self.state.lock().undo.push_back(entry);
+
+        Ok(self.status())
+    }
+
+    /// Apply the `after` state of the changes in `entry` when `forward`
+    /// is set otherwise the `before` state, changes are applied within a
+    /// single transaction and the affected devices are updated
+    async fn apply(&self, entry: &HistoryEntry, forward: bool) -> anyhow::Result<()> {
+        let mut tx = self.db.begin().await?;
+
+        let mut changes: Vec<&HistoryChange> = entry.iter().collect();
+        if !forward {
+            changes.reverse();
+        }
+
+        for change in changes {
+            match change {
+                HistoryChange::Tile { before, after } => {
+                    let (current, target) = if forward {
+                        (before, after)
+                    } else {
+                        (after, before)
+                    };
+
+                    match (current, target) {
+                        (_, Some(target)) => restore_tile(&mut *tx, target).await?,
+                        // Redoing a delete moves the tile back to the trash while
+                        // undoing a create removes the tile entirely
+                        (Some(current), None) if forward => {
+                            TileModel::delete(&mut *tx, current.id).await?
+                        }
+                        (Some(current), None) => TileModel::purge(&mut *tx, current.id).await?,
+                        (None, None) => {}
+                    }
+                }
+                HistoryChange::Folder { before, after } => {
+                    let target = if forward { after } else { before };
+                    target.restore(&mut *tx).await?;
+                }
+                HistoryChange::Profile { before, after } => {
+                    let target = if forward { after } else { before };
+                    target.restore(&mut *tx).await?;
+                }
+            }
+        }
+
+        tx.commit().await?;
+
+        let mut folder_ids: BTreeSet<FolderId> = BTreeSet::new();
+        for change in entry {
+            match change {
+                HistoryChange::Tile { before, after } => {
+                    folder_ids.extend(before.iter().chain(after).map(|tile| tile.folder_id));
+                }
+                HistoryChange::Folder { after, .. } => {
+                    folder_ids.insert(after.id);
+                }
+                HistoryChange::Profile { .. } => {}
+            }
+        }
+
+        for folder_id in folder_ids {
+            self.devices.update_folder_devices(folder_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove the icons that could only be restored by undoing the removed
+    /// `entries`, icons of deleted tiles are kept until the tile is purged
+    /// from the trash
+    async fn remove_undo_icons(&self, entries: impl IntoIterator<Item = HistoryEntry>) {
+        for change in entries.into_iter().flatten() {
+            if let HistoryChange::Tile { before, after } = &change
+                && after.is_some()
+            {
+                self.remove_icons(HistoryChange::replaced_icons(before, after))
+                    .await;
+            }
+        }
+    }
+
+    /// Remove the icons that could only be restored by redoing the
+    /// removed `entries`
+    async fn remove_redo_icons(&self, entries: impl IntoIterator<Item = HistoryEntry>) {
+        for change in entries.into_iter().flatten() {
+            if let HistoryChange::Tile { before, after } = &change {
+                self.remove_icons(HistoryChange::replaced_icons(after, before))
+                    .await;
+            }
+        }
+    }
+
+    /// Remove uploaded icons that are no longer reachable
+    async fn remove_icons(&self, icons: Vec<TileIcon>) {
+        for icon in icons {
+            if let Err(cause) = self.icons.handle_tile_change_icon(&icon).await {
+                tracing::error!(?cause, "failed to remove icon");
+            }
+        }
+    }
+}
+
+/// Restore the tile `target` placing it into free space within its folder,
+/// the current state and value of the tile are changed by devices rather
+/// than through the history so they are kept
+async fn restore_tile(db: &mut SqliteConnection, target: &TileModel) -> anyhow::Result<()> {
+    let mut tile = target.clone();
+
+    if let Some(current) = TileModel::get_by_id_with_deleted(&mut *db, tile.id).await? {
+        tile.config.state = if (current.config.state as usize) < tile.config.states.len() {
+            current.config.state
+        } else {
+            0
+        };
+        tile.value = tile.config.control.clamp_value(current.value);
+    }
+
+    let folder = FolderModel::get_by_id(&mut *db, tile.folder_id)
+        .await?
+        .context("folder of the tile is in the trash")?;
+
+    let existing = TileModel::get_by_folder(&mut *db, folder.id).await?;
+    let mut grid = FolderGrid::with_positions(
+        folder.config.rows,
+        folder.config.columns,
+        folder.config.pages,
+        existing
+            .iter()
+            .filter(|other| other.id != tile.id)
+            .map(|other| &other.position),
+    );
+
+    tile.position = grid
+        .place(&tile.position)
+        .context("not enough free space in the folder")?;
+
+    tile.restore(&mut *db).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HistoryChange, restore_tile};
+    use crate::database::{
+        entity::{
+            folder::{CreateFolder, FolderModel},
+            profile::{CreateProfile, ProfileModel},
+            tile::{CreateTile, TileIcon, TileModel, TilePosition, mock_tile},
+        },
+        mock_database,
+    };
+
+    fn tile(icon: TileIcon) -> TileModel {
+        let mut tile = mock_tile(TilePosition {
+            page: 0,
+            row: 0,
+            column: 0,
+            row_span: 1,
+            column_span: 1,
+        });
+        tile.config.icon = icon;
+        tile
+    }
+
+    /// Only uploaded icons missing from the other side of a change are replaced
+    #[test]
+    fn test_replaced_icons() {
+        let uploaded = TileIcon::Uploaded {
+            path: "icon.png".to_string(),
+        };
+
+        let before = Some(tile(uploaded.clone()));
+        let replaced = Some(tile(TileIcon::None));
+        let kept = Some(tile(uploaded.clone()));
+
+        assert_eq!(
+            HistoryChange::replaced_icons(&before, &replaced),
+            vec![uploaded.clone()]
+        );
+        assert_eq!(
+            HistoryChange::replaced_icons(&before, &None),
+            vec![uploaded]
+        );
+        assert!(HistoryChange::replaced_icons(&before, &kept).is_empty());
+        assert!(HistoryChange::replaced_icons(&None, &before).is_empty());
+    }
+
+    /// Restoring a tile whose position was taken places it into free space
+    #[tokio::test]
+    async fn test_restore_tile_occupied_position() {
+        let db = mock_database().await;
+
+        let profile = ProfileModel::create(
+            &db,
+            CreateProfile {
+                name: "Profile".to_string(),
+                default: true,
+                config: Default::default(),
+                order: 0,
+            },
+        )
+        .await
+        .unwrap();
+        let folder = FolderModel::create(
+            &db,
+            CreateFolder {
+                name: "Folder".to_string(),
+                config: Default::default(),
+                profile_id: profile.id,
+                default: true,
+                order: 0,
+            },
+        )
+        .await
+        .unwrap();
+
+        let create = |column: u32| CreateTile {
+            config: Default::default(),
+            folder_id: folder.id,
+            plugin_id: "com.example.test".to_string().try_into().unwrap(),
+            action_id: "test".to_string().try_into().unwrap(),
+            position: TilePosition {
+                page: 0,
+                row: 0,
+                column,
+                row_span: 1,
+                column_span: 1,
+            },
+        };
+
+        // Tile is moved away and another tile takes its old position
+        let before = TileModel::create(&db, create(0)).await.unwrap();
+        let moved = before
+            .clone()
+            .update_position(&db, create(1).position)
+            .await
+            .unwrap();
+        let other = TileModel::create(&db, create(0)).await.unwrap();
+
+        let mut conn = db.acquire().await.unwrap();
+        restore_tile(&mut conn, &before).await.unwrap();
+        drop(conn);
+
+        let restored = TileModel::get_by_id(&db, moved.id).await.unwrap().unwrap();
+        assert_ne!(restored.position.column, other.position.column);
+        assert_eq!(restored.position.row, 0);
+    }
+}
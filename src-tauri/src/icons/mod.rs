@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -149,6 +149,50 @@ impl Icons {
         Ok(file_name)
     }
 
+    /// Copy the file behind an uploaded icon to a new file so the copy is not
+    /// removed when the original icon changes. Other icons are returned as is
+    pub async fn copy_uploaded_icon(&self, icon: &TileIcon) -> anyhow::Result<TileIcon> {
+        let path = match icon {
+            TileIcon::Uploaded { path } => path,
+            _ => return Ok(icon.clone()),
+        };
+
+        let file_path = self.uploaded_path.join(path);
+        if !file_path.exists() {
+            return Ok(TileIcon::None);
+        }
+
+        let extension = file_extension(path.clone())?;
+        let file_id = Uuid::new_v4();
+        let file_name = format!("{file_id}.{extension}");
+
+        tokio::fs::copy(&file_path, self.uploaded_path.join(&file_name))
+            .await
+            .context("copy file")?;
+
+        Ok(TileIcon::Uploaded { path: file_name })
+    }
+
+    /// Remove uploaded icon files that are not in `used`
+    pub async fn remove_unused_uploaded_icons(&self, used: &HashSet<String>) -> anyhow::Result<()> {
+        if !self.uploaded_path.is_dir() {
+            return Ok(());
+        }
+
+        let mut entries = tokio::fs::read_dir(&self.uploaded_path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if used.contains(&file_name) || !entry.file_type().await?.is_file() {
+                continue;
+            }
+
+            tracing::debug!(?file_name, "removing unused uploaded icon");
+            tokio::fs::remove_file(entry.path()).await?;
+        }
+
+        Ok(())
+    }
+
     // Handle change in icon when using an uploaded icon (Remove the old file)
     pub async fn handle_tile_change_icon(&self, previous_icon: &TileIcon) -> anyhow::Result<()> {
         let path = match previous_icon {
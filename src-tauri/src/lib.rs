@@ -1,11 +1,14 @@
 use std::{error::Error, str::FromStr, sync::Arc};
 
 use anyhow::Context;
+use backup::Backups;
 use database::{DbPool, entity::settings::SettingsModel};
 use device::Devices;
 use events::DeepLinkContext;
 use fonts::Fonts;
+use history::History;
 use icons::Icons;
+use midi::Midi;
 use plugin::Plugins;
 use server::{ServerPort, create_http_socket};
 use tauri::{
@@ -16,27 +19,33 @@ use tauri_plugin_deep_link::{DeepLinkExt, OpenUrlEvent};
 use tile::Tiles;
 use tilepad_manifest::plugin::PluginId;
 use tokio::{fs::create_dir_all, sync::mpsc};
+use trash::Trash;
 use utils::tracing::setup_main_subscriber;
-use x25519_dalek::PublicKey;
 
-use crate::utils::encryption::{self, ServerKeyPair};
+use crate::utils::encryption::ServerKeys;
 
+mod backup;
 mod commands;
 mod database;
 mod device;
 mod events;
 mod fonts;
+mod history;
 mod icons;
+mod midi;
 mod plugin;
+mod profile;
 mod server;
 mod tile;
+mod trash;
 mod tray;
 mod utils;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     use commands::{
-        actions, devices, folders, fonts, icons, plugins, profiles, server, settings, tiles,
+        actions, backups, devices, folders, fonts, history, icons, midi, plugins, profiles, server,
+        settings, tiles, trash,
     };
 
     tauri::Builder::default()
@@ -51,12 +60,35 @@ pub fn run() {
             // Devices
             devices::devices_get_requests,
             devices::devices_get_devices,
+            devices::devices_reorder_devices,
             devices::devices_get_connected_devices,
             devices::devices_approve_request,
             devices::devices_decline_request,
             devices::devices_revoke_device,
             devices::devices_set_device_profile,
             devices::devices_set_device_folder,
+            devices::devices_set_device_config,
+            devices::devices_get_pairing_open,
+            devices::devices_set_pairing_open,
+            devices::devices_get_server_key,
+            devices::devices_rotate_server_key,
+            devices::devices_get_auto_approve_rules,
+            devices::devices_create_auto_approve_rule,
+            devices::devices_update_auto_approve_rule,
+            devices::devices_delete_auto_approve_rule,
+            devices::devices_get_auto_approvals,
+            devices::devices_get_device_events,
+            devices::devices_get_device_groups,
+            devices::devices_create_device_group,
+            devices::devices_update_device_group,
+            devices::devices_delete_device_group,
+            devices::devices_get_device_group_members,
+            devices::devices_set_device_group,
+            // MIDI
+            midi::midi_get_input_ports,
+            midi::midi_get_bindings,
+            midi::midi_create_binding,
+            midi::midi_delete_binding,
             // Server
             server::server_get_connection_info,
             server::server_get_port,
@@ -64,14 +96,22 @@ pub fn run() {
             profiles::profiles_get_profiles,
             profiles::profiles_get_profile,
             profiles::profiles_delete_profile,
+            profiles::profiles_reorder_profiles,
+            profiles::profiles_set_default,
+            profiles::profiles_export_profile,
+            profiles::profiles_import_profile,
             profiles::profiles_set_name,
             profiles::profiles_create_profile,
+            profiles::profiles_copy_profile,
             // Folders
             folders::folders_get_folders,
             folders::folders_get_folder,
             folders::folders_delete_folder,
+            folders::folders_reorder_folders,
+            folders::folders_set_default,
             folders::folders_set_name,
             folders::folders_set_config,
+            folders::folders_get_resize_conflicts,
             folders::folders_create_folder,
             // Actions
             actions::actions_get_actions,
@@ -80,12 +120,19 @@ pub fn run() {
             tiles::tiles_get_tiles,
             tiles::tiles_get_tile,
             tiles::tiles_create_tile,
+            tiles::tiles_duplicate_tile,
+            tiles::tiles_copy_tiles,
+            tiles::tiles_move_tile,
             tiles::tiles_update_tile_properties,
             tiles::tiles_update_tile_label,
             tiles::tiles_update_tile_icon,
             tiles::tiles_update_tile_icon_options,
             tiles::tiles_delete_tile,
             tiles::tiles_update_tile_position,
+            tiles::tiles_update_tile_control,
+            tiles::tiles_update_tile_value,
+            tiles::tiles_update_tile_states,
+            tiles::tiles_update_tile_state,
             // Plugins
             plugins::plugins_send_plugin_message,
             plugins::plugins_send_plugin_display_message,
@@ -110,9 +157,25 @@ pub fn run() {
             icons::icons_download_bundle,
             // Fonts
             fonts::fonts_fonts,
+            // History
+            history::history_get_status,
+            history::history_undo,
+            history::history_redo,
             // Settings
             settings::settings_get_settings,
-            settings::settings_set_settings
+            settings::settings_set_settings,
+            // Backups
+            backups::backups_get_backups,
+            backups::backups_create_backup,
+            backups::backups_download_backup,
+            backups::backups_restore_backup,
+            backups::backups_restore_stored_backup,
+            // Trash
+            trash::trash_get_trash,
+            trash::trash_restore_profile,
+            trash::trash_restore_folder,
+            trash::trash_restore_tile,
+            trash::trash_empty_trash
         ])
         .build(tauri::generate_context!())
         .expect("error while running tauri application")
@@ -168,22 +231,32 @@ fn setup(app: &mut App) -> Result<(), Box<dyn Error>> {
     let user_icons = app_data_path.join("icons");
     let uploaded_icons = app_data_path.join("uploaded_icons");
 
-    let private_key = match block_on(encryption::setup_private_key(
-        &app_data_path.join("private_key.bin"),
+    // Apply any restore that was staged before the last restart, startup is
+    // stopped when the restore fails rather than running on unexpected data
+    if let Err(cause) = block_on(backup::apply_pending_restore(&app_data_path)) {
+        tracing::error!(?cause, "failed to apply backup restore");
+
+        rfd::MessageDialog::new()
+            .set_title("Failed to restore backup")
+            .set_description(format!("{cause:#}"))
+            .set_level(rfd::MessageLevel::Error)
+            .set_buttons(rfd::MessageButtons::Ok)
+            .show();
+
+        std::process::exit(1);
+    }
+
+    let server_keys = match block_on(ServerKeys::load(
+        app_data_path.join("private_key.bin"),
+        app_data_path.join("private_key.previous.bin"),
     )) {
         Ok(value) => value,
         Err(cause) => {
-            tracing::error!(?cause, "failed to load database");
+            tracing::error!(?cause, "failed to load server keys");
             std::process::exit(1);
         }
     };
 
-    let public_key = PublicKey::from(&private_key);
-    let server_key_pair = ServerKeyPair {
-        private_key,
-        public_key,
-    };
-
     let db = match block_on(database::connect_database(app_data_path.join("app.db"))) {
         Ok(value) => value,
         Err(cause) => {
@@ -216,22 +289,53 @@ fn setup(app: &mut App) -> Result<(), Box<dyn Error>> {
         logs_path,
         ServerPort(settings.port),
     ));
+    let (midi_event_tx, midi_event_rx) = mpsc::unbounded_channel();
+    let midi = Arc::new(Midi::new(db.clone(), midi_event_tx));
     let devices = Arc::new(Devices::new(
         app_event_tx.clone(),
         db.clone(),
         plugins.clone(),
-        server_key_pair,
+        midi.clone(),
+        server_keys,
+        settings.pairing.clone(),
+        settings.keep_alive.clone(),
+        settings.serial.clone(),
     ));
     let tiles = Arc::new(Tiles::new(db.clone(), icons.clone(), devices.clone()));
+
+    // Remove icons left behind by the undo history of the previous run, done
+    // before anything can upload new icons
+    if let Err(cause) = block_on(tiles.remove_unused_icons()) {
+        tracing::error!(?cause, "failed to remove unused uploaded icons");
+    }
+
     let fonts = Arc::new(Fonts::new());
+    let history = Arc::new(History::new(db.clone(), icons.clone(), devices.clone()));
+    let backups = Arc::new(Backups::new(
+        db.clone(),
+        plugins.clone(),
+        app_data_path.clone(),
+        settings.backup.clone(),
+    ));
+    let trash = Arc::new(Trash::new(
+        db.clone(),
+        icons.clone(),
+        devices.clone(),
+        history.clone(),
+        settings.trash.clone(),
+    ));
 
     app.manage(app_event_tx.clone());
     app.manage(db.clone());
     app.manage(devices.clone());
     app.manage(plugins.clone());
+    app.manage(midi.clone());
     app.manage(icons.clone());
     app.manage(tiles.clone());
     app.manage(fonts.clone());
+    app.manage(history.clone());
+    app.manage(backups.clone());
+    app.manage(trash.clone());
     app.manage(worker_guard);
     app.manage(ServerPort(settings.port));
 
@@ -252,6 +356,33 @@ fn setup(app: &mut App) -> Result<(), Box<dyn Error>> {
         app_event_rx,
     ));
 
+    // Spawn device request expiry
+    spawn(devices.clone().run_request_expiry());
+
+    // Spawn server key retirement
+    spawn(devices.clone().run_key_retirement());
+
+    // Spawn scheduled automatic backups
+    spawn(backups.run_scheduled_backups());
+
+    // Spawn purging of expired trash items
+    spawn(trash.run_purge());
+
+    // Spawn serial device port scanning
+    spawn(devices.clone().run_serial_rescan());
+
+    // Load MIDI bindings and spawn MIDI input processing
+    if let Err(cause) = block_on(midi.reload_bindings()) {
+        tracing::error!(?cause, "failed to load midi bindings");
+    }
+
+    spawn(midi::process_events(
+        midi.clone(),
+        devices.clone(),
+        midi_event_rx,
+    ));
+    spawn(midi.clone().run_port_scan());
+
     // Binding a socket must come before the rest of the app setup
     // (Socket must be bound before plugins load to prevent phantom processes holding the port)
     match tauri::async_runtime::block_on(create_http_socket(settings.port)) {
@@ -0,0 +1,576 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Context;
+use midir::{MidiIO, MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
+use parking_lot::{Mutex, RwLock};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        DbPool,
+        entity::{
+            device::DeviceId,
+            midi_binding::{MidiBindingModel, MidiFeedback, MidiTrigger},
+            tile::TileId,
+        },
+    },
+    device::{Devices, protocol::DeviceIndicator},
+};
+
+/// Synthetic device ID used as the tile context device for
+/// tiles executed from MIDI input
+pub const MIDI_DEVICE_ID: DeviceId = Uuid::from_u128(0x7e1e_9ad0_0000_4000_8000_6d69_6469_0001);
+
+/// Client name used when connecting to MIDI ports
+const MIDI_CLIENT_NAME: &str = "Tilepad";
+
+/// Interval between scans for bound MIDI ports that have been plugged in
+const PORT_SCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+pub type MidiEventSender = mpsc::UnboundedSender<MidiEvent>;
+pub type MidiEventReceiver = mpsc::UnboundedReceiver<MidiEvent>;
+
+/// Message received from a MIDI input port
+#[derive(Debug)]
+pub struct MidiEvent {
+    pub port_name: String,
+    pub message: MidiMessage,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiMessage {
+    NoteOn { channel: u8, note: u8, velocity: u8 },
+    NoteOff { channel: u8, note: u8 },
+    ControlChange { channel: u8, control: u8, value: u8 },
+}
+
+impl MidiMessage {
+    /// Parse a MIDI message, messages other than notes and control
+    /// changes are ignored
+    pub fn parse(bytes: &[u8]) -> Option<MidiMessage> {
+        let (status, data1, data2) = match bytes {
+            [status, data1, data2, ..] => (*status, *data1, *data2),
+            _ => return None,
+        };
+
+        let channel = status & 0x0F;
+
+        match status & 0xF0 {
+            0x90 if data2 > 0 => Some(MidiMessage::NoteOn {
+                channel,
+                note: data1,
+                velocity: data2,
+            }),
+            // Note on with zero velocity is commonly sent in place of note off
+            0x80 | 0x90 => Some(MidiMessage::NoteOff {
+                channel,
+                note: data1,
+            }),
+            0xB0 => Some(MidiMessage::ControlChange {
+                channel,
+                control: data1,
+                value: data2,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl MidiTrigger {
+    /// Check if a message triggers this binding, notes trigger when pressed
+    /// and control changes trigger on any non zero value
+    pub fn matches(&self, message: &MidiMessage) -> bool {
+        match (self, message) {
+            (
+                MidiTrigger::Note { channel, note },
+                MidiMessage::NoteOn {
+                    channel: msg_channel,
+                    note: msg_note,
+                    ..
+                },
+            ) => note == msg_note && channel.is_none_or(|channel| channel == *msg_channel),
+            (
+                MidiTrigger::ControlChange { channel, control },
+                MidiMessage::ControlChange {
+                    channel: msg_channel,
+                    control: msg_control,
+                    value,
+                },
+            ) => {
+                *value > 0
+                    && control == msg_control
+                    && channel.is_none_or(|channel| channel == *msg_channel)
+            }
+            _ => false,
+        }
+    }
+
+    /// Create the message to send back to the controller to set the
+    /// LED for this trigger to `value`
+    pub fn feedback_message(&self, value: u8) -> [u8; 3] {
+        match self {
+            MidiTrigger::Note { channel, note } => [
+                0x90 | (channel.unwrap_or(0) & 0x0F),
+                *note & 0x7F,
+                value & 0x7F,
+            ],
+            MidiTrigger::ControlChange { channel, control } => [
+                0xB0 | (channel.unwrap_or(0) & 0x0F),
+                *control & 0x7F,
+                value & 0x7F,
+            ],
+        }
+    }
+}
+
+impl MidiFeedback {
+    /// Get the value to send for an indicator
+    pub fn get_value(&self, indicator: DeviceIndicator) -> u8 {
+        match indicator {
+            DeviceIndicator::Success => self.success,
+            DeviceIndicator::Error => self.error,
+            DeviceIndicator::Warning => self.warning,
+            DeviceIndicator::Loading => self.loading,
+            DeviceIndicator::None => 0,
+        }
+    }
+}
+
+pub struct Midi {
+    db: DbPool,
+
+    /// Sender for messages received from input ports
+    event_tx: MidiEventSender,
+
+    /// Current MIDI bindings
+    bindings: RwLock<Vec<MidiBindingModel>>,
+
+    /// Open connections to input ports by port name
+    inputs: Mutex<HashMap<String, MidiInputConnection<()>>>,
+
+    /// Open connections to output ports by port name, used for LED feedback
+    outputs: Mutex<HashMap<String, MidiOutputConnection>>,
+
+    /// Client used to list the available ports, created on first use and
+    /// reused for every scan
+    client: Mutex<Option<MidiInput>>,
+}
+
+impl Midi {
+    pub fn new(db: DbPool, event_tx: MidiEventSender) -> Self {
+        Self {
+            db,
+            event_tx,
+            bindings: Default::default(),
+            inputs: Default::default(),
+            outputs: Default::default(),
+            client: Default::default(),
+        }
+    }
+
+    /// Get the names of all available MIDI input ports
+    pub fn get_input_ports(&self) -> anyhow::Result<Vec<String>> {
+        let client = &mut *self.client.lock();
+        let input = match client {
+            Some(value) => value,
+            None => client.insert(MidiInput::new(MIDI_CLIENT_NAME)?),
+        };
+
+        Ok(get_port_names(input))
+    }
+
+    /// Reload the bindings from the database and connect to any new ports
+    pub async fn reload_bindings(&self) -> anyhow::Result<()> {
+        let bindings = MidiBindingModel::all(&self.db).await?;
+        *self.bindings.write() = bindings;
+        self.connect_ports();
+        Ok(())
+    }
+
+    /// Background task that periodically connects to bound ports
+    /// that have been plugged in
+    pub async fn run_port_scan(self: Arc<Self>) {
+        loop {
+            self.connect_ports();
+            tokio::time::sleep(PORT_SCAN_INTERVAL).await;
+        }
+    }
+
+    /// Connect to all ports that have bindings, closes connections to
+    /// ports that are no longer bound or no longer present
+    pub fn connect_ports(&self) {
+        let wanted: HashSet<String> = self
+            .bindings
+            .read()
+            .iter()
+            .map(|binding| binding.port_name.clone())
+            .collect();
+
+        let available: HashSet<String> = match self.get_input_ports() {
+            Ok(value) => value.into_iter().collect(),
+            Err(cause) => {
+                tracing::debug!(?cause, "midi input is not available");
+                return;
+            }
+        };
+
+        let present: Vec<&String> = wanted.intersection(&available).collect();
+
+        {
+            let inputs = &mut *self.inputs.lock();
+            inputs.retain(|port_name, _| present.contains(&port_name));
+
+            for port_name in &present {
+                if inputs.contains_key(*port_name) {
+                    continue;
+                }
+
+                match connect_input(port_name, self.event_tx.clone()) {
+                    Ok(connection) => {
+                        tracing::debug!(?port_name, "connected to midi input port");
+                        inputs.insert(port_name.to_string(), connection);
+                    }
+                    Err(cause) => {
+                        tracing::warn!(?cause, ?port_name, "failed to connect to midi input port");
+                    }
+                }
+            }
+        }
+
+        {
+            let outputs = &mut *self.outputs.lock();
+            outputs.retain(|port_name, _| present.contains(&port_name));
+
+            for port_name in &present {
+                if outputs.contains_key(*port_name) {
+                    continue;
+                }
+
+                // Not all controllers have an output port for feedback
+                if let Ok(connection) = connect_output(port_name) {
+                    outputs.insert(port_name.to_string(), connection);
+                }
+            }
+        }
+    }
+
+    /// Get the tiles bound to a message from a port
+    pub fn get_bound_tiles(&self, port_name: &str, message: &MidiMessage) -> Vec<TileId> {
+        self.bindings
+            .read()
+            .iter()
+            .filter(|binding| {
+                binding.port_name == port_name && binding.config.trigger.matches(message)
+            })
+            .map(|binding| binding.tile_id)
+            .collect()
+    }
+
+    /// Send LED feedback for an indicator to the controllers bound to
+    /// the tile, the feedback is cleared after `duration` milliseconds
+    pub fn display_tile_indicator(
+        self: &Arc<Self>,
+        tile_id: TileId,
+        indicator: DeviceIndicator,
+        duration: u32,
+    ) {
+        let messages: Vec<(String, MidiTrigger, u8)> = self
+            .bindings
+            .read()
+            .iter()
+            .filter(|binding| binding.tile_id == tile_id)
+            .filter_map(|binding| {
+                let feedback = binding.config.feedback.as_ref()?;
+                Some((
+                    binding.port_name.clone(),
+                    binding.config.trigger.clone(),
+                    feedback.get_value(indicator),
+                ))
+            })
+            .collect();
+
+        if messages.is_empty() {
+            return;
+        }
+
+        for (port_name, trigger, value) in &messages {
+            self.send_output(port_name, &trigger.feedback_message(*value));
+        }
+
+        if duration == 0 || matches!(indicator, DeviceIndicator::None) {
+            return;
+        }
+
+        let midi = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(duration as u64)).await;
+
+            for (port_name, trigger, _) in &messages {
+                midi.send_output(port_name, &trigger.feedback_message(0));
+            }
+        });
+    }
+
+    fn send_output(&self, port_name: &str, message: &[u8]) {
+        if let Some(output) = self.outputs.lock().get_mut(port_name)
+            && let Err(cause) = output.send(message)
+        {
+            tracing::warn!(?cause, ?port_name, "failed to send midi feedback");
+        }
+    }
+}
+
+/// Process messages from MIDI input ports executing the bound tiles
+pub async fn process_events(
+    midi: Arc<Midi>,
+    devices: Arc<Devices>,
+    mut event_rx: MidiEventReceiver,
+) {
+    while let Some(event) = event_rx.recv().await {
+        for tile_id in midi.get_bound_tiles(&event.port_name, &event.message) {
+            let devices = devices.clone();
+
+            tokio::spawn(async move {
+                if let Err(cause) = devices.device_execute_tile(MIDI_DEVICE_ID, tile_id).await {
+                    tracing::error!(?cause, ?tile_id, "failed to execute midi bound tile");
+                }
+            });
+        }
+    }
+}
+
+fn get_port_names<T: MidiIO>(io: &T) -> Vec<String> {
+    io.ports()
+        .iter()
+        .filter_map(|port| io.port_name(port).ok())
+        .map(|name| normalize_port_name(&name).to_string())
+        .collect()
+}
+
+/// ALSA port names end with the client and port numbers, these change when a
+/// device is plugged back in so they are removed to keep bindings valid
+fn normalize_port_name(name: &str) -> &str {
+    match name.rsplit_once(' ') {
+        Some((prefix, suffix))
+            if suffix.contains(':')
+                && suffix
+                    .split(':')
+                    .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit())) =>
+        {
+            prefix
+        }
+        _ => name,
+    }
+}
+
+/// Connect to the input port named `port_name` forwarding parsed
+/// messages to `event_tx`, connecting consumes the client so each
+/// connection has its own
+fn connect_input(
+    port_name: &str,
+    event_tx: MidiEventSender,
+) -> anyhow::Result<MidiInputConnection<()>> {
+    let input = MidiInput::new(MIDI_CLIENT_NAME)?;
+    let port = input
+        .ports()
+        .into_iter()
+        .find(|port| {
+            input
+                .port_name(port)
+                .is_ok_and(|name| normalize_port_name(&name) == port_name)
+        })
+        .context("midi input port not found")?;
+
+    let event_port_name = port_name.to_string();
+    let connection = input
+        .connect(
+            &port,
+            "tilepad-input",
+            move |_timestamp, bytes, _| {
+                if let Some(message) = MidiMessage::parse(bytes) {
+                    _ = event_tx.send(MidiEvent {
+                        port_name: event_port_name.clone(),
+                        message,
+                    });
+                }
+            },
+            (),
+        )
+        .map_err(|err| anyhow::anyhow!("failed to connect to midi input: {err}"))?;
+
+    Ok(connection)
+}
+
+/// Connect to the output port named `port_name`
+fn connect_output(port_name: &str) -> anyhow::Result<MidiOutputConnection> {
+    let output = MidiOutput::new(MIDI_CLIENT_NAME)?;
+    let port = output
+        .ports()
+        .into_iter()
+        .find(|port| {
+            output
+                .port_name(port)
+                .is_ok_and(|name| normalize_port_name(&name) == port_name)
+        })
+        .context("midi output port not found")?;
+
+    let connection = output
+        .connect(&port, "tilepad-feedback")
+        .map_err(|err| anyhow::anyhow!("failed to connect to midi output: {err}"))?;
+
+    Ok(connection)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MidiMessage, normalize_port_name};
+    use crate::database::entity::midi_binding::MidiTrigger;
+
+    #[test]
+    fn test_parse_messages() {
+        assert_eq!(
+            MidiMessage::parse(&[0x91, 60, 100]),
+            Some(MidiMessage::NoteOn {
+                channel: 1,
+                note: 60,
+                velocity: 100
+            })
+        );
+        assert_eq!(
+            MidiMessage::parse(&[0x90, 60, 0]),
+            Some(MidiMessage::NoteOff {
+                channel: 0,
+                note: 60
+            })
+        );
+        assert_eq!(
+            MidiMessage::parse(&[0xB2, 7, 127]),
+            Some(MidiMessage::ControlChange {
+                channel: 2,
+                control: 7,
+                value: 127
+            })
+        );
+        assert_eq!(MidiMessage::parse(&[0xF8]), None);
+    }
+
+    #[test]
+    fn test_trigger_matches() {
+        let trigger = MidiTrigger::Note {
+            channel: None,
+            note: 60,
+        };
+        assert!(trigger.matches(&MidiMessage::parse(&[0x93, 60, 1]).unwrap()));
+        assert!(!trigger.matches(&MidiMessage::parse(&[0x83, 60, 0]).unwrap()));
+        assert!(!trigger.matches(&MidiMessage::parse(&[0x93, 61, 1]).unwrap()));
+
+        let trigger = MidiTrigger::ControlChange {
+            channel: Some(0),
+            control: 20,
+        };
+        assert!(trigger.matches(&MidiMessage::parse(&[0xB0, 20, 127]).unwrap()));
+        assert!(!trigger.matches(&MidiMessage::parse(&[0xB0, 20, 0]).unwrap()));
+        assert!(!trigger.matches(&MidiMessage::parse(&[0xB1, 20, 127]).unwrap()));
+    }
+
+    #[test]
+    fn test_normalize_port_name() {
+        assert_eq!(
+            normalize_port_name("Launchpad Mini:Launchpad Mini MIDI 1 24:0"),
+            "Launchpad Mini:Launchpad Mini MIDI 1"
+        );
+        assert_eq!(normalize_port_name("Launchpad Mini"), "Launchpad Mini");
+    }
+}
+
+/// Tests using virtual ports, these require the ALSA sequencer
+#[cfg(all(test, target_os = "linux"))]
+mod alsa_test {
+    use std::time::Duration;
+
+    use midir::{
+        MidiInput, MidiOutput,
+        os::unix::{VirtualInput, VirtualOutput},
+    };
+    use tokio::sync::mpsc;
+
+    use super::{MidiMessage, connect_input, connect_output, get_port_names};
+    use crate::database::entity::midi_binding::MidiTrigger;
+
+    /// Find the name of a port containing `name`
+    fn find_port_name(names: Vec<String>, name: &str) -> String {
+        names
+            .into_iter()
+            .find(|port_name| port_name.contains(name))
+            .expect("virtual port not found")
+    }
+
+    /// Tests receiving input from a virtual ALSA port
+    #[tokio::test]
+    async fn test_virtual_port_input() {
+        let output = MidiOutput::new("tilepad-test-controller").unwrap();
+        let mut controller = output.create_virtual("tilepad-test-pad").unwrap();
+
+        let input = MidiInput::new("tilepad-test").unwrap();
+        let port_name = find_port_name(get_port_names(&input), "tilepad-test-pad");
+
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+        let _connection = connect_input(&port_name, event_tx).unwrap();
+
+        controller.send(&[0x90, 36, 127]).unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), event_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(event.port_name, port_name);
+        assert_eq!(
+            event.message,
+            MidiMessage::NoteOn {
+                channel: 0,
+                note: 36,
+                velocity: 127
+            }
+        );
+    }
+
+    /// Tests sending LED feedback to a virtual ALSA port
+    #[tokio::test]
+    async fn test_virtual_port_feedback() {
+        let input = MidiInput::new("tilepad-test-controller").unwrap();
+
+        let (message_tx, mut message_rx) = mpsc::unbounded_channel();
+        let _controller = input
+            .create_virtual(
+                "tilepad-test-leds",
+                move |_, bytes, _| {
+                    _ = message_tx.send(bytes.to_vec());
+                },
+                (),
+            )
+            .unwrap();
+
+        let output = MidiOutput::new("tilepad-test").unwrap();
+        let port_name = find_port_name(get_port_names(&output), "tilepad-test-leds");
+        let mut connection = connect_output(&port_name).unwrap();
+
+        let trigger = MidiTrigger::Note {
+            channel: Some(2),
+            note: 40,
+        };
+        connection.send(&trigger.feedback_message(127)).unwrap();
+
+        let message = tokio::time::timeout(Duration::from_secs(5), message_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(message, vec![0x92, 40, 127]);
+    }
+}
@@ -4,7 +4,7 @@ pub mod system;
 use std::sync::Arc;
 
 use crate::{
-    database::{DbPool, JsonObject},
+    database::{DbPool, JsonObject, entity::tile::TileControl},
     device::Devices,
     events::{InspectorContext, TileInteractionContext},
     plugin::Plugins,
@@ -34,6 +34,7 @@ pub async fn handle_internal_message(
 }
 
 pub async fn handle_internal_action(
+    plugins: &Plugins,
     devices: &Devices,
     context: TileInteractionContext,
     properties: JsonObject,
@@ -44,7 +45,7 @@ pub async fn handle_internal_action(
         }
 
         "com.tilepad.system.system" => {
-            system::actions::handle(devices, context, properties).await?;
+            system::actions::handle(plugins, devices, context, properties).await?;
         }
 
         plugin_id => {
@@ -54,3 +55,27 @@ pub async fn handle_internal_action(
 
     Ok(())
 }
+
+pub async fn handle_internal_value_changed(
+    devices: &Devices,
+    context: TileInteractionContext,
+    properties: JsonObject,
+    control: &TileControl,
+    value: f64,
+    delta: f64,
+) -> anyhow::Result<()> {
+    match context.plugin_id.as_str() {
+        "com.tilepad.system.system" => {
+            system::actions::handle_value_changed(
+                devices, context, properties, control, value, delta,
+            )
+            .await?;
+        }
+
+        plugin_id => {
+            tracing::warn!(?plugin_id, ?context, "internal action has no value");
+        }
+    }
+
+    Ok(())
+}
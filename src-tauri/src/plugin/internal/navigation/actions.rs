@@ -3,7 +3,7 @@ use crate::{
         JsonObject,
         entity::{folder::FolderId, profile::ProfileId},
     },
-    device::Devices,
+    device::{Devices, protocol::DeviceIndicator},
     events::TileInteractionContext,
 };
 use serde::Deserialize;
@@ -23,20 +23,63 @@ pub async fn handle(
     context: TileInteractionContext,
     properties: JsonObject,
 ) -> anyhow::Result<()> {
+    // Navigation that fails (i.e the device is not allowed to access the
+    // target) shows an error on the tile instead of switching
+    let error_indicator = || {
+        devices.display_tile_indicator(
+            context.device_id,
+            context.tile_id,
+            DeviceIndicator::Error,
+            2000,
+        );
+    };
+
     match context.action_id.as_str() {
         "switch_folder" => {
             let data: SwitchFolderProperties =
                 serde_json::from_value(serde_json::Value::Object(properties))?;
             devices
                 .update_device_folder(context.device_id, data.folder)
-                .await?;
+                .await
+                .inspect_err(|_| error_indicator())?;
         }
         "switch_profile" => {
             let data: SwitchProfileProperties =
                 serde_json::from_value(serde_json::Value::Object(properties))?;
             devices
                 .update_device_profile(context.device_id, data.profile)
-                .await?;
+                .await
+                .inspect_err(|_| error_indicator())?;
+        }
+        "back" => {
+            devices
+                .navigate_device_back(context.device_id)
+                .await
+                .inspect_err(|_| error_indicator())?;
+        }
+        "home" => {
+            devices
+                .navigate_device_home(context.device_id)
+                .await
+                .inspect_err(|_| error_indicator())?;
+        }
+        "previous_profile" => {
+            devices
+                .navigate_device_previous_profile(context.device_id)
+                .await
+                .inspect_err(|_| error_indicator())?;
+        }
+        "next_page" => {
+            devices
+                .change_device_page(context.device_id, 1)
+                .await
+                .inspect_err(|_| error_indicator())?;
+        }
+        "previous_page" => {
+            devices
+                .change_device_page(context.device_id, -1)
+                .await
+                .inspect_err(|_| error_indicator())?;
         }
         action_id => {
             tracing::warn!(?action_id, ?context, "unknown internal action");
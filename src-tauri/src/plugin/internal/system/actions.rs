@@ -8,11 +8,14 @@ use tauri_plugin_opener::{open_path, open_url};
 use tokio::sync::oneshot;
 
 use crate::{
-    database::JsonObject,
+    database::{JsonObject, entity::tile::TileControl},
     device::{Devices, protocol::DeviceIndicator},
     events::TileInteractionContext,
+    plugin::Plugins,
 };
 
+use super::macros;
+
 #[derive(Deserialize)]
 pub struct SystemWebsiteProperties {
     url: Option<String>,
@@ -76,6 +79,7 @@ pub struct ClipboardProperties {
 }
 
 pub async fn handle(
+    plugins: &Plugins,
     devices: &Devices,
     context: TileInteractionContext,
     properties: JsonObject,
@@ -245,6 +249,9 @@ pub async fn handle(
                 success_indicator();
             }
         }
+        "macro" => {
+            macros::run(plugins, devices, context, properties).await?;
+        }
         action_id => {
             tracing::warn!(?action_id, ?context, "unknown internal action");
         }
@@ -253,6 +260,108 @@ pub async fn handle(
     Ok(())
 }
 
+/// Handle a continuous control tile changing value
+pub async fn handle_value_changed(
+    devices: &Devices,
+    context: TileInteractionContext,
+    _properties: JsonObject,
+    control: &TileControl,
+    value: f64,
+    delta: f64,
+) -> anyhow::Result<()> {
+    match context.action_id.as_str() {
+        "volume" => {
+            let volume = control_percent(control, value);
+            let delta = control_range_percent(control, delta);
+
+            set_system_volume(volume, delta).await.inspect_err(|_| {
+                devices.display_tile_indicator(
+                    context.device_id,
+                    context.tile_id,
+                    DeviceIndicator::Error,
+                    2000,
+                );
+            })?;
+        }
+        action_id => {
+            tracing::warn!(?action_id, ?context, "internal action has no value");
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert a control value to a percentage of the control range
+fn control_percent(control: &TileControl, value: f64) -> f64 {
+    control_range_percent(control, value - control.min).clamp(0.0, 100.0)
+}
+
+/// Convert an amount of the control range to a percentage of the range
+fn control_range_percent(control: &TileControl, amount: f64) -> f64 {
+    let range = control.max - control.min;
+    if range <= 0.0 {
+        return 0.0;
+    }
+
+    amount / range * 100.0
+}
+
+/// Set the system output volume to `value` percent
+#[cfg(target_os = "linux")]
+async fn set_system_volume(value: f64, _delta: f64) -> anyhow::Result<()> {
+    let volume = format!("{}%", value.clamp(0.0, 100.0).round());
+    let status = tokio::process::Command::new("pactl")
+        .args(["set-sink-volume", "@DEFAULT_SINK@", &volume])
+        .status()
+        .await?;
+
+    anyhow::ensure!(status.success(), "failed to set volume: {status}");
+    Ok(())
+}
+
+/// Set the system output volume to `value` percent
+#[cfg(target_os = "macos")]
+async fn set_system_volume(value: f64, _delta: f64) -> anyhow::Result<()> {
+    let script = format!(
+        "set volume output volume {}",
+        value.clamp(0.0, 100.0).round()
+    );
+    let status = tokio::process::Command::new("osascript")
+        .args(["-e", &script])
+        .status()
+        .await?;
+
+    anyhow::ensure!(status.success(), "failed to set volume: {status}");
+    Ok(())
+}
+
+/// Changes the system volume by `delta` percent using the volume keys, each
+/// key press changes the volume by 2 percent.
+///
+/// The absolute `value` cannot be applied this way so the system volume only
+/// follows the control while nothing else changes the volume, and changes
+/// smaller than a single key press are dropped
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+async fn set_system_volume(_value: f64, delta: f64) -> anyhow::Result<()> {
+    let presses = (delta / 2.0).round();
+    if presses == 0.0 {
+        return Ok(());
+    }
+
+    let key = if presses > 0.0 {
+        Key::VolumeUp
+    } else {
+        Key::VolumeDown
+    };
+
+    let keys = vec![key; presses.abs() as usize];
+    background_execute_enigo_action(EnoAction::HotKey {
+        modifiers: Vec::new(),
+        keys,
+    })
+    .await
+}
+
 pub enum EnoAction {
     HotKey { modifiers: Vec<Key>, keys: Vec<Key> },
     Key { key: Key },
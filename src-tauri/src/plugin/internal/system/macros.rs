@@ -0,0 +1,102 @@
+use std::{future::Future, pin::Pin, time::Duration};
+
+use serde::Deserialize;
+use tilepad_manifest::plugin::{ActionId, PluginId};
+
+use crate::{
+    database::JsonObject,
+    device::{Devices, protocol::DeviceIndicator},
+    events::TileInteractionContext,
+    plugin::Plugins,
+};
+
+/// Longest delay allowed for a single step
+const MAX_STEP_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize)]
+pub struct MacroProperties {
+    steps: Option<Vec<MacroStep>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum MacroStep {
+    /// Execute an action from a plugin
+    Action {
+        plugin_id: PluginId,
+        action_id: ActionId,
+        #[serde(default)]
+        properties: JsonObject,
+    },
+
+    /// Wait before running the next step
+    Delay {
+        /// Duration to wait in milliseconds
+        duration: u64,
+    },
+}
+
+/// Run the steps of a macro in order, each action step is dispatched the same
+/// way as a tile click. The tile shows a loading indicator while the macro runs
+/// and an error indicator if any of the steps fail.
+///
+/// Steps for external plugins complete once the plugin has been sent the
+/// action, the macro does not wait for the plugin to finish handling it
+pub async fn run(
+    plugins: &Plugins,
+    devices: &Devices,
+    context: TileInteractionContext,
+    properties: JsonObject,
+) -> anyhow::Result<()> {
+    let data: MacroProperties = serde_json::from_value(serde_json::Value::Object(properties))?;
+    let steps = match data.steps {
+        Some(value) if !value.is_empty() => value,
+        _ => return Ok(()),
+    };
+
+    let indicator = |indicator: DeviceIndicator, duration: u32| {
+        devices.display_tile_indicator(context.device_id, context.tile_id, indicator, duration);
+    };
+
+    let step_count = steps.len();
+
+    for (index, step) in steps.into_iter().enumerate() {
+        // Loading indicator is refreshed between steps as the
+        // actions may display their own indicators
+        indicator(DeviceIndicator::Loading, 10_000);
+
+        match step {
+            MacroStep::Action {
+                plugin_id,
+                action_id,
+                properties,
+            } => {
+                let step_context = TileInteractionContext {
+                    device_id: context.device_id,
+                    plugin_id,
+                    action_id,
+                    tile_id: context.tile_id,
+                };
+
+                // Boxed as macros can contain other macros
+                let future: Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send + '_>> =
+                    Box::pin(plugins.handle_action(devices, step_context, properties));
+
+                if let Err(cause) = future.await {
+                    tracing::warn!(?cause, step = index + 1, step_count, "macro step failed");
+                    indicator(DeviceIndicator::Error, 2000);
+                    return Err(cause.context(format!("macro step {} failed", index + 1)));
+                }
+            }
+
+            MacroStep::Delay { duration } => {
+                let duration = Duration::from_millis(duration).min(MAX_STEP_DELAY);
+                tokio::time::sleep(duration).await;
+            }
+        }
+    }
+
+    indicator(DeviceIndicator::Success, 1000);
+
+    Ok(())
+}
@@ -1,2 +1,3 @@
 pub mod actions;
+pub mod macros;
 pub mod messages;
@@ -8,7 +8,11 @@ use std::{
 use crate::{
     database::{
         DbPool, JsonObject,
-        entity::{device::DeviceId, plugin_properties::PluginPropertiesModel, tile::TileModel},
+        entity::{
+            device::DeviceId,
+            plugin_properties::PluginPropertiesModel,
+            tile::{TileControl, TileModel},
+        },
     },
     device::Devices,
     events::{
@@ -220,6 +224,12 @@ impl Plugins {
         self.plugins.read().get(plugin_id).cloned()
     }
 
+    /// Check if a plugin is an internal plugin
+    pub fn is_internal_plugin(&self, plugin_id: &PluginId) -> bool {
+        self.get_plugin(plugin_id)
+            .is_some_and(|plugin| plugin.manifest.plugin.internal.is_some_and(|value| value))
+    }
+
     /// Get a list of all plugins and the state of the plugins task
     pub fn get_plugins_with_state(&self) -> Vec<PluginWithState> {
         let plugins = self.plugins.read();
@@ -399,7 +409,7 @@ impl Plugins {
             .context("plugin not found")?;
 
         if plugin.manifest.plugin.internal.is_some_and(|value| value) {
-            internal::handle_internal_action(devices, ctx, properties).await?;
+            internal::handle_internal_action(self, devices, ctx, properties).await?;
         } else {
             let session = match self.get_plugin_session(&ctx.plugin_id) {
                 Some(value) => value,
@@ -412,6 +422,43 @@ impl Plugins {
         Ok(())
     }
 
+    pub async fn handle_value_changed(
+        &self,
+        devices: &Devices,
+        ctx: TileInteractionContext,
+        properties: JsonObject,
+        control: &TileControl,
+        value: f64,
+        delta: f64,
+    ) -> anyhow::Result<()> {
+        tracing::debug!(?ctx, ?value, ?delta, "changing action value");
+
+        let plugin = self
+            .get_plugin(&ctx.plugin_id)
+            .context("plugin not found")?;
+
+        if plugin.manifest.plugin.internal.is_some_and(|value| value) {
+            internal::handle_internal_value_changed(
+                devices, ctx, properties, control, value, delta,
+            )
+            .await?;
+        } else {
+            let session = match self.get_plugin_session(&ctx.plugin_id) {
+                Some(value) => value,
+                None => return Ok(()),
+            };
+
+            session.send_message(ServerPluginMessage::ValueChanged {
+                ctx,
+                properties,
+                value,
+                delta,
+            });
+        }
+
+        Ok(())
+    }
+
     /// Retrieve the plugin properties from a specific plugin
     pub async fn get_plugin_properties(&self, plugin_id: PluginId) -> anyhow::Result<JsonObject> {
         let result = PluginPropertiesModel::get_by_plugin(&self.db, plugin_id).await?;
@@ -682,6 +729,15 @@ impl Plugins {
             handle.kill().await;
         }
     }
+
+    /// Stop the tasks for all plugins
+    pub async fn stop_all_tasks(&self) {
+        let plugin_ids: Vec<PluginId> = self.tasks.read().keys().cloned().collect();
+
+        for plugin_id in plugin_ids {
+            self.stop_task(&plugin_id).await;
+        }
+    }
 }
 
 /// Task state holder that pushes the state changes to
@@ -76,6 +76,12 @@ pub enum ClientPluginMessage {
     /// Set the current label for a tile
     SetTileLabel { tile_id: TileId, label: TileLabel },
 
+    /// Set the current value for a continuous control tile
+    SetTileValue { tile_id: TileId, value: f64 },
+
+    /// Set the current state index for a multi-state tile
+    SetTileState { tile_id: TileId, state: u32 },
+
     /// Get all currently visible tiles
     GetVisibleTiles,
 
@@ -113,6 +119,16 @@ pub enum ServerPluginMessage {
         properties: JsonObject,
     },
 
+    /// Continuous control tile was moved on a remote device
+    ValueChanged {
+        ctx: TileInteractionContext,
+        properties: JsonObject,
+        /// New value of the tile
+        value: f64,
+        /// Change from the previous value
+        delta: f64,
+    },
+
     /// Got a message from the inspector
     RecvFromInspector {
         ctx: InspectorContext,
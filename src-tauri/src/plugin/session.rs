@@ -256,6 +256,26 @@ impl PluginSession {
                 }
             }
 
+            ClientPluginMessage::SetTileValue { tile_id, value } => {
+                if let Err(cause) = self
+                    .tiles
+                    .update_tile_value(tile_id, Some(plugin_id), value)
+                    .await
+                {
+                    tracing::error!(?cause, "failed to save tile value");
+                }
+            }
+
+            ClientPluginMessage::SetTileState { tile_id, state } => {
+                if let Err(cause) = self
+                    .tiles
+                    .update_tile_state(tile_id, Some(plugin_id), state)
+                    .await
+                {
+                    tracing::error!(?cause, "failed to save tile state");
+                }
+            }
+
             ClientPluginMessage::GetVisibleTiles => {
                 match self.tiles.get_visible_tiles(plugin_id).await {
                     Ok(tiles) => {
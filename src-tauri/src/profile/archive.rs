@@ -0,0 +1,647 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::Cursor,
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tilepad_manifest::plugin::{ActionId, PluginId};
+use tokio::io::BufReader;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        DbPool, JsonObject,
+        entity::{
+            folder::{CreateFolder, FolderConfig, FolderId, FolderModel},
+            profile::{ProfileConfig, ProfileId, ProfileModel},
+            tile::{TileConfig, TileIcon, TileId, TileModel, TilePosition},
+        },
+    },
+    icons::Icons,
+    plugin::Plugins,
+    utils::zip::{create_zip_bytes, create_zip_reader, read_zip_files},
+};
+
+/// Current version of the archive format
+const ARCHIVE_VERSION: u32 = 1;
+
+/// Name of the file within the archive containing the profile
+const PROFILE_FILE: &str = "profile.json";
+
+/// Directory within the archive containing uploaded icons
+const ICONS_DIR: &str = "icons/";
+
+/// Maximum number of files read from an archive
+const MAX_ARCHIVE_FILES: usize = 4096;
+
+/// Maximum combined uncompressed size of the files read from an archive (64MB)
+const MAX_ARCHIVE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// ID of the internal navigation plugin
+const NAVIGATION_PLUGIN_ID: &str = "com.tilepad.system.navigation";
+
+/// ID of the internal system plugin (Contains macros)
+const SYSTEM_PLUGIN_ID: &str = "com.tilepad.system.system";
+
+/// Profile stored within an archive
+#[derive(Serialize, Deserialize)]
+pub struct ProfileArchive {
+    /// Version of the archive format
+    pub version: u32,
+    /// ID of the profile when it was exported
+    pub profile_id: ProfileId,
+    pub name: String,
+    pub config: ProfileConfig,
+    pub folders: Vec<ArchivedFolder>,
+    pub tiles: Vec<ArchivedTile>,
+    /// Plugins used by the tiles
+    pub plugins: Vec<ArchiveDependency>,
+    /// Icon packs used by the tiles
+    pub icon_packs: Vec<ArchiveDependency>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ArchivedFolder {
+    pub id: FolderId,
+    pub name: String,
+    pub config: FolderConfig,
+    pub default: bool,
+    pub order: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ArchivedTile {
+    pub id: TileId,
+    pub folder_id: FolderId,
+    pub config: TileConfig,
+    pub properties: JsonObject,
+    pub plugin_id: PluginId,
+    pub action_id: ActionId,
+    pub position: TilePosition,
+    pub value: f64,
+}
+
+/// Plugin or icon pack required by an archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveDependency {
+    pub id: String,
+    /// Version installed when the archive was created, [None]
+    /// when it was not installed
+    pub version: Option<String>,
+}
+
+/// Outcome of importing a profile archive
+#[derive(Serialize)]
+pub struct ProfileImportResult {
+    /// The imported profile
+    pub profile: ProfileModel,
+    /// Plugins required by the profile that are not installed
+    pub missing_plugins: Vec<ArchiveDependency>,
+    /// Icon packs required by the profile that are not installed
+    pub missing_icon_packs: Vec<ArchiveDependency>,
+    /// Navigation references to folders or profiles outside of the archive
+    pub unresolved_references: Vec<UnresolvedReference>,
+}
+
+/// Tile property referencing a folder or profile that was not
+/// part of the archive
+#[derive(Debug, Serialize)]
+pub struct UnresolvedReference {
+    /// ID of the imported tile
+    pub tile_id: TileId,
+    /// Name of the property containing the reference
+    pub property: String,
+    /// ID that could not be resolved
+    pub value: Uuid,
+}
+
+/// Export a profile along with its folders, tiles and uploaded icons
+/// into a zip archive
+pub async fn export_profile(
+    db: &DbPool,
+    plugins: &Plugins,
+    icons: &Icons,
+    profile_id: ProfileId,
+) -> anyhow::Result<Vec<u8>> {
+    let profile = ProfileModel::get_by_id(db, profile_id)
+        .await?
+        .context("unknown profile")?;
+
+    let mut folders = Vec::new();
+    let mut tiles = Vec::new();
+
+    for folder in FolderModel::all(db, profile_id).await? {
+        for tile in TileModel::get_by_folder(db, folder.id).await? {
+            tiles.push(ArchivedTile {
+                id: tile.id,
+                folder_id: tile.folder_id,
+                config: tile.config,
+                properties: tile.properties,
+                plugin_id: tile.plugin_id,
+                action_id: tile.action_id,
+                position: tile.position,
+                value: tile.value,
+            });
+        }
+
+        folders.push(ArchivedFolder {
+            id: folder.id,
+            name: folder.name,
+            config: folder.config,
+            default: folder.default,
+            order: folder.order,
+        });
+    }
+
+    // Collect the dependencies of the tiles
+    let mut plugin_ids = BTreeMap::new();
+    let mut pack_ids = BTreeMap::new();
+    let mut uploaded = BTreeSet::new();
+
+    for tile in &tiles {
+        plugin_ids.insert(tile.plugin_id.0.clone(), tile.plugin_id.clone());
+
+        for icon in tile.config.icons() {
+            match icon {
+                TileIcon::PluginIcon { plugin_id, .. } => {
+                    plugin_ids.insert(plugin_id.0.clone(), plugin_id.clone());
+                }
+                TileIcon::IconPack { pack_id, .. } => {
+                    pack_ids.insert(pack_id.0.clone(), pack_id.clone());
+                }
+                TileIcon::Uploaded { path } => {
+                    uploaded.insert(path.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let plugins = plugin_ids
+        .into_iter()
+        .map(|(id, plugin_id)| ArchiveDependency {
+            id,
+            version: plugins
+                .get_plugin(&plugin_id)
+                .map(|plugin| plugin.manifest.plugin.version.clone()),
+        })
+        .collect();
+
+    let icon_packs = pack_ids
+        .into_iter()
+        .map(|(id, pack_id)| ArchiveDependency {
+            id,
+            version: icons
+                .get_icon_packs()
+                .into_iter()
+                .find(|pack| pack.manifest.icons.id == pack_id)
+                .map(|pack| pack.manifest.icons.version.clone()),
+        })
+        .collect();
+
+    let archive = ProfileArchive {
+        version: ARCHIVE_VERSION,
+        profile_id: profile.id,
+        name: profile.name,
+        config: profile.config,
+        folders,
+        tiles,
+        plugins,
+        icon_packs,
+    };
+
+    let mut files = vec![(
+        PROFILE_FILE.to_string(),
+        serde_json::to_vec_pretty(&archive)?,
+    )];
+
+    for path in uploaded {
+        let file_path = icons.uploaded_path().join(&path);
+        if !file_path.exists() {
+            continue;
+        }
+
+        let data = tokio::fs::read(&file_path)
+            .await
+            .context("failed to read uploaded icon")?;
+        files.push((format!("{ICONS_DIR}{path}"), data));
+    }
+
+    create_zip_bytes(files).await
+}
+
+/// Import a profile from a zip archive, the profile, folders and tiles are
+/// all given new IDs and references between them are updated to match.
+/// Rows are created within a single transaction, uploaded icons from the
+/// archive are removed again if the import fails
+pub async fn import_profile(
+    db: &DbPool,
+    plugins: &Plugins,
+    icons: &Icons,
+    data: &[u8],
+) -> anyhow::Result<ProfileImportResult> {
+    let reader = BufReader::new(Cursor::new(data));
+    let zip = create_zip_reader(reader).await?;
+    let mut files = read_zip_files(zip, MAX_ARCHIVE_FILES, MAX_ARCHIVE_SIZE, |file_name| {
+        file_name == PROFILE_FILE || file_name.starts_with(ICONS_DIR)
+    })
+    .await?;
+
+    let archive = files
+        .remove(PROFILE_FILE)
+        .context("profile archive missing profile.json")?;
+    let archive: ProfileArchive =
+        serde_json::from_slice(&archive).context("invalid profile archive")?;
+
+    anyhow::ensure!(
+        archive.version <= ARCHIVE_VERSION,
+        "profile archive was created by a newer version of tilepad"
+    );
+
+    // Uploaded icons are stored under new names to avoid replacing existing icons
+    let mut icon_map: HashMap<String, String> = HashMap::new();
+    for (file_name, data) in files {
+        let path = match file_name.strip_prefix(ICONS_DIR) {
+            Some(value) => value.to_string(),
+            None => continue,
+        };
+
+        match icons.upload_user_icon(path.clone(), data).await {
+            Ok(new_path) => {
+                icon_map.insert(path, new_path);
+            }
+            Err(cause) => {
+                remove_imported_icons(icons, icon_map).await;
+                return Err(cause);
+            }
+        }
+    }
+
+    let order = ProfileModel::all(db).await?.len() as u32;
+    let (profile, unresolved_references) =
+        match import_profile_rows(db, &archive, order, &icon_map).await {
+            Ok(value) => value,
+            Err(cause) => {
+                remove_imported_icons(icons, icon_map).await;
+                return Err(cause);
+            }
+        };
+
+    let missing_plugins = archive
+        .plugins
+        .into_iter()
+        .filter(
+            |dependency| match PluginId::try_from(dependency.id.clone()) {
+                Ok(plugin_id) => plugins.get_plugin(&plugin_id).is_none(),
+                Err(_) => true,
+            },
+        )
+        .collect();
+
+    let installed_packs = icons.get_icon_packs();
+    let missing_icon_packs = archive
+        .icon_packs
+        .into_iter()
+        .filter(|dependency| {
+            !installed_packs
+                .iter()
+                .any(|pack| pack.manifest.icons.id.0 == dependency.id)
+        })
+        .collect();
+
+    Ok(ProfileImportResult {
+        profile,
+        missing_plugins,
+        missing_icon_packs,
+        unresolved_references,
+    })
+}
+
+/// Create the profile, folders and tiles of an imported `archive`
+/// within a single transaction
+async fn import_profile_rows(
+    db: &DbPool,
+    archive: &ProfileArchive,
+    order: u32,
+    icon_map: &HashMap<String, String>,
+) -> anyhow::Result<(ProfileModel, Vec<UnresolvedReference>)> {
+    let mut tx = db.begin().await?;
+
+    let template = ProfileModel {
+        id: archive.profile_id,
+        name: archive.name.clone(),
+        default: false,
+        config: archive.config.clone(),
+        order,
+        deleted_at: None,
+    };
+    let profile = template
+        .create_copy(&mut *tx, archive.name.clone(), order)
+        .await?;
+
+    let mut id_map: HashMap<Uuid, Uuid> = HashMap::new();
+    id_map.insert(archive.profile_id, profile.id);
+
+    let mut folders = Vec::with_capacity(archive.folders.len());
+    for folder in &archive.folders {
+        let created = FolderModel::create(
+            &mut *tx,
+            CreateFolder {
+                name: folder.name.clone(),
+                config: folder.config.clone(),
+                profile_id: profile.id,
+                default: folder.default,
+                order: folder.order,
+            },
+        )
+        .await?;
+
+        id_map.insert(folder.id, created.id);
+        folders.push(created);
+    }
+
+    let mut unresolved_references = Vec::new();
+
+    for tile in &archive.tiles {
+        let folder_id = match id_map.get(&tile.folder_id) {
+            Some(value) => *value,
+            None => continue,
+        };
+
+        let mut config = tile.config.clone();
+        config.icon = remap_uploaded_icon(config.icon, icon_map);
+        for state in &mut config.states {
+            state.icon = remap_uploaded_icon(std::mem::take(&mut state.icon), icon_map);
+        }
+
+        let mut properties = tile.properties.clone();
+        let mut unresolved = Vec::new();
+        remap_references(
+            &tile.plugin_id,
+            &tile.action_id,
+            &mut properties,
+            &id_map,
+            &mut unresolved,
+        );
+
+        let template = TileModel {
+            id: tile.id,
+            config: Default::default(),
+            properties,
+            folder_id,
+            plugin_id: tile.plugin_id.clone(),
+            action_id: tile.action_id.clone(),
+            position: tile.position.clone(),
+            value: tile.value,
+            deleted_at: None,
+        };
+
+        let created = template
+            .create_copy(&mut *tx, folder_id, config, tile.position.clone())
+            .await?;
+        id_map.insert(tile.id, created.id);
+
+        unresolved_references.extend(unresolved.into_iter().map(|(property, value)| {
+            UnresolvedReference {
+                tile_id: created.id,
+                property,
+                value,
+            }
+        }));
+    }
+
+    // Alternative layout positions are keyed by tile and must use the imported tiles
+    for folder in folders {
+        let mut config = folder.config.clone();
+        if remap_layout_positions(&mut config, &id_map) {
+            folder.set_config(&mut *tx, config).await?;
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok((profile, unresolved_references))
+}
+
+/// Remove the uploaded icons created while importing an archive
+async fn remove_imported_icons(icons: &Icons, icon_map: HashMap<String, String>) {
+    for path in icon_map.into_values() {
+        if let Err(cause) = icons
+            .handle_tile_change_icon(&TileIcon::Uploaded { path })
+            .await
+        {
+            tracing::error!(?cause, "failed to remove imported icon");
+        }
+    }
+}
+
+/// Update the tile IDs that per-device layout positions are keyed by to their
+/// new IDs, positions of tiles missing from `id_map` are dropped. Returns
+/// whether the config has any layout positions that were updated
+pub fn remap_layout_positions(config: &mut FolderConfig, id_map: &HashMap<Uuid, Uuid>) -> bool {
+    if config
+        .layouts
+        .iter()
+        .all(|layout| layout.positions.is_empty())
+    {
+        return false;
+    }
+
+    for layout in &mut config.layouts {
+        layout.positions = std::mem::take(&mut layout.positions)
+            .into_iter()
+            .filter_map(|(tile_id, position)| Some((*id_map.get(&tile_id)?, position)))
+            .collect();
+    }
+
+    true
+}
+
+/// Update an uploaded icon to use the file name it was imported as, icons
+/// missing from the archive are removed
+fn remap_uploaded_icon(icon: TileIcon, icon_map: &HashMap<String, String>) -> TileIcon {
+    match icon {
+        TileIcon::Uploaded { path } => match icon_map.get(&path) {
+            Some(path) => TileIcon::Uploaded { path: path.clone() },
+            None => TileIcon::None,
+        },
+        icon => icon,
+    }
+}
+
+/// Update the folder and profile IDs referenced by navigation actions (Including
+/// navigation steps within macros) to their imported IDs. References that
+/// are not part of the archive are added to `unresolved`
+pub fn remap_references(
+    plugin_id: &PluginId,
+    action_id: &ActionId,
+    properties: &mut JsonObject,
+    id_map: &HashMap<Uuid, Uuid>,
+    unresolved: &mut Vec<(String, Uuid)>,
+) {
+    match (plugin_id.0.as_str(), action_id.0.as_str()) {
+        (NAVIGATION_PLUGIN_ID, "switch_folder") => {
+            remap_reference(properties, "folder", id_map, unresolved);
+        }
+        (NAVIGATION_PLUGIN_ID, "switch_profile") => {
+            remap_reference(properties, "profile", id_map, unresolved);
+        }
+        (SYSTEM_PLUGIN_ID, "macro") => {
+            let steps = match properties.get_mut("steps").and_then(Value::as_array_mut) {
+                Some(value) => value,
+                None => return,
+            };
+
+            for step in steps {
+                let step = match step.as_object_mut() {
+                    Some(value) => value,
+                    None => continue,
+                };
+
+                let plugin_id = step
+                    .get("plugin_id")
+                    .and_then(Value::as_str)
+                    .and_then(|value| PluginId::try_from(value.to_string()).ok());
+                let action_id = step
+                    .get("action_id")
+                    .and_then(Value::as_str)
+                    .and_then(|value| ActionId::try_from(value.to_string()).ok());
+
+                let (plugin_id, action_id) = match (plugin_id, action_id) {
+                    (Some(plugin_id), Some(action_id)) => (plugin_id, action_id),
+                    _ => continue,
+                };
+
+                if let Some(properties) = step.get_mut("properties").and_then(Value::as_object_mut)
+                {
+                    remap_references(&plugin_id, &action_id, properties, id_map, unresolved);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Update a single property containing a folder or profile ID
+fn remap_reference(
+    properties: &mut JsonObject,
+    property: &str,
+    id_map: &HashMap<Uuid, Uuid>,
+    unresolved: &mut Vec<(String, Uuid)>,
+) {
+    let value = match properties
+        .get(property)
+        .and_then(Value::as_str)
+        .and_then(|value| Uuid::parse_str(value).ok())
+    {
+        Some(value) => value,
+        None => return,
+    };
+
+    match id_map.get(&value) {
+        Some(new_id) => {
+            properties.insert(property.to_string(), Value::String(new_id.to_string()));
+        }
+        None => unresolved.push((property.to_string(), value)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+    use tilepad_manifest::plugin::{ActionId, PluginId};
+    use uuid::Uuid;
+
+    use super::{remap_layout_positions, remap_references};
+    use crate::database::entity::{
+        folder::{FolderConfig, FolderLayout, FolderLayoutTarget, ScreenSizeClass},
+        tile::TilePosition,
+    };
+
+    /// Navigation references within tiles and macro steps are remapped and
+    /// references outside of the archive are reported
+    #[test]
+    fn test_remap_references() {
+        let folder_id = Uuid::new_v4();
+        let new_folder_id = Uuid::new_v4();
+        let external_profile = Uuid::new_v4();
+        let id_map = HashMap::from([(folder_id, new_folder_id)]);
+
+        let mut properties = json!({
+            "steps": [
+                {
+                    "type": "Action",
+                    "plugin_id": "com.tilepad.system.navigation",
+                    "action_id": "switch_folder",
+                    "properties": { "folder": folder_id.to_string() }
+                },
+                {
+                    "type": "Action",
+                    "plugin_id": "com.tilepad.system.navigation",
+                    "action_id": "switch_profile",
+                    "properties": { "profile": external_profile.to_string() }
+                }
+            ]
+        });
+
+        let mut unresolved = Vec::new();
+        remap_references(
+            &PluginId::try_from("com.tilepad.system.system".to_string()).unwrap(),
+            &ActionId::try_from("macro".to_string()).unwrap(),
+            properties.as_object_mut().unwrap(),
+            &id_map,
+            &mut unresolved,
+        );
+
+        assert_eq!(
+            properties["steps"][0]["properties"]["folder"],
+            json!(new_folder_id.to_string())
+        );
+        assert_eq!(unresolved, vec![("profile".to_string(), external_profile)]);
+    }
+
+    /// Layout positions follow the new tile IDs and positions of tiles
+    /// that were not imported are dropped
+    #[test]
+    fn test_remap_layout_positions() {
+        let tile_id = Uuid::new_v4();
+        let new_tile_id = Uuid::new_v4();
+        let missing_tile_id = Uuid::new_v4();
+        let id_map = HashMap::from([(tile_id, new_tile_id)]);
+
+        let position = TilePosition {
+            page: 0,
+            row: 1,
+            column: 2,
+            row_span: 1,
+            column_span: 1,
+        };
+
+        let mut config = FolderConfig {
+            rows: 4,
+            columns: 4,
+            pages: 1,
+            layouts: vec![FolderLayout {
+                target: FolderLayoutTarget::SizeClass {
+                    size_class: ScreenSizeClass::Small,
+                },
+                rows: 2,
+                columns: 4,
+                positions: HashMap::from([
+                    (tile_id, position.clone()),
+                    (missing_tile_id, position.clone()),
+                ]),
+            }],
+        };
+
+        assert!(remap_layout_positions(&mut config, &id_map));
+        assert_eq!(
+            config.layouts[0].positions,
+            HashMap::from([(new_tile_id, position)])
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use crate::database::entity::tile::{TileId, TileModel, TilePosition};
+
+/// Check if a position including its spans fits within a grid of `rows` and
+/// `columns` on one of the first `pages` pages
+pub fn is_within_bounds(position: &TilePosition, rows: u32, columns: u32, pages: u32) -> bool {
+    position.page < pages.max(1)
+        && position.row + position.row_span.max(1) <= rows
+        && position.column + position.column_span.max(1) <= columns
+}
+
+/// Validate that `position` fits within the grid and does not overlap any of
+/// the `tiles` on the same page, the tile being moved can be excluded using `exclude`
+pub fn validate_position(
+    tiles: &[TileModel],
+    exclude: Option<TileId>,
+    position: &TilePosition,
+    rows: u32,
+    columns: u32,
+    pages: u32,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        is_within_bounds(position, rows, columns, pages),
+        "tile position is outside of the folder grid"
+    );
+
+    let grid = TileGrid::with_positions(
+        columns,
+        tiles
+            .iter()
+            .filter(|tile| exclude.is_none_or(|tile_id| tile.id != tile_id))
+            .map(|tile| &tile.position)
+            .filter(|other| other.page == position.page),
+    );
+
+    anyhow::ensure!(
+        grid.is_free(
+            position.row,
+            position.column,
+            position.row_span.max(1),
+            position.column_span.max(1)
+        ),
+        "tile position overlaps another tile"
+    );
+
+    Ok(())
+}
+
+/// Find new positions for tiles that would be outside of a grid of `rows`,
+/// `columns` and `pages`, tiles that still fit keep their position. Returns the
+/// new positions for the stranded tiles or [None] when they cannot all be placed
+pub fn reflow_stranded(
+    tiles: &[TileModel],
+    rows: u32,
+    columns: u32,
+    pages: u32,
+) -> Option<Vec<(TileId, TilePosition)>> {
+    let (fitting, mut stranded): (Vec<&TileModel>, Vec<&TileModel>) = tiles
+        .iter()
+        .partition(|tile| is_within_bounds(&tile.position, rows, columns, pages));
+
+    stranded.sort_by_key(|tile| {
+        let position = &tile.position;
+        (position.page, position.row, position.column)
+    });
+
+    let mut grid = FolderGrid::with_positions(
+        rows,
+        columns,
+        pages,
+        fitting.iter().map(|tile| &tile.position),
+    );
+
+    stranded
+        .into_iter()
+        .map(|tile| {
+            let position = grid.place(&tile.position)?;
+            Some((tile.id, position))
+        })
+        .collect()
+}
+
+/// Grid tracking the occupied cells across all pages of a folder
+pub struct FolderGrid {
+    rows: u32,
+    pages: Vec<TileGrid>,
+}
+
+impl FolderGrid {
+    /// Create a grid with the provided `positions` already occupied, positions
+    /// on pages outside of the grid are ignored
+    pub fn with_positions<'a>(
+        rows: u32,
+        columns: u32,
+        pages: u32,
+        positions: impl IntoIterator<Item = &'a TilePosition>,
+    ) -> Self {
+        let mut pages: Vec<TileGrid> = (0..pages.max(1)).map(|_| TileGrid::new(columns)).collect();
+
+        for position in positions {
+            if let Some(grid) = pages.get_mut(position.page as usize) {
+                grid.fill(position);
+            }
+        }
+
+        Self { rows, pages }
+    }
+
+    /// Place a tile into the folder, the tile keeps its current position when
+    /// that space is free otherwise the first free space on its own page is
+    /// used followed by the other pages in order. Returns [None] if the tile
+    /// does not fit anywhere
+    pub fn place(&mut self, position: &TilePosition) -> Option<TilePosition> {
+        let rows = self.rows;
+        let own_page = position.page as usize;
+
+        if let Some(position) = self
+            .pages
+            .get_mut(own_page)
+            .and_then(|grid| grid.place_within(position, rows))
+        {
+            return Some(position);
+        }
+
+        self.pages
+            .iter_mut()
+            .enumerate()
+            .filter(|(page, _)| *page != own_page)
+            .find_map(|(page, grid)| {
+                let position = TilePosition {
+                    page: page as u32,
+                    ..position.clone()
+                };
+                grid.place_within(&position, rows)
+            })
+    }
+}
+
+/// Grid tracking which cells are occupied by tiles on a single page
+pub struct TileGrid {
+    columns: u32,
+    cells: Vec<Vec<bool>>,
+}
+
+impl TileGrid {
+    pub fn new(columns: u32) -> Self {
+        Self {
+            columns,
+            cells: Vec::new(),
+        }
+    }
+
+    /// Create a grid with the provided `positions` already occupied
+    pub fn with_positions<'a>(
+        columns: u32,
+        positions: impl IntoIterator<Item = &'a TilePosition>,
+    ) -> Self {
+        let mut grid = Self::new(columns);
+        for position in positions {
+            grid.fill(position);
+        }
+        grid
+    }
+
+    /// Number of rows that have had cells filled
+    pub fn rows(&self) -> u32 {
+        self.cells.len() as u32
+    }
+
+    pub fn is_free(&self, row: u32, column: u32, row_span: u32, column_span: u32) -> bool {
+        if column + column_span > self.columns {
+            return false;
+        }
+
+        (row..row + row_span).all(|row| {
+            (column..column + column_span).all(|column| {
+                self.cells
+                    .get(row as usize)
+                    .is_none_or(|cells| !cells[column as usize])
+            })
+        })
+    }
+
+    pub fn fill(&mut self, position: &TilePosition) {
+        let row_span = position.row_span.max(1);
+        let column_span = position.column_span.max(1);
+
+        for row in position.row..position.row + row_span {
+            while self.cells.len() <= row as usize {
+                self.cells.push(vec![false; self.columns as usize]);
+            }
+
+            for column in position.column..(position.column + column_span).min(self.columns) {
+                self.cells[row as usize][column as usize] = true;
+            }
+        }
+    }
+
+    /// Place a tile of the same size as `position` into the first free space,
+    /// spans wider than the grid are reduced to fit
+    pub fn place(&mut self, position: &TilePosition) -> TilePosition {
+        let row_span = position.row_span.max(1);
+        let column_span = position.column_span.clamp(1, self.columns);
+
+        let mut row = 0;
+        loop {
+            if let Some(position) = self.place_in_row(position.page, row, row_span, column_span) {
+                return position;
+            }
+
+            row += 1;
+        }
+    }
+
+    /// Place a tile into a grid limited to `rows` rows, the tile keeps its
+    /// current position when that space is free otherwise the first free
+    /// space that fits the tile is used. Returns [None] if the tile does
+    /// not fit anywhere
+    pub fn place_within(&mut self, position: &TilePosition, rows: u32) -> Option<TilePosition> {
+        let row_span = position.row_span.max(1);
+        let column_span = position.column_span.max(1);
+
+        if column_span > self.columns || row_span > rows {
+            return None;
+        }
+
+        if position.row + row_span <= rows
+            && self.is_free(position.row, position.column, row_span, column_span)
+        {
+            let position = TilePosition {
+                row_span,
+                column_span,
+                ..position.clone()
+            };
+            self.fill(&position);
+            return Some(position);
+        }
+
+        (0..=rows - row_span)
+            .find_map(|row| self.place_in_row(position.page, row, row_span, column_span))
+    }
+
+    fn place_in_row(
+        &mut self,
+        page: u32,
+        row: u32,
+        row_span: u32,
+        column_span: u32,
+    ) -> Option<TilePosition> {
+        let column =
+            (0..self.columns).find(|column| self.is_free(row, *column, row_span, column_span))?;
+
+        let position = TilePosition {
+            page,
+            row,
+            column,
+            row_span,
+            column_span,
+        };
+        self.fill(&position);
+        Some(position)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TileGrid, reflow_stranded, validate_position};
+    use crate::database::entity::tile::{TilePosition, mock_tile};
+
+    fn position(row: u32, column: u32, row_span: u32, column_span: u32) -> TilePosition {
+        TilePosition {
+            page: 0,
+            row,
+            column,
+            row_span,
+            column_span,
+        }
+    }
+
+    /// Positions are rejected when a span overlaps another tile or leaves the grid
+    #[test]
+    fn test_validate_position() {
+        let tiles = vec![mock_tile(position(0, 0, 2, 2))];
+
+        assert!(validate_position(&tiles, None, &position(1, 1, 1, 1), 3, 3, 1).is_err());
+        assert!(validate_position(&tiles, None, &position(0, 2, 1, 2), 3, 3, 1).is_err());
+        assert!(validate_position(&tiles, None, &position(2, 0, 1, 3), 3, 3, 1).is_ok());
+        assert!(
+            validate_position(&tiles, Some(tiles[0].id), &position(1, 1, 1, 1), 3, 3, 1).is_ok()
+        );
+    }
+
+    /// Shrinking a grid relocates only the stranded tiles
+    #[test]
+    fn test_reflow_stranded() {
+        let tiles = vec![
+            mock_tile(position(0, 0, 1, 1)),
+            mock_tile(position(0, 3, 1, 1)),
+        ];
+
+        let positions = reflow_stranded(&tiles, 2, 2, 1).unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].0, tiles[1].id);
+        assert_eq!((positions[0].1.row, positions[0].1.column), (0, 1));
+
+        // Overflow onto the second page
+        let positions = reflow_stranded(&tiles, 1, 1, 2).unwrap();
+        assert_eq!(positions[0].1.page, 1);
+
+        assert!(reflow_stranded(&tiles, 1, 1, 1).is_none());
+    }
+
+    /// Tiles keep their position when free, move to the first free space
+    /// when taken and fail to place when the grid is full
+    #[test]
+    fn test_place_within() {
+        let existing = [position(0, 0, 1, 1), position(0, 1, 1, 1)];
+        let mut grid = TileGrid::with_positions(2, existing.iter());
+
+        let kept = grid.place_within(&position(1, 1, 1, 1), 2).unwrap();
+        assert_eq!((kept.row, kept.column), (1, 1));
+
+        let moved = grid.place_within(&position(0, 0, 1, 1), 2).unwrap();
+        assert_eq!((moved.row, moved.column), (1, 0));
+
+        assert!(grid.place_within(&position(0, 0, 1, 1), 2).is_none());
+    }
+}
@@ -3,18 +3,29 @@ use crate::{
         DbPool, JsonObject,
         entity::{
             device::DeviceId,
+            folder::{CreateFolder, FolderConfig, FolderId, FolderModel},
+            profile::{ProfileId, ProfileModel},
             tile::{
-                TileIcon, TileIconOptions, TileId, TileLabel, TileModel, TilePosition, UpdateKind,
+                CreateTile, TileConfig, TileControl, TileIcon, TileIconOptions, TileId, TileLabel,
+                TileModel, TilePosition, TileState, UpdateKind,
             },
         },
     },
     device::{Devices, protocol::DeviceIndicator},
     events::DisplayContext,
     icons::Icons,
+    profile::archive::{remap_layout_positions, remap_references},
 };
 use anyhow::Context;
-use std::sync::Arc;
+use grid::FolderGrid;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 use tilepad_manifest::plugin::PluginId;
+use uuid::Uuid;
+
+pub mod grid;
 
 pub struct Tiles {
     /// Access to the database
@@ -52,6 +63,102 @@ impl Tiles {
         Ok(tile)
     }
 
+    /// Get a folder by ID
+    async fn get_folder(&self, folder_id: FolderId) -> anyhow::Result<FolderModel> {
+        let folder = FolderModel::get_by_id(&self.db, folder_id)
+            .await?
+            .context("folder not found")?;
+        Ok(folder)
+    }
+
+    /// Create a new tile, the position of the tile must be within the
+    /// folder grid and must not overlap any other tiles
+    pub async fn create_tile(&self, create: CreateTile) -> anyhow::Result<TileModel> {
+        let folder = self.get_folder(create.folder_id).await?;
+        let tiles = TileModel::get_by_folder(&self.db, folder.id).await?;
+
+        grid::validate_position(
+            &tiles,
+            None,
+            &create.position,
+            folder.config.rows,
+            folder.config.columns,
+            folder.config.pages,
+        )?;
+
+        let tile = TileModel::create(&self.db, create).await?;
+        self.devices.background_update_folder(tile.folder_id);
+        Ok(tile)
+    }
+
+    /// Get the tiles that would no longer fit within the folder
+    /// `folder_id` if it was resized to `rows`, `columns` and `pages`
+    pub async fn get_resize_conflicts(
+        &self,
+        folder_id: FolderId,
+        rows: u32,
+        columns: u32,
+        pages: u32,
+    ) -> anyhow::Result<Vec<TileModel>> {
+        let tiles = TileModel::get_by_folder(&self.db, folder_id).await?;
+
+        Ok(tiles
+            .into_iter()
+            .filter(|tile| !grid::is_within_bounds(&tile.position, rows, columns, pages))
+            .collect())
+    }
+
+    /// Update the config of a folder, when the grid shrinks tiles that no longer
+    /// fit are relocated into free space if `reflow` is enabled otherwise the
+    /// update is rejected. Relocated tiles and the config are updated within a
+    /// single transaction, nothing is changed if the tiles cannot be relocated
+    pub async fn update_folder_config(
+        &self,
+        folder_id: FolderId,
+        mut config: FolderConfig,
+        reflow: bool,
+    ) -> anyhow::Result<FolderModel> {
+        config.pages = config.pages.max(1);
+
+        let folder = self.get_folder(folder_id).await?;
+        let tiles = TileModel::get_by_folder(&self.db, folder_id).await?;
+
+        let stranded = tiles
+            .iter()
+            .filter(|tile| {
+                !grid::is_within_bounds(&tile.position, config.rows, config.columns, config.pages)
+            })
+            .count();
+
+        let mut positions: HashMap<TileId, TilePosition> = HashMap::new();
+
+        if stranded > 0 {
+            anyhow::ensure!(
+                reflow,
+                "{stranded} tiles would be outside of the resized folder"
+            );
+
+            positions = grid::reflow_stranded(&tiles, config.rows, config.columns, config.pages)
+                .context("not enough free space in the resized folder for all tiles")?
+                .into_iter()
+                .collect();
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        for tile in tiles {
+            if let Some(position) = positions.remove(&tile.id) {
+                tile.update_position(&mut *tx, position).await?;
+            }
+        }
+
+        let folder = folder.set_config(&mut *tx, config).await?;
+        tx.commit().await?;
+
+        self.devices.background_update_folder(folder.id);
+        Ok(folder)
+    }
+
     /// Forward handling a plugin message onto the devices
     pub async fn handle_plugin_message(
         &self,
@@ -95,12 +202,16 @@ impl Tiles {
         icon: TileIcon,
         kind: UpdateKind,
     ) -> anyhow::Result<TileModel> {
+        let is_plugin = plugin_id.is_some();
         let tile = self.get_tile(tile_id, plugin_id).await?;
 
-        // Handle change in icon when using an uploaded icon (Remove the old file)
-        self.icons
-            .handle_tile_change_icon(&tile.config.icon)
-            .await?;
+        // Handle change in icon when using an uploaded icon (Remove the old file), icons
+        // replaced by the user are kept for undo and removed later by the history
+        if is_plugin {
+            self.icons
+                .handle_tile_change_icon(&tile.config.icon)
+                .await?;
+        }
 
         let tile = tile.update_icon(&self.db, icon, kind).await?;
         self.devices.background_update_folder(tile.folder_id);
@@ -134,6 +245,71 @@ impl Tiles {
         Ok(tile)
     }
 
+    /// Update the kind and range of control for a tile
+    pub async fn update_tile_control(
+        &self,
+        tile_id: TileId,
+        plugin_id: Option<PluginId>,
+        control: TileControl,
+    ) -> anyhow::Result<TileModel> {
+        let tile = self.get_tile(tile_id, plugin_id).await?;
+        let tile = tile.update_control(&self.db, control).await?;
+        self.devices.background_update_folder(tile.folder_id);
+        Ok(tile)
+    }
+
+    /// Update the states of a multi-state tile
+    pub async fn update_tile_states(
+        &self,
+        tile_id: TileId,
+        plugin_id: Option<PluginId>,
+        states: Vec<TileState>,
+        cycle_states: bool,
+    ) -> anyhow::Result<TileModel> {
+        let tile = self.get_tile(tile_id, plugin_id).await?;
+        let tile = tile.update_states(&self.db, states, cycle_states).await?;
+        self.devices.background_update_folder(tile.folder_id);
+        Ok(tile)
+    }
+
+    /// Set the current state of a multi-state tile
+    pub async fn update_tile_state(
+        &self,
+        tile_id: TileId,
+        plugin_id: Option<PluginId>,
+        state: u32,
+    ) -> anyhow::Result<TileModel> {
+        let tile = self.get_tile(tile_id, plugin_id).await?;
+
+        anyhow::ensure!(
+            (state as usize) < tile.config.states.len(),
+            "tile does not have a state {state}"
+        );
+
+        let tile = tile.update_state(&self.db, state).await?;
+        self.devices
+            .update_tile_state_devices(tile.folder_id, tile.id, state)
+            .await?;
+        Ok(tile)
+    }
+
+    /// Set the current value of a continuous control tile, the value is
+    /// clamped to the control range and pushed to devices
+    pub async fn update_tile_value(
+        &self,
+        tile_id: TileId,
+        plugin_id: Option<PluginId>,
+        value: f64,
+    ) -> anyhow::Result<TileModel> {
+        let tile = self.get_tile(tile_id, plugin_id).await?;
+        let value = tile.config.control.clamp_value(value);
+        let tile = tile.update_value(&self.db, value).await?;
+        self.devices
+            .update_tile_value_devices(tile.folder_id, tile.id, value)
+            .await?;
+        Ok(tile)
+    }
+
     /// Change the row and column position of a tile
     pub async fn update_tile_position(
         &self,
@@ -142,11 +318,198 @@ impl Tiles {
         position: TilePosition,
     ) -> anyhow::Result<TileModel> {
         let tile = self.get_tile(tile_id, plugin_id).await?;
+        let folder = self.get_folder(tile.folder_id).await?;
+        let tiles = TileModel::get_by_folder(&self.db, folder.id).await?;
+
+        grid::validate_position(
+            &tiles,
+            Some(tile.id),
+            &position,
+            folder.config.rows,
+            folder.config.columns,
+            folder.config.pages,
+        )?;
+
         let tile = tile.update_position(&self.db, position).await?;
         self.devices.background_update_folder(tile.folder_id);
         Ok(tile)
     }
 
+    /// Duplicate a tile within its own folder, the copy is placed
+    /// into the first free space
+    pub async fn duplicate_tile(&self, tile_id: TileId) -> anyhow::Result<TileModel> {
+        let tile = self.get_tile(tile_id, None).await?;
+        let mut tiles = self.copy_tiles(vec![tile.id], tile.folder_id).await?;
+        tiles.pop().context("tile was not copied")
+    }
+
+    /// Copy a selection of tiles into the folder `folder_id`. Tiles keep their
+    /// position when it is free in the target folder otherwise they are placed
+    /// into the first free space. Nothing is copied if the selection does not fit
+    pub async fn copy_tiles(
+        &self,
+        tile_ids: Vec<TileId>,
+        folder_id: FolderId,
+    ) -> anyhow::Result<Vec<TileModel>> {
+        let folder = self.get_folder(folder_id).await?;
+
+        let mut sources = Vec::with_capacity(tile_ids.len());
+        for tile_id in tile_ids {
+            sources.push(self.get_tile(tile_id, None).await?);
+        }
+
+        // Place tiles in reading order so the selection keeps its arrangement
+        sources.sort_by_key(|tile| (tile.position.page, tile.position.row, tile.position.column));
+
+        let existing = TileModel::get_by_folder(&self.db, folder_id).await?;
+        let mut grid = FolderGrid::with_positions(
+            folder.config.rows,
+            folder.config.columns,
+            folder.config.pages,
+            existing.iter().map(|tile| &tile.position),
+        );
+
+        let mut positions = Vec::with_capacity(sources.len());
+        for tile in &sources {
+            let position = grid
+                .place(&tile.position)
+                .context("not enough free space in the target folder")?;
+            positions.push(position);
+        }
+
+        let mut tiles = Vec::with_capacity(sources.len());
+        for (tile, position) in sources.iter().zip(positions) {
+            let config = self.copy_tile_config(&tile.config).await?;
+            let tile = tile
+                .create_copy(&self.db, folder_id, config, position)
+                .await?;
+            tiles.push(tile);
+        }
+
+        self.devices.background_update_folder(folder_id);
+        Ok(tiles)
+    }
+
+    /// Move a tile into the folder `folder_id`, the folder may belong to
+    /// another profile. The tile keeps its position when it is free in the
+    /// target folder otherwise it is placed into the first free space
+    pub async fn move_tile(
+        &self,
+        tile_id: TileId,
+        folder_id: FolderId,
+    ) -> anyhow::Result<TileModel> {
+        let tile = self.get_tile(tile_id, None).await?;
+        if tile.folder_id == folder_id {
+            return Ok(tile);
+        }
+
+        let folder = self.get_folder(folder_id).await?;
+
+        let existing = TileModel::get_by_folder(&self.db, folder_id).await?;
+        let mut grid = FolderGrid::with_positions(
+            folder.config.rows,
+            folder.config.columns,
+            folder.config.pages,
+            existing.iter().map(|tile| &tile.position),
+        );
+
+        let position = grid
+            .place(&tile.position)
+            .context("not enough free space in the target folder")?;
+
+        let previous_folder_id = tile.folder_id;
+        let tile = tile.update_folder(&self.db, folder_id, position).await?;
+
+        self.devices.background_update_folder(previous_folder_id);
+        self.devices.background_update_folder(folder_id);
+        Ok(tile)
+    }
+
+    /// Create a copy of the profile `profile_id` named `name` including all
+    /// of its folders and tiles in a single transaction. Uploaded icons are
+    /// duplicated and navigation references to folders within the profile
+    /// are updated to point to the copied folders
+    pub async fn copy_profile(
+        &self,
+        profile_id: ProfileId,
+        name: String,
+    ) -> anyhow::Result<ProfileModel> {
+        let profile = ProfileModel::get_by_id(&self.db, profile_id)
+            .await?
+            .context("unknown profile")?;
+        let folders = FolderModel::all(&self.db, profile_id).await?;
+
+        let mut tiles = Vec::new();
+        for folder in &folders {
+            tiles.extend(TileModel::get_by_folder(&self.db, folder.id).await?);
+        }
+
+        // Icons are copied up front and removed again if the copy fails
+        let mut configs = Vec::with_capacity(tiles.len());
+        for tile in &tiles {
+            match self.copy_tile_config(&tile.config).await {
+                Ok(config) => configs.push(config),
+                Err(cause) => {
+                    self.remove_copied_icons(&configs).await;
+                    return Err(cause);
+                }
+            }
+        }
+
+        let order = ProfileModel::all(&self.db).await?.len() as u32;
+        let result =
+            copy_profile_rows(&self.db, &profile, name, order, &folders, &tiles, &configs).await;
+
+        if result.is_err() {
+            self.remove_copied_icons(&configs).await;
+        }
+
+        result
+    }
+
+    /// Remove uploaded icons that are not used by any tile (Including tiles
+    /// in the trash). Replaced icons are kept while they can be restored by
+    /// the in-memory undo history so any left when the app closes are removed
+    /// by this on the next startup
+    pub async fn remove_unused_icons(&self) -> anyhow::Result<()> {
+        let tiles = TileModel::all_with_deleted(&self.db).await?;
+
+        let used: HashSet<String> = tiles
+            .iter()
+            .flat_map(|tile| tile.config.icons())
+            .filter_map(|icon| match icon {
+                TileIcon::Uploaded { path } => Some(path.clone()),
+                _ => None,
+            })
+            .collect();
+
+        self.icons.remove_unused_uploaded_icons(&used).await
+    }
+
+    /// Remove the uploaded icons that were copied for `configs`
+    async fn remove_copied_icons(&self, configs: &[TileConfig]) {
+        for config in configs {
+            for icon in config.icons() {
+                if let Err(cause) = self.icons.handle_tile_change_icon(icon).await {
+                    tracing::error!(?cause, "failed to remove copied icon");
+                }
+            }
+        }
+    }
+
+    /// Copy a tile config for use by a new tile, uploaded icons are copied
+    /// so that the new tile does not share the files with the original
+    async fn copy_tile_config(&self, config: &TileConfig) -> anyhow::Result<TileConfig> {
+        let mut config = config.clone();
+        config.icon = self.icons.copy_uploaded_icon(&config.icon).await?;
+
+        for state in &mut config.states {
+            state.icon = self.icons.copy_uploaded_icon(&state.icon).await?;
+        }
+
+        Ok(config)
+    }
+
     /// Get all tiles that are currently visible
     pub async fn get_visible_tiles(&self, plugin_id: PluginId) -> anyhow::Result<Vec<TileModel>> {
         // Load all connected devices
@@ -170,3 +533,73 @@ impl Tiles {
             .display_tile_indicator(device_id, tile_id, indicator, duration);
     }
 }
+
+/// Insert the copies of a `profile` along with its `folders` and `tiles` within
+/// a single transaction, `configs` are the copied configs for each of the `tiles`
+async fn copy_profile_rows(
+    db: &DbPool,
+    profile: &ProfileModel,
+    name: String,
+    order: u32,
+    folders: &[FolderModel],
+    tiles: &[TileModel],
+    configs: &[TileConfig],
+) -> anyhow::Result<ProfileModel> {
+    let mut tx = db.begin().await?;
+
+    let copy = profile.create_copy(&mut *tx, name, order).await?;
+
+    let mut id_map: HashMap<Uuid, Uuid> = HashMap::new();
+    id_map.insert(profile.id, copy.id);
+
+    let mut folder_copies = Vec::with_capacity(folders.len());
+    for folder in folders {
+        let created = FolderModel::create(
+            &mut *tx,
+            CreateFolder {
+                name: folder.name.clone(),
+                config: folder.config.clone(),
+                profile_id: copy.id,
+                default: folder.default,
+                order: folder.order,
+            },
+        )
+        .await?;
+
+        id_map.insert(folder.id, created.id);
+        folder_copies.push(created);
+    }
+
+    for (tile, config) in tiles.iter().zip(configs) {
+        let folder_id = *id_map
+            .get(&tile.folder_id)
+            .context("tile folder was not copied")?;
+
+        // References outside of the profile are left pointing at the original
+        let mut template = tile.clone();
+        remap_references(
+            &tile.plugin_id,
+            &tile.action_id,
+            &mut template.properties,
+            &id_map,
+            &mut Vec::new(),
+        );
+
+        let created = template
+            .create_copy(&mut *tx, folder_id, config.clone(), tile.position.clone())
+            .await?;
+        id_map.insert(tile.id, created.id);
+    }
+
+    // Alternative layout positions are keyed by tile and must use the copied tiles
+    for folder in folder_copies {
+        let mut config = folder.config.clone();
+        if remap_layout_positions(&mut config, &id_map) {
+            folder.set_config(&mut *tx, config).await?;
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(copy)
+}
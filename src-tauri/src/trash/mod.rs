@@ -0,0 +1,512 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::Serialize;
+
+use crate::{
+    database::{
+        DbPool,
+        entity::{
+            device::DeviceModel,
+            folder::{FolderId, FolderModel},
+            profile::{ProfileId, ProfileModel},
+            settings::TrashSettings,
+            tile::{TileId, TileModel},
+        },
+    },
+    device::{Devices, get_relocation_folder},
+    history::History,
+    icons::Icons,
+    tile::grid::FolderGrid,
+};
+
+/// Interval between purging expired items from the trash
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Trash bin for deleted profiles, folders and tiles
+pub struct Trash {
+    /// Access to the database
+    db: DbPool,
+
+    /// Access to icons
+    icons: Arc<Icons>,
+
+    /// Access to devices
+    devices: Arc<Devices>,
+
+    /// Access to the undo history
+    history: Arc<History>,
+
+    /// Settings for purging the trash
+    settings: RwLock<TrashSettings>,
+}
+
+/// Items currently in the trash
+#[derive(Debug, Serialize)]
+pub struct TrashContents {
+    pub profiles: Vec<ProfileModel>,
+    pub folders: Vec<FolderModel>,
+    pub tiles: Vec<TileModel>,
+}
+
+impl Trash {
+    pub fn new(
+        db: DbPool,
+        icons: Arc<Icons>,
+        devices: Arc<Devices>,
+        history: Arc<History>,
+        settings: TrashSettings,
+    ) -> Self {
+        Self {
+            db,
+            icons,
+            devices,
+            history,
+            settings: RwLock::new(settings),
+        }
+    }
+
+    pub fn set_settings(&self, settings: TrashSettings) {
+        *self.settings.write() = settings;
+    }
+
+    /// Get the items currently in the trash, items deleted along with
+    /// their profile or folder are restored with that parent
+    pub async fn get_trash(&self) -> anyhow::Result<TrashContents> {
+        let profiles = ProfileModel::all_deleted(&self.db).await?;
+        let folders = FolderModel::all_deleted(&self.db).await?;
+        let tiles = TileModel::all_deleted(&self.db).await?;
+
+        Ok(TrashContents {
+            profiles,
+            folders,
+            tiles,
+        })
+    }
+
+    /// Restore a profile from the trash
+    pub async fn restore_profile(&self, profile_id: ProfileId) -> anyhow::Result<ProfileModel> {
+        ProfileModel::restore_deleted(&self.db, profile_id).await?;
+
+        let profile = ProfileModel::get_by_id(&self.db, profile_id)
+            .await?
+            .context("unknown profile")?;
+        Ok(profile)
+    }
+
+    /// Restore a folder from the trash, the profile of the folder must
+    /// not be in the trash
+    pub async fn restore_folder(&self, folder_id: FolderId) -> anyhow::Result<FolderModel> {
+        let folder = FolderModel::get_deleted_by_id(&self.db, folder_id)
+            .await?
+            .context("folder is not in the trash")?;
+
+        ProfileModel::get_by_id(&self.db, folder.profile_id)
+            .await?
+            .context("profile of the folder is in the trash")?;
+
+        FolderModel::restore_deleted(&self.db, folder_id).await?;
+
+        let folder = FolderModel::get_by_id(&self.db, folder_id)
+            .await?
+            .context("unknown folder")?;
+        Ok(folder)
+    }
+
+    /// Restore a tile from the trash, the tile keeps its position when it
+    /// is still free otherwise it is placed into the first free space
+    pub async fn restore_tile(&self, tile_id: TileId) -> anyhow::Result<TileModel> {
+        let tile = restore_deleted_tile(&self.db, tile_id).await?;
+        self.devices.background_update_folder(tile.folder_id);
+        Ok(tile)
+    }
+
+    /// Permanently delete everything in the trash
+    pub async fn empty_trash(&self) -> anyhow::Result<()> {
+        self.purge(Utc::now()).await
+    }
+
+    /// Permanently delete everything that was moved to the trash before
+    /// `before`, uploaded icons used by the purged tiles are removed
+    async fn purge(&self, before: DateTime<Utc>) -> anyhow::Result<()> {
+        let tiles = TileModel::get_purgeable(&self.db, before).await?;
+
+        let relocations = get_purge_relocations(&self.db, before).await?;
+        let moved_folder_ids: HashSet<FolderId> =
+            relocations.iter().map(|(_, folder)| folder.id).collect();
+
+        purge_deleted(&self.db, before, relocations).await?;
+
+        // Purged tiles can no longer be restored through the history
+        let tile_ids: HashSet<TileId> = tiles.iter().map(|tile| tile.id).collect();
+        self.history.forget_tiles(&tile_ids).await;
+
+        // Update the sessions of the devices that were moved
+        for folder_id in moved_folder_ids {
+            if let Err(cause) = self.devices.update_folder_devices(folder_id).await {
+                tracing::error!(?cause, "failed to update devices moved out of the trash");
+            }
+        }
+
+        for tile in tiles {
+            for icon in tile.config.icons() {
+                if let Err(cause) = self.icons.handle_tile_change_icon(icon).await {
+                    tracing::error!(?cause, "failed to remove purged tile icon");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Background task that purges items that have been in the
+    /// trash for longer than the retention period
+    pub async fn run_purge(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let retention = self.settings.read().retention;
+            let before = Utc::now() - chrono::Duration::days(retention as i64);
+
+            if let Err(cause) = self.purge(before).await {
+                tracing::error!(?cause, "failed to purge trash");
+            }
+        }
+    }
+}
+
+/// Restore the tile `tile_id` from the trash placing it into free space
+/// within its folder, the folder of the tile must not be in the trash
+async fn restore_deleted_tile(db: &DbPool, tile_id: TileId) -> anyhow::Result<TileModel> {
+    let tile = TileModel::get_deleted_by_id(db, tile_id)
+        .await?
+        .context("tile is not in the trash")?;
+
+    let folder = FolderModel::get_by_id(db, tile.folder_id)
+        .await?
+        .context("folder of the tile is in the trash")?;
+
+    let existing = TileModel::get_by_folder(db, folder.id).await?;
+    let mut grid = FolderGrid::with_positions(
+        folder.config.rows,
+        folder.config.columns,
+        folder.config.pages,
+        existing.iter().map(|tile| &tile.position),
+    );
+
+    let position = grid
+        .place(&tile.position)
+        .context("not enough free space in the folder")?;
+
+    let tile = tile.restore_deleted(db, position).await?;
+    Ok(tile)
+}
+
+/// Get the folders to move the devices still using a profile or folder that will
+/// be purged to, devices that are not allowed to access any other folder are
+/// moved to the default folder
+async fn get_purge_relocations(
+    db: &DbPool,
+    before: DateTime<Utc>,
+) -> anyhow::Result<Vec<(DeviceModel, FolderModel)>> {
+    let devices = DeviceModel::all_purgeable(db, before).await?;
+    if devices.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let default_profile = ProfileModel::get_default_profile(db)
+        .await?
+        .context("default profile is missing")?;
+    let default_folder = FolderModel::get_default(db, default_profile.id)
+        .await?
+        .context("default folder is missing")?;
+
+    let mut relocations = Vec::with_capacity(devices.len());
+    for device in devices {
+        let folder = get_relocation_folder(db, &device.config, default_profile.id, None, None)
+            .await?
+            .unwrap_or_else(|| default_folder.clone());
+        relocations.push((device, folder));
+    }
+
+    Ok(relocations)
+}
+
+/// Permanently delete everything that was moved to the trash before `before`,
+/// devices still using a purged profile or folder are moved using `relocations`
+/// first so the purge is not blocked by them
+async fn purge_deleted(
+    db: &DbPool,
+    before: DateTime<Utc>,
+    relocations: Vec<(DeviceModel, FolderModel)>,
+) -> anyhow::Result<()> {
+    let mut tx = db.begin().await?;
+    for (device, folder) in relocations {
+        device
+            .set_profile(&mut *tx, folder.profile_id, folder.id)
+            .await?;
+    }
+    TileModel::purge_deleted(&mut *tx, before).await?;
+    FolderModel::purge_deleted(&mut *tx, before).await?;
+    ProfileModel::purge_deleted(&mut *tx, before).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{get_purge_relocations, purge_deleted, restore_deleted_tile};
+    use crate::database::{
+        DbPool,
+        entity::{
+            device::{CreateDevice, DeviceConfig, DeviceModel},
+            folder::{CreateFolder, FolderId, FolderModel},
+            profile::{CreateProfile, ProfileId, ProfileModel},
+            tile::{CreateTile, TileModel, TilePosition},
+        },
+        mock_database,
+    };
+    use chrono::Utc;
+
+    async fn create_profile(db: &DbPool, default: bool) -> ProfileModel {
+        ProfileModel::create(
+            db,
+            CreateProfile {
+                name: "Profile".to_string(),
+                default,
+                config: Default::default(),
+                order: 0,
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn create_folder(db: &DbPool, profile_id: ProfileId, default: bool) -> FolderModel {
+        FolderModel::create(
+            db,
+            CreateFolder {
+                name: "Folder".to_string(),
+                config: Default::default(),
+                profile_id,
+                default,
+                order: 0,
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn create_tile(db: &DbPool, folder_id: FolderId, column: u32) -> TileModel {
+        TileModel::create(
+            db,
+            CreateTile {
+                config: Default::default(),
+                folder_id,
+                plugin_id: "com.example.test".to_string().try_into().unwrap(),
+                action_id: "test".to_string().try_into().unwrap(),
+                position: TilePosition {
+                    page: 0,
+                    row: 0,
+                    column,
+                    row_span: 1,
+                    column_span: 1,
+                },
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn create_device(
+        db: &DbPool,
+        index: u8,
+        folder: &FolderModel,
+        config: DeviceConfig,
+    ) -> DeviceModel {
+        DeviceModel::create(
+            db,
+            CreateDevice {
+                name: "Device".to_string(),
+                public_key: vec![index; 32],
+                config,
+                profile_id: folder.profile_id,
+                folder_id: folder.id,
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn delete_folder(db: &DbPool, folder_id: FolderId) {
+        let mut conn = db.acquire().await.unwrap();
+        FolderModel::delete(&mut conn, folder_id).await.unwrap();
+    }
+
+    async fn is_tile_deleted(db: &DbPool, tile: &TileModel) -> bool {
+        TileModel::get_deleted_by_id(db, tile.id)
+            .await
+            .unwrap()
+            .is_some()
+    }
+
+    /// Tile trashed on its own before its folder, the tile cannot be restored
+    /// until its folder has been restored and stays in the trash afterwards
+    #[tokio::test]
+    async fn test_restore_tile_with_folder() {
+        let db = mock_database().await;
+        let profile = create_profile(&db, true).await;
+        let folder = create_folder(&db, profile.id, false).await;
+        let trashed = create_tile(&db, folder.id, 0).await;
+        let other = create_tile(&db, folder.id, 1).await;
+
+        TileModel::delete(&db, trashed.id).await.unwrap();
+        delete_folder(&db, folder.id).await;
+
+        // The tile is hidden from the trash while its folder is in the trash
+        // and cannot be restored into the folder
+        assert!(TileModel::all_deleted(&db).await.unwrap().is_empty());
+        assert!(restore_deleted_tile(&db, trashed.id).await.is_err());
+
+        FolderModel::restore_deleted(&db, folder.id).await.unwrap();
+
+        // Only the tile deleted along with the folder is restored
+        assert!(!is_tile_deleted(&db, &other).await);
+        assert!(is_tile_deleted(&db, &trashed).await);
+
+        let deleted = TileModel::all_deleted(&db).await.unwrap();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].id, trashed.id);
+
+        let tile = restore_deleted_tile(&db, trashed.id).await.unwrap();
+        assert_eq!(tile.position, trashed.position);
+        assert!(!is_tile_deleted(&db, &trashed).await);
+    }
+
+    /// Tile restored after another tile took its position is placed
+    /// into the first free space
+    #[tokio::test]
+    async fn test_restore_tile_occupied_position() {
+        let db = mock_database().await;
+        let profile = create_profile(&db, true).await;
+        let folder = create_folder(&db, profile.id, true).await;
+        let trashed = create_tile(&db, folder.id, 0).await;
+
+        TileModel::delete(&db, trashed.id).await.unwrap();
+        let other = create_tile(&db, folder.id, 0).await;
+
+        let tile = restore_deleted_tile(&db, trashed.id).await.unwrap();
+        assert_ne!(tile.position, other.position);
+        assert!(!is_tile_deleted(&db, &trashed).await);
+    }
+
+    /// Purging rows still used by a device moves the device to a folder
+    /// it can access instead of failing the purge
+    #[tokio::test]
+    async fn test_purge_referenced_rows() {
+        let db = mock_database().await;
+        let default_profile = create_profile(&db, true).await;
+        let default_folder = create_folder(&db, default_profile.id, true).await;
+        let allowed_folder = create_folder(&db, default_profile.id, false).await;
+        let folder = create_folder(&db, default_profile.id, false).await;
+        let profile = create_profile(&db, false).await;
+        let profile_folder = create_folder(&db, profile.id, true).await;
+
+        let device = create_device(&db, 0, &folder, DeviceConfig::default()).await;
+        let restricted_device = create_device(
+            &db,
+            1,
+            &profile_folder,
+            DeviceConfig {
+                allowed_profiles: None,
+                allowed_folders: Some(vec![profile_folder.id, allowed_folder.id]),
+            },
+        )
+        .await;
+
+        delete_folder(&db, folder.id).await;
+        {
+            let mut conn = db.acquire().await.unwrap();
+            ProfileModel::delete(&mut conn, profile.id).await.unwrap();
+        }
+
+        let before = Utc::now();
+        let relocations = get_purge_relocations(&db, before).await.unwrap();
+        assert_eq!(relocations.len(), 2);
+        purge_deleted(&db, before, relocations).await.unwrap();
+
+        let device = DeviceModel::get_by_id(&db, device.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(device.folder_id, default_folder.id);
+
+        // Restricted devices are moved to a folder they can access
+        let restricted_device = DeviceModel::get_by_id(&db, restricted_device.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(restricted_device.profile_id, default_profile.id);
+        assert_eq!(restricted_device.folder_id, allowed_folder.id);
+
+        assert!(FolderModel::all_deleted(&db).await.unwrap().is_empty());
+        assert!(ProfileModel::all_deleted(&db).await.unwrap().is_empty());
+        assert!(
+            FolderModel::get_deleted_by_id(&db, profile_folder.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    /// Restoring a profile only restores the folders and tiles that were
+    /// deleted along with the profile
+    #[tokio::test]
+    async fn test_restore_profile_children() {
+        let db = mock_database().await;
+        create_profile(&db, true).await;
+        let profile = create_profile(&db, false).await;
+        let folder = create_folder(&db, profile.id, true).await;
+        let trashed_folder = create_folder(&db, profile.id, false).await;
+        let tile = create_tile(&db, folder.id, 0).await;
+        let trashed_tile = create_tile(&db, folder.id, 1).await;
+
+        TileModel::delete(&db, trashed_tile.id).await.unwrap();
+        delete_folder(&db, trashed_folder.id).await;
+        {
+            let mut conn = db.acquire().await.unwrap();
+            ProfileModel::delete(&mut conn, profile.id).await.unwrap();
+        }
+
+        ProfileModel::restore_deleted(&db, profile.id)
+            .await
+            .unwrap();
+
+        assert!(
+            ProfileModel::get_by_id(&db, profile.id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            FolderModel::get_by_id(&db, folder.id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(!is_tile_deleted(&db, &tile).await);
+
+        // Items trashed before the profile stay in the trash
+        assert!(
+            FolderModel::get_deleted_by_id(&db, trashed_folder.id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(is_tile_deleted(&db, &trashed_tile).await);
+    }
+}
@@ -1,7 +1,16 @@
-use std::path::Path;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
 
+use chrono::{DateTime, TimeDelta, Utc};
+use hkdf::Hkdf;
+use sha2::{Digest, Sha256};
 use x25519_dalek::{PublicKey, StaticSecret};
 
+/// Info string used when deriving key rotation proofs
+const KEY_ROTATION_PROOF_INFO: &[u8] = b"tilepad server key rotation";
+
 /// New-type to store the server private key
 #[derive(Clone)]
 pub struct ServerKeyPair {
@@ -9,6 +18,149 @@ pub struct ServerKeyPair {
     pub public_key: PublicKey,
 }
 
+impl From<StaticSecret> for ServerKeyPair {
+    fn from(private_key: StaticSecret) -> Self {
+        let public_key = PublicKey::from(&private_key);
+        Self {
+            private_key,
+            public_key,
+        }
+    }
+}
+
+/// Server key that was replaced by a rotation, remains valid
+/// for handshakes until it is retired
+#[derive(Clone)]
+pub struct PreviousServerKey {
+    pub key_pair: ServerKeyPair,
+    pub retire_at: DateTime<Utc>,
+}
+
+/// Current and previous server keys along with where they are stored
+pub struct ServerKeys {
+    /// Path to the current private key
+    path: PathBuf,
+    /// Path to the previous private key
+    previous_path: PathBuf,
+
+    /// Current server key
+    pub current: ServerKeyPair,
+    /// Previous server key during a rotation transition window
+    pub previous: Option<PreviousServerKey>,
+}
+
+impl ServerKeys {
+    /// Load the server keys, creates a new current key if one does not exist
+    /// and discards the previous key if it has been retired
+    pub async fn load(path: PathBuf, previous_path: PathBuf) -> std::io::Result<ServerKeys> {
+        let current = setup_private_key(&path).await?;
+        let mut keys = ServerKeys {
+            path,
+            previous: read_previous_private_key(&previous_path).await?,
+            previous_path,
+            current: ServerKeyPair::from(current),
+        };
+
+        keys.retire_previous(Utc::now())?;
+        Ok(keys)
+    }
+
+    /// Get the key pair matching `public_key`, uses the current key when
+    /// no key is specified or the key is unknown or retired
+    pub fn get_key_pair(
+        &self,
+        public_key: Option<&[u8; 32]>,
+        now: DateTime<Utc>,
+    ) -> &ServerKeyPair {
+        match (public_key, &self.previous) {
+            (Some(public_key), Some(previous))
+                if previous.key_pair.public_key.as_bytes() == public_key
+                    && previous.retire_at > now =>
+            {
+                &previous.key_pair
+            }
+            _ => &self.current,
+        }
+    }
+
+    /// Rotate the server key, the current key becomes the previous key
+    /// and stays valid for `window`. Rotating is rejected while an earlier
+    /// previous key is still within its transition window as replacing it
+    /// would lock out clients that have not moved to the current key yet
+    ///
+    /// Key files are written synchronously so rotation and retirement can
+    /// happen while holding a lock on the keys
+    pub fn rotate(&mut self, window: TimeDelta, now: DateTime<Utc>) -> std::io::Result<()> {
+        self.retire_previous(now)?;
+
+        if self.previous.is_some() {
+            return Err(std::io::Error::other(
+                "previous server key has not been retired yet",
+            ));
+        }
+
+        let previous = PreviousServerKey {
+            key_pair: self.current.clone(),
+            retire_at: now + window,
+        };
+
+        // Previous key is written first so the current key is never lost
+        write_previous_private_key(&self.previous_path, &previous)?;
+
+        let current = StaticSecret::random();
+        write_file_atomic(&self.path, current.as_bytes())?;
+
+        self.current = ServerKeyPair::from(current);
+        self.previous = Some(previous);
+        Ok(())
+    }
+
+    /// Retire the previous key if its transition window has ended,
+    /// returns whether the key was retired
+    pub fn retire_previous(&mut self, now: DateTime<Utc>) -> std::io::Result<bool> {
+        match &self.previous {
+            Some(previous) if previous.retire_at <= now => {}
+            _ => return Ok(false),
+        }
+
+        if self.previous_path.exists() {
+            std::fs::remove_file(&self.previous_path)?;
+        }
+
+        self.previous = None;
+        Ok(true)
+    }
+}
+
+/// Creates a fingerprint for a public key, this is the hex encoded
+/// SHA-256 hash of the key bytes
+pub fn public_key_fingerprint(public_key: &[u8]) -> String {
+    hex::encode(Sha256::digest(public_key))
+}
+
+/// Creates proof that the server holds the private key for `new_public_key`,
+/// the client verifies this by deriving the same value using its own private
+/// key and the new server public key
+pub fn key_rotation_proof(
+    new_private_key: &StaticSecret,
+    previous_public_key: &PublicKey,
+    client_public_key: &PublicKey,
+) -> [u8; 32] {
+    let new_public_key = PublicKey::from(new_private_key);
+    let shared_secret = new_private_key.diffie_hellman(client_public_key);
+
+    let mut transcript = [0u8; 96];
+    transcript[..32].copy_from_slice(client_public_key.as_bytes());
+    transcript[32..64].copy_from_slice(previous_public_key.as_bytes());
+    transcript[64..].copy_from_slice(new_public_key.as_bytes());
+
+    let mut proof = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&transcript[..]), shared_secret.as_bytes())
+        .expand(KEY_ROTATION_PROOF_INFO, &mut proof)
+        .expect("proof length is valid for sha256");
+    proof
+}
+
 /// Sets up the server private key, loads an existing key if one is present at `path`
 /// otherwise will generate the save a new one
 pub async fn setup_private_key(path: &Path) -> std::io::Result<StaticSecret> {
@@ -62,3 +214,158 @@ async fn read_private_key(path: &Path) -> std::io::Result<Option<StaticSecret>>
     let secret = StaticSecret::from(buffer);
     Ok(Some(secret))
 }
+
+/// Write the previous key to `path`, stored as the key bytes followed
+/// by the little-endian retirement timestamp
+fn write_previous_private_key(path: &Path, previous: &PreviousServerKey) -> std::io::Result<()> {
+    let mut bytes = Vec::with_capacity(40);
+    bytes.extend_from_slice(previous.key_pair.private_key.as_bytes());
+    bytes.extend_from_slice(&previous.retire_at.timestamp().to_le_bytes());
+
+    write_file_atomic(path, &bytes)
+}
+
+/// Write `bytes` to a temporary file next to `path` then rename it over
+/// `path`, a crash part way through the write leaves the existing file intact
+fn write_file_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut file = std::fs::File::create(&temp_path)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+
+    std::fs::rename(&temp_path, path)
+}
+
+/// Read the previous key from `path`
+async fn read_previous_private_key(path: &Path) -> std::io::Result<Option<PreviousServerKey>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let bytes = tokio::fs::read(path).await?;
+
+    if bytes.len() != 40 {
+        return Err(std::io::Error::other(
+            "unexpected previous key size, expected 40 bytes",
+        ));
+    }
+
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&bytes[0..32]);
+
+    let mut timestamp = [0u8; 8];
+    timestamp.copy_from_slice(&bytes[32..40]);
+
+    let retire_at = DateTime::from_timestamp(i64::from_le_bytes(timestamp), 0)
+        .ok_or_else(|| std::io::Error::other("invalid previous key retirement time"))?;
+
+    Ok(Some(PreviousServerKey {
+        key_pair: ServerKeyPair::from(StaticSecret::from(key)),
+        retire_at,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ServerKeys, key_rotation_proof};
+    use chrono::{TimeDelta, Utc};
+    use hkdf::Hkdf;
+    use sha2::Sha256;
+    use x25519_dalek::{PublicKey, StaticSecret};
+
+    async fn load_keys(dir: &std::path::Path) -> ServerKeys {
+        ServerKeys::load(dir.join("server.key"), dir.join("server.previous.key"))
+            .await
+            .unwrap()
+    }
+
+    /// Rotating keeps the replaced key usable until it is retired
+    #[tokio::test]
+    async fn test_rotate_and_retire() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = Utc::now();
+        let window = TimeDelta::hours(1);
+
+        let mut keys = load_keys(dir.path()).await;
+        let original = keys.current.public_key;
+
+        keys.rotate(window, now).unwrap();
+        assert_ne!(keys.current.public_key, original);
+
+        // Previous key is selected when pinned until the window ends
+        let pair = keys.get_key_pair(Some(original.as_bytes()), now);
+        assert_eq!(pair.public_key, original);
+        let pair = keys.get_key_pair(Some(original.as_bytes()), now + window);
+        assert_eq!(pair.public_key, keys.current.public_key);
+
+        // Both keys are persisted without leaving temporary files behind
+        assert!(!dir.path().join("server.key.tmp").exists());
+        assert!(!dir.path().join("server.previous.key.tmp").exists());
+        let loaded = load_keys(dir.path()).await;
+        assert_eq!(loaded.current.public_key, keys.current.public_key);
+        let previous = loaded.previous.as_ref().unwrap();
+        assert_eq!(previous.key_pair.public_key, original);
+
+        assert!(!keys.retire_previous(now).unwrap());
+        assert!(keys.retire_previous(now + window).unwrap());
+        assert!(keys.previous.is_none());
+        assert!(!dir.path().join("server.previous.key").exists());
+    }
+
+    /// A second rotation within the transition window is rejected
+    #[tokio::test]
+    async fn test_rotate_during_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = Utc::now();
+        let window = TimeDelta::hours(1);
+
+        let mut keys = load_keys(dir.path()).await;
+        let original = keys.current.public_key;
+
+        keys.rotate(window, now).unwrap();
+        let rotated = keys.current.public_key;
+
+        assert!(keys.rotate(window, now + TimeDelta::minutes(30)).is_err());
+        assert_eq!(keys.current.public_key, rotated);
+        let previous = keys.previous.as_ref().unwrap();
+        assert_eq!(previous.key_pair.public_key, original);
+
+        // Rotating again is allowed once the window has ended
+        keys.rotate(window, now + window).unwrap();
+        let previous = keys.previous.as_ref().unwrap();
+        assert_eq!(previous.key_pair.public_key, rotated);
+    }
+
+    /// Client can derive the same proof using its own private key
+    #[test]
+    fn test_key_rotation_proof() {
+        let new_private_key = StaticSecret::random();
+        let new_public_key = PublicKey::from(&new_private_key);
+        let previous_public_key = PublicKey::from(&StaticSecret::random());
+        let client_private_key = StaticSecret::random();
+        let client_public_key = PublicKey::from(&client_private_key);
+
+        let proof = key_rotation_proof(&new_private_key, &previous_public_key, &client_public_key);
+
+        let shared_secret = client_private_key.diffie_hellman(&new_public_key);
+        let mut transcript = Vec::new();
+        transcript.extend_from_slice(client_public_key.as_bytes());
+        transcript.extend_from_slice(previous_public_key.as_bytes());
+        transcript.extend_from_slice(new_public_key.as_bytes());
+
+        let mut expected = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(&transcript), shared_secret.as_bytes())
+            .expand(b"tilepad server key rotation", &mut expected)
+            .unwrap();
+        assert_eq!(proof, expected);
+
+        // Proof is bound to the previous key
+        let other_previous = PublicKey::from(&StaticSecret::random());
+        let other = key_rotation_proof(&new_private_key, &other_previous, &client_public_key);
+        assert_ne!(proof, other);
+    }
+}
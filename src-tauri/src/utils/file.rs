@@ -1,4 +1,7 @@
-use std::{collections::VecDeque, path::Path};
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Context;
 use tokio::{fs::canonicalize, try_join};
@@ -69,6 +72,37 @@ pub async fn copy_dir_all(src: &Path, dst: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Collects the paths of all files within the `src` directory and its
+/// subdirectories, paths are relative to `src`. A missing directory is
+/// treated as empty
+pub async fn collect_files(src: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    if !src.is_dir() {
+        return Ok(files);
+    }
+
+    let mut queue = VecDeque::new();
+    queue.push_back(PathBuf::new());
+
+    while let Some(relative_dir) = queue.pop_front() {
+        let mut entries = tokio::fs::read_dir(src.join(&relative_dir)).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let relative_path = relative_dir.join(entry.file_name());
+            let file_type = entry.file_type().await?;
+
+            if file_type.is_dir() {
+                queue.push_back(relative_path);
+            } else {
+                files.push(relative_path);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
 /// On unix systems the file must first be made executable
 #[cfg(unix)]
 pub fn make_file_executable(path: &Path) -> std::io::Result<()> {
@@ -0,0 +1,95 @@
+use std::{fmt::Debug, future::Future};
+
+use futures::{SinkExt, StreamExt};
+use serde::{Serialize, de::DeserializeOwned};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::mpsc,
+};
+use tokio_util::{
+    bytes::Bytes,
+    codec::{Framed, LengthDelimitedCodec},
+};
+
+use super::ws_msgpack::{WsMpRx, WsMpTx};
+
+/// Maximum size of a single frame
+const MAX_FRAME_LENGTH: usize = 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum FramedMpError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Encode(#[from] rmp_serde::encode::Error),
+}
+
+/// Abstraction for implementing a MSGPACK protocol on top of a byte
+/// stream (i.e a serial port). Each message is a frame prefixed with
+/// its length as a big-endian u32.
+///
+/// Returns the future that performs the reading and writing along with
+/// channels for receiving and sending messages, the future completes when
+/// the stream ends or all the senders are dropped
+pub fn framed_msgpack<S, MsgOut, MsgIn>(
+    stream: S,
+) -> (
+    impl Future<Output = Result<(), FramedMpError>>,
+    WsMpRx<MsgIn>,
+    WsMpTx<MsgOut>,
+)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    MsgOut: Serialize,
+    MsgIn: DeserializeOwned + Debug,
+{
+    let (inbound_tx, inbound_rx) = mpsc::unbounded_channel();
+    let (outbound_tx, mut outbound_rx) = mpsc::unbounded_channel::<MsgOut>();
+
+    let codec = LengthDelimitedCodec::builder()
+        .max_frame_length(MAX_FRAME_LENGTH)
+        .new_codec();
+    let mut framed = Framed::new(stream, codec);
+
+    let future = async move {
+        loop {
+            tokio::select! {
+                frame = framed.next() => {
+                    let frame = match frame {
+                        Some(result) => result?,
+                        // Stream has ended
+                        None => return Ok(()),
+                    };
+
+                    let msg: MsgIn = match rmp_serde::from_slice(&frame) {
+                        Ok(value) => value,
+                        Err(cause) => {
+                            tracing::warn!(?cause, "got invalid or unknown message from stream");
+                            continue;
+                        }
+                    };
+
+                    if inbound_tx.send(msg).is_err() {
+                        // Receiver for messages has dropped
+                        return Ok(());
+                    }
+                }
+
+                msg = outbound_rx.recv() => {
+                    let msg = match msg {
+                        Some(value) => value,
+                        // All message senders have dropped
+                        None => return Ok(()),
+                    };
+
+                    let msg = rmp_serde::to_vec_named(&msg)?;
+                    framed.send(Bytes::from(msg)).await?;
+                }
+            }
+        }
+    };
+
+    (future, inbound_rx, outbound_tx)
+}
@@ -3,6 +3,7 @@ pub mod display;
 pub mod encryption;
 pub mod error;
 pub mod file;
+pub mod framed_msgpack;
 pub mod inspector;
 pub mod tracing;
 pub mod ws;
@@ -3,10 +3,13 @@ use std::{
     future::Future,
     pin::Pin,
     task::{Context, Poll, ready},
+    time::Duration,
 };
 
 use axum::extract::ws::{Message as WsMessage, WebSocket};
+use chrono::{DateTime, Utc};
 use futures::{SinkExt, StreamExt};
+use parking_lot::Mutex;
 use serde::{Serialize, de::DeserializeOwned};
 use thiserror::Error;
 use tokio::sync::mpsc;
@@ -28,6 +31,36 @@ pub struct WebSocketMpFuture<MsgOut, MsgIn> {
     buffered_item: Option<WsMessage>,
 }
 
+/// Presence details for a socket, updated as messages are received
+#[derive(Default)]
+pub struct WsMpPresence {
+    state: Mutex<WsMpPresenceState>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct WsMpPresenceState {
+    /// Last time anything was received from the socket
+    pub last_seen: Option<DateTime<Utc>>,
+    /// Round trip time in milliseconds of the last answered ping
+    pub rtt_ms: Option<u32>,
+}
+
+impl WsMpPresence {
+    pub fn get(&self) -> WsMpPresenceState {
+        *self.state.lock()
+    }
+
+    /// Record that something was received from the socket
+    pub fn on_seen(&self) {
+        self.state.lock().last_seen = Some(Utc::now());
+    }
+
+    /// Record the round trip time of an answered ping
+    pub fn on_rtt(&self, rtt: Duration) {
+        self.state.lock().rtt_ms = Some(rtt.as_millis().min(u32::MAX as u128) as u32);
+    }
+}
+
 pub type WsMpTx<M> = mpsc::UnboundedSender<M>;
 pub type WsMpRx<M> = mpsc::UnboundedReceiver<M>;
 
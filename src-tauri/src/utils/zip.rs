@@ -1,10 +1,16 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Context;
-use async_zip::tokio::read::seek::ZipFileReader;
+use async_zip::{
+    Compression, ZipEntryBuilder,
+    tokio::{read::seek::ZipFileReader, write::ZipFileWriter},
+};
 use tokio::{
-    fs::{OpenOptions, create_dir_all},
-    io::{AsyncBufRead, AsyncSeek},
+    fs::{File, OpenOptions, create_dir_all},
+    io::{AsyncBufRead, AsyncReadExt, AsyncSeek, AsyncWriteExt},
 };
 use tokio_util::compat::FuturesAsyncReadCompatExt;
 
@@ -76,6 +82,95 @@ where
     Ok(())
 }
 
+/// Extracts the provided zip file reader to the provided `out_dir` like
+/// [extract_zip]. Fails if any entry does not match `allowed`, more than
+/// `max_files` files are present or the combined uncompressed size of the
+/// files exceeds `max_size` bytes
+pub async fn extract_zip_limited<R>(
+    mut zip: ZipFileReader<R>,
+    out_dir: &Path,
+    max_files: usize,
+    max_size: u64,
+    allowed: impl Fn(&str) -> bool,
+) -> anyhow::Result<()>
+where
+    R: AsyncBufRead + AsyncSeek + Unpin,
+{
+    let mut files = 0;
+    let mut remaining = max_size;
+
+    for index in 0..zip.file().entries().len() {
+        let entry = zip
+            .file()
+            .entries()
+            .get(index)
+            .context("failed to get entry")?;
+
+        let file_name = entry
+            .filename()
+            .as_str()
+            .context("invalid file name")?
+            .replace('\\', "/");
+
+        if !allowed(&file_name) {
+            return Err(anyhow::anyhow!("zip contains unexpected file {file_name}"));
+        }
+
+        let path = out_dir.join(sanitize_file_path(&file_name));
+        let entry_is_dir = entry.dir().context("failed to detect entry is dir")?;
+
+        if entry_is_dir {
+            if !path.exists() {
+                create_dir_all(&path)
+                    .await
+                    .context("failed to create extracted directory")?;
+            }
+
+            continue;
+        }
+
+        files += 1;
+        anyhow::ensure!(files <= max_files, "zip contains too many files");
+
+        // Sizes in the zip headers are not trusted, the reader is limited to
+        // one byte past the remaining size to detect files that are too large
+        let mut entry_reader = zip
+            .reader_without_entry(index)
+            .await
+            .context("failed to read ZipEntry")?
+            .compat()
+            .take(remaining + 1);
+
+        let parent = path
+            .parent()
+            .context("file entry should have parent directories")?;
+
+        if !parent.is_dir() {
+            create_dir_all(parent)
+                .await
+                .context("failed to create parent directories")?;
+        }
+
+        let mut writer = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .truncate(true)
+            .open(&path)
+            .await
+            .context("Failed to create extracted file")?;
+
+        let size = tokio::io::copy(&mut entry_reader, &mut writer)
+            .await
+            .context("failed to copy to extracted file")?;
+
+        remaining = remaining
+            .checked_sub(size)
+            .context("zip contents are too large")?;
+    }
+
+    Ok(())
+}
+
 /// Attempts to extract the contents of a file named `file_name` from the provided
 /// `zip` zip file, returns the bytes of the file if one was found
 pub async fn extract_zip_file<R>(
@@ -114,6 +209,114 @@ where
     Ok(Some(data))
 }
 
+/// Reads the contents of the files within the provided `zip` zip file that
+/// match `include` into memory, returns a map of the file names to the bytes
+/// of the file. Fails if more than `max_files` files match or the combined
+/// uncompressed size of the files exceeds `max_size` bytes
+pub async fn read_zip_files<R>(
+    mut zip: ZipFileReader<R>,
+    max_files: usize,
+    max_size: u64,
+    include: impl Fn(&str) -> bool,
+) -> anyhow::Result<HashMap<String, Vec<u8>>>
+where
+    R: AsyncBufRead + AsyncSeek + Unpin,
+{
+    let mut files = HashMap::new();
+    let mut remaining = max_size;
+
+    for index in 0..zip.file().entries().len() {
+        let entry = zip
+            .file()
+            .entries()
+            .get(index)
+            .context("failed to get entry")?;
+
+        if entry.dir().context("failed to detect entry is dir")? {
+            continue;
+        }
+
+        let file_name = entry
+            .filename()
+            .as_str()
+            .context("invalid file name")?
+            .replace('\\', "/");
+
+        if !include(&file_name) {
+            continue;
+        }
+
+        anyhow::ensure!(files.len() < max_files, "zip contains too many files");
+
+        // Sizes in the zip headers are not trusted, the reader is limited to
+        // one byte past the remaining size to detect files that are too large
+        let mut file_reader = zip
+            .reader_without_entry(index)
+            .await
+            .context("failed to read file from zip")?
+            .compat()
+            .take(remaining + 1);
+
+        let mut data = Vec::new();
+        let size = tokio::io::copy(&mut file_reader, &mut data)
+            .await
+            .context("failed to read zip file")?;
+
+        remaining = remaining
+            .checked_sub(size)
+            .context("zip contents are too large")?;
+
+        files.insert(file_name, data);
+    }
+
+    Ok(files)
+}
+
+/// Creates a zip file in memory containing the provided `files`, each
+/// file is a pair of the file name and the contents of the file
+pub async fn create_zip_bytes(files: Vec<(String, Vec<u8>)>) -> anyhow::Result<Vec<u8>> {
+    let mut writer = ZipFileWriter::with_tokio(Vec::new());
+
+    for (file_name, data) in files {
+        let entry = ZipEntryBuilder::new(file_name.into(), Compression::Deflate);
+        writer
+            .write_entry_whole(entry, &data)
+            .await
+            .context("failed to write zip file")?;
+    }
+
+    let data = writer.close().await.context("failed to finish zip")?;
+    Ok(data.into_inner())
+}
+
+/// Creates a zip file at `path` containing the provided `files`, each file
+/// is a pair of the file name within the zip and the path to read it from
+pub async fn create_zip_file(path: &Path, files: Vec<(String, PathBuf)>) -> anyhow::Result<()> {
+    let file = File::create(path)
+        .await
+        .context("failed to create zip file")?;
+    let mut writer = ZipFileWriter::with_tokio(file);
+
+    for (file_name, file_path) in files {
+        let data = tokio::fs::read(&file_path)
+            .await
+            .with_context(|| format!("failed to read {}", file_path.display()))?;
+
+        let entry = ZipEntryBuilder::new(file_name.into(), Compression::Deflate);
+        writer
+            .write_entry_whole(entry, &data)
+            .await
+            .context("failed to write zip file")?;
+    }
+
+    let mut file = writer.close().await.context("failed to finish zip")?;
+    file.get_mut()
+        .flush()
+        .await
+        .context("failed to flush zip")?;
+    Ok(())
+}
+
 /// Returns a relative path without reserved names, redundant separators, ".", or "..".
 fn sanitize_file_path(path: &str) -> PathBuf {
     // Replaces backwards slashes
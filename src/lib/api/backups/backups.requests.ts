@@ -0,0 +1,24 @@
+import { invoke } from "@tauri-apps/api/core";
+
+import type { BackupInfo } from "../types/backups";
+
+export function getBackups(): Promise<BackupInfo[]> {
+  return invoke<BackupInfo[]>("backups_get_backups");
+}
+
+export function createBackup(): Promise<BackupInfo> {
+  return invoke<BackupInfo>("backups_create_backup");
+}
+
+export function downloadBackup(fileName: string): Promise<ArrayBuffer> {
+  return invoke<ArrayBuffer>("backups_download_backup", { fileName });
+}
+
+export async function restoreBackup(file: File): Promise<void> {
+  const data = await file.arrayBuffer();
+  return invoke<void>("backups_restore_backup", { data });
+}
+
+export function restoreStoredBackup(fileName: string): Promise<void> {
+  return invoke<void>("backups_restore_stored_backup", { fileName });
+}
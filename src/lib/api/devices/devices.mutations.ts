@@ -1,12 +1,13 @@
 import { createMutation } from "@tanstack/svelte-query";
 
-import type { DeviceId } from "../types/devices";
+import type { DeviceId, DeviceConfig } from "../types/devices";
 import type { FolderId } from "../types/folders";
 import type { ProfileId } from "../types/profiles";
 
 import { invalidateDevices } from "./devices.mutators";
 import {
   revokeDevice,
+  setDeviceConfig,
   setDeviceFolder,
   setDeviceProfile,
 } from "./devices.requests";
@@ -50,3 +51,18 @@ export function createRevokeDeviceFolderMutation() {
     },
   }));
 }
+
+export function createSetDeviceConfigMutation() {
+  return createMutation(() => ({
+    mutationFn: ({
+      deviceId,
+      config,
+    }: {
+      deviceId: DeviceId;
+      config: DeviceConfig;
+    }) => setDeviceConfig(deviceId, config),
+    onSuccess() {
+      invalidateDevices();
+    },
+  }));
+}
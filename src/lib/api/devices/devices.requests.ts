@@ -5,9 +5,22 @@ import type { ProfileId } from "../types/profiles";
 import type {
   DeviceId,
   DeviceModel,
+  DeviceConfig,
   DeviceRequest,
+  ServerKeyInfo,
+  DeviceGroupId,
   ConnectedDevice,
   DeviceRequestId,
+  DeviceEventPage,
+  DeviceEventQuery,
+  DeviceGroupModel,
+  AutoApproveRuleId,
+  CreateDeviceGroup,
+  UpdateDeviceGroup,
+  AutoApproveRuleModel,
+  CreateAutoApproveRule,
+  UpdateAutoApproveRule,
+  DeviceAutoApprovalModel,
 } from "../types/devices";
 
 export function getDeviceRequests() {
@@ -18,6 +31,10 @@ export function getDevices() {
   return invoke<DeviceModel[]>("devices_get_devices");
 }
 
+export function reorderDevices(deviceIds: DeviceId[]) {
+  return invoke<DeviceModel[]>("devices_reorder_devices", { deviceIds });
+}
+
 export function getConnectedDevices() {
   return invoke<ConnectedDevice[]>("devices_get_connected_devices");
 }
@@ -41,3 +58,90 @@ export function setDeviceProfile(deviceId: DeviceId, profileId: ProfileId) {
 export function setDeviceFolder(deviceId: DeviceId, folderId: FolderId) {
   return invoke<void>("devices_set_device_folder", { deviceId, folderId });
 }
+
+export function setDeviceConfig(deviceId: DeviceId, config: DeviceConfig) {
+  return invoke<DeviceModel>("devices_set_device_config", { deviceId, config });
+}
+
+export function getPairingOpen() {
+  return invoke<boolean>("devices_get_pairing_open");
+}
+
+export function setPairingOpen(open: boolean) {
+  return invoke<void>("devices_set_pairing_open", { open });
+}
+
+export function getServerKey() {
+  return invoke<ServerKeyInfo>("devices_get_server_key");
+}
+
+export function rotateServerKey(transitionHours: number) {
+  return invoke<ServerKeyInfo>("devices_rotate_server_key", {
+    transitionHours,
+  });
+}
+
+export function getAutoApproveRules() {
+  return invoke<AutoApproveRuleModel[]>("devices_get_auto_approve_rules");
+}
+
+export function createAutoApproveRule(create: CreateAutoApproveRule) {
+  return invoke<AutoApproveRuleModel>("devices_create_auto_approve_rule", {
+    create,
+  });
+}
+
+export function updateAutoApproveRule(
+  ruleId: AutoApproveRuleId,
+  update: UpdateAutoApproveRule,
+) {
+  return invoke<AutoApproveRuleModel>("devices_update_auto_approve_rule", {
+    ruleId,
+    update,
+  });
+}
+
+export function deleteAutoApproveRule(ruleId: AutoApproveRuleId) {
+  return invoke<void>("devices_delete_auto_approve_rule", { ruleId });
+}
+
+export function getAutoApprovals() {
+  return invoke<DeviceAutoApprovalModel[]>("devices_get_auto_approvals");
+}
+
+export function getDeviceEvents(query: DeviceEventQuery) {
+  return invoke<DeviceEventPage>("devices_get_device_events", { query });
+}
+
+export function getDeviceGroups() {
+  return invoke<DeviceGroupModel[]>("devices_get_device_groups");
+}
+
+export function createDeviceGroup(create: CreateDeviceGroup) {
+  return invoke<DeviceGroupModel>("devices_create_device_group", { create });
+}
+
+export function updateDeviceGroup(
+  groupId: DeviceGroupId,
+  update: UpdateDeviceGroup,
+) {
+  return invoke<DeviceGroupModel>("devices_update_device_group", {
+    groupId,
+    update,
+  });
+}
+
+export function deleteDeviceGroup(groupId: DeviceGroupId) {
+  return invoke<void>("devices_delete_device_group", { groupId });
+}
+
+export function getDeviceGroupMembers(groupId: DeviceGroupId) {
+  return invoke<DeviceId[]>("devices_get_device_group_members", { groupId });
+}
+
+export function setDeviceGroup(
+  deviceId: DeviceId,
+  groupId: DeviceGroupId | null,
+) {
+  return invoke<void>("devices_set_device_group", { deviceId, groupId });
+}
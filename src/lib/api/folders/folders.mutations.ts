@@ -4,6 +4,7 @@ import type { ProfileId } from "../types/profiles";
 import type { FolderId, CreateFolder, FolderConfig } from "../types/folders";
 
 import { foldersKeys } from "./folders.keys";
+import { invalidateTilesList } from "../tiles/tiles.mutators";
 import { invalidateFoldersList } from "./folders.mutators";
 import {
   createFolder,
@@ -50,12 +51,15 @@ export function createSetFolderConfigMutation() {
     mutationFn: ({
       folderId,
       config,
+      reflow,
     }: {
       folderId: FolderId;
       config: FolderConfig;
-    }) => setFolderConfig(folderId, config),
+      reflow: boolean;
+    }) => setFolderConfig(folderId, config, reflow),
     onSuccess(folder) {
       invalidateFoldersList(queryClient, folder.profile_id);
+      invalidateTilesList(folder.id);
       queryClient.setQueryData(
         foldersKeys.specific(folder.profile_id, folder.id),
         folder,
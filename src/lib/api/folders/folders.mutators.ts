@@ -30,6 +30,21 @@ export function updateFolderRows(
   );
 }
 
+export function updateFolderPages(
+  client: QueryClient,
+  profileId: ProfileId,
+  folderId: FolderId,
+  pages: number,
+) {
+  client.setQueryData<FolderModel>(
+    foldersKeys.specific(profileId, folderId),
+    (data) => {
+      if (data === undefined) return data;
+      return { ...data, config: { ...data.config, pages } };
+    },
+  );
+}
+
 export function updateFolderColumns(
   client: QueryClient,
   profileId: ProfileId,
@@ -1,5 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
 
+import type { TileModel } from "../types/tiles";
 import type { ProfileId } from "../types/profiles";
 import type {
   FolderId,
@@ -31,13 +32,43 @@ export function setFolderName(folderId: FolderId, name: string) {
   });
 }
 
-export function setFolderConfig(folderId: FolderId, config: FolderConfig) {
+export function setFolderConfig(
+  folderId: FolderId,
+  config: FolderConfig,
+  reflow: boolean,
+) {
   return invoke<FolderModel>("folders_set_config", {
     folderId,
     config,
+    reflow,
   });
 }
 
+export function getFolderResizeConflicts(
+  folderId: FolderId,
+  rows: number,
+  columns: number,
+  pages: number,
+) {
+  return invoke<TileModel[]>("folders_get_resize_conflicts", {
+    folderId,
+    rows,
+    columns,
+    pages,
+  });
+}
+
+export function reorderFolders(profileId: ProfileId, folderIds: FolderId[]) {
+  return invoke<FolderModel[]>("folders_reorder_folders", {
+    profileId,
+    folderIds,
+  });
+}
+
+export function setDefaultFolder(folderId: FolderId) {
+  return invoke<FolderModel>("folders_set_default", { folderId });
+}
+
 export function deleteFolder(folderId: FolderId) {
   return invoke<object>("folders_delete_folder", { folderId });
 }
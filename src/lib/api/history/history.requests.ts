@@ -0,0 +1,27 @@
+import { invoke } from "@tauri-apps/api/core";
+
+import type { HistoryStatus } from "../types/history";
+
+import { queryClient } from "../client";
+
+export function getHistoryStatus() {
+  return invoke<HistoryStatus>("history_get_status");
+}
+
+export async function undo() {
+  const status = await invoke<HistoryStatus>("history_undo");
+
+  // Undone changes can affect any profile, folder or tile
+  queryClient.invalidateQueries();
+
+  return status;
+}
+
+export async function redo() {
+  const status = await invoke<HistoryStatus>("history_redo");
+
+  // Redone changes can affect any profile, folder or tile
+  queryClient.invalidateQueries();
+
+  return status;
+}
@@ -0,0 +1,23 @@
+import { invoke } from "@tauri-apps/api/core";
+
+import type {
+  MidiBindingId,
+  MidiBindingModel,
+  CreateMidiBinding,
+} from "../types/midi";
+
+export function getMidiInputPorts() {
+  return invoke<string[]>("midi_get_input_ports");
+}
+
+export function getMidiBindings() {
+  return invoke<MidiBindingModel[]>("midi_get_bindings");
+}
+
+export function createMidiBinding(create: CreateMidiBinding) {
+  return invoke<MidiBindingModel>("midi_create_binding", { create });
+}
+
+export function deleteMidiBinding(bindingId: MidiBindingId) {
+  return invoke<void>("midi_delete_binding", { bindingId });
+}
@@ -1,6 +1,11 @@
 import { invoke } from "@tauri-apps/api/core";
 
-import type { ProfileId, ProfileModel, CreateProfile } from "../types/profiles";
+import type {
+  ProfileId,
+  ProfileModel,
+  CreateProfile,
+  ProfileImportResult,
+} from "../types/profiles";
 
 import { queryClient } from "../client";
 import { profilesKeys } from "./profiles.keys";
@@ -25,6 +30,18 @@ export async function createProfile(create: CreateProfile) {
   return profile;
 }
 
+export async function copyProfile(profileId: ProfileId, name: string) {
+  const profile = await invoke<ProfileModel>("profiles_copy_profile", {
+    profileId,
+    name,
+  });
+
+  invalidateProfilesList();
+  queryClient.setQueryData(profilesKeys.specific(profile.id), profile);
+
+  return profile;
+}
+
 export async function setProfileName(profileId: ProfileId, name: string) {
   const profile = await invoke<ProfileModel>("profiles_set_name", {
     profileId,
@@ -37,8 +54,47 @@ export async function setProfileName(profileId: ProfileId, name: string) {
   return profile;
 }
 
+export async function reorderProfiles(profileIds: ProfileId[]) {
+  const profiles = await invoke<ProfileModel[]>("profiles_reorder_profiles", {
+    profileIds,
+  });
+
+  invalidateProfilesList();
+
+  return profiles;
+}
+
+export async function setDefaultProfile(profileId: ProfileId) {
+  const profile = await invoke<ProfileModel>("profiles_set_default", {
+    profileId,
+  });
+
+  invalidateProfilesList();
+
+  return profile;
+}
+
 export async function deleteProfile(profileId: ProfileId) {
   await invoke("profiles_delete_profile", { profileId });
 
   invalidateProfilesList();
 }
+
+export function exportProfile(profileId: ProfileId) {
+  return invoke<ArrayBuffer>("profiles_export_profile", { profileId });
+}
+
+export async function importProfile(file: File) {
+  const data = await file.arrayBuffer();
+  const result = await invoke<ProfileImportResult>("profiles_import_profile", {
+    data,
+  });
+
+  invalidateProfilesList();
+  queryClient.setQueryData(
+    profilesKeys.specific(result.profile.id),
+    result.profile,
+  );
+
+  return result;
+}
@@ -6,8 +6,10 @@ import type {
   TileIcon,
   TileModel,
   TileLabel,
+  TileState,
   CreateTile,
   UpdateKind,
+  TileControl,
   TilePosition,
   TileIconOptions,
 } from "../types/tiles";
@@ -33,6 +35,18 @@ export function updateTilePosition(tileId: TileId, position: TilePosition) {
   });
 }
 
+export function duplicateTile(tileId: TileId) {
+  return invoke<TileModel>("tiles_duplicate_tile", { tileId });
+}
+
+export function copyTiles(tileIds: TileId[], folderId: FolderId) {
+  return invoke<TileModel[]>("tiles_copy_tiles", { tileIds, folderId });
+}
+
+export function moveTile(tileId: TileId, folderId: FolderId) {
+  return invoke<TileModel>("tiles_move_tile", { tileId, folderId });
+}
+
 export function updateTileProperties(
   tileId: TileId,
   properties: object,
@@ -79,6 +93,30 @@ export function updateTileIconOptions(
   });
 }
 
+export function updateTileControl(tileId: TileId, control: TileControl) {
+  return invoke<TileModel>("tiles_update_tile_control", { tileId, control });
+}
+
+export function updateTileValue(tileId: TileId, value: number) {
+  return invoke<TileModel>("tiles_update_tile_value", { tileId, value });
+}
+
+export function updateTileStates(
+  tileId: TileId,
+  states: TileState[],
+  cycleStates: boolean,
+) {
+  return invoke<TileModel>("tiles_update_tile_states", {
+    tileId,
+    states,
+    cycleStates,
+  });
+}
+
+export function setTileState(tileId: TileId, state: number) {
+  return invoke<TileModel>("tiles_update_tile_state", { tileId, state });
+}
+
 export function deleteTile(tileId: TileId) {
   return invoke("tiles_delete_tile", { tileId });
 }
@@ -0,0 +1,49 @@
+import { invoke } from "@tauri-apps/api/core";
+
+import type { TileId, TileModel } from "../types/tiles";
+import type { TrashContents } from "../types/trash";
+import type { FolderId, FolderModel } from "../types/folders";
+import type { ProfileId, ProfileModel } from "../types/profiles";
+
+import { queryClient } from "../client";
+import { invalidateTilesList } from "../tiles/tiles.mutators";
+import { invalidateFoldersList } from "../folders/folders.mutators";
+import { invalidateProfilesList } from "../profiles/profiles.mutators";
+
+export function getTrash(): Promise<TrashContents> {
+  return invoke<TrashContents>("trash_get_trash");
+}
+
+export async function restoreProfile(
+  profileId: ProfileId,
+): Promise<ProfileModel> {
+  const profile = await invoke<ProfileModel>("trash_restore_profile", {
+    profileId,
+  });
+
+  invalidateProfilesList();
+
+  return profile;
+}
+
+export async function restoreFolder(folderId: FolderId): Promise<FolderModel> {
+  const folder = await invoke<FolderModel>("trash_restore_folder", {
+    folderId,
+  });
+
+  invalidateFoldersList(queryClient, folder.profile_id);
+
+  return folder;
+}
+
+export async function restoreTile(tileId: TileId): Promise<TileModel> {
+  const tile = await invoke<TileModel>("trash_restore_tile", { tileId });
+
+  invalidateTilesList(tile.folder_id);
+
+  return tile;
+}
+
+export function emptyTrash(): Promise<void> {
+  return invoke<void>("trash_empty_trash");
+}
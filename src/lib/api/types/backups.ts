@@ -0,0 +1,6 @@
+export interface BackupInfo {
+  file_name: string;
+  size: number;
+  created_at: string;
+  automatic: boolean;
+}
@@ -14,6 +14,7 @@ export interface DeviceRequest {
   session_id: DeviceSessionId;
   device_name: string;
   client_public_key: number[];
+  created_at: string;
 }
 
 export interface DeviceModel {
@@ -28,9 +29,128 @@ export interface DeviceModel {
   public_key: number[];
 }
 
-export type DeviceConfig = object;
+export interface DeviceConfig {
+  allowed_profiles: ProfileId[] | null;
+  allowed_folders: FolderId[] | null;
+}
 
 export interface ConnectedDevice {
   device_id: DeviceId;
   session_id: DeviceSessionId;
+  socket_addr: string;
+  last_seen: string | null;
+  rtt_ms: number | null;
+}
+
+export type AutoApproveRuleId = Uuid;
+
+export interface AutoApproveRuleConfig {
+  subnet: string | null;
+  name_pattern: string | null;
+  public_key_fingerprint: string | null;
+}
+
+export interface AutoApproveRuleModel {
+  id: AutoApproveRuleId;
+  name: string;
+  config: AutoApproveRuleConfig;
+  enabled: boolean;
+  profile_id: ProfileId;
+  folder_id: FolderId;
+  order: number;
+  created_at: string;
+}
+
+export interface CreateAutoApproveRule {
+  name: string;
+  config: AutoApproveRuleConfig;
+  enabled: boolean;
+  profile_id: ProfileId;
+  folder_id: FolderId;
+  order: number;
+}
+
+export interface UpdateAutoApproveRule {
+  name: string;
+  config: AutoApproveRuleConfig;
+  enabled: boolean;
+  profile_id: ProfileId;
+  folder_id: FolderId;
+}
+
+export interface DeviceAutoApprovalModel {
+  id: Uuid;
+  rule_id: AutoApproveRuleId;
+  rule_name: string;
+  device_id: DeviceId;
+  device_name: string;
+  public_key_fingerprint: string;
+  socket_addr: string;
+  created_at: string;
+}
+
+export type DeviceEventKind =
+  | "Connected"
+  | "Disconnected"
+  | "Approved"
+  | "AutoApproved"
+  | "Declined"
+  | "Revoked"
+  | "HandshakeFailed";
+
+export interface DeviceEventModel {
+  id: Uuid;
+  kind: DeviceEventKind;
+  device_id: DeviceId | null;
+  public_key_fingerprint: string | null;
+  socket_addr: string | null;
+  message: string | null;
+  created_at: string;
+}
+
+export interface DeviceEventQuery {
+  device_id: DeviceId | null;
+  from: string | null;
+  to: string | null;
+  page: number;
+  page_size: number;
+}
+
+export interface DeviceEventPage {
+  events: DeviceEventModel[];
+  total: number;
+}
+
+export interface ServerKeyInfo {
+  public_key_fingerprint: string;
+  previous_public_key_fingerprint: string | null;
+  previous_retire_at: string | null;
+}
+
+export type DeviceGroupId = Uuid;
+
+export interface DeviceGroupFolderPair {
+  folder_id: FolderId;
+  paired_folder_id: FolderId;
+}
+
+export interface DeviceGroupConfig {
+  folder_pairs: DeviceGroupFolderPair[];
+}
+
+export interface DeviceGroupModel {
+  id: DeviceGroupId;
+  name: string;
+  config: DeviceGroupConfig;
+  created_at: string;
+}
+
+export interface CreateDeviceGroup {
+  name: string;
+  config: DeviceGroupConfig;
+}
+
+export interface UpdateDeviceGroup {
+  name: string;
+  config: DeviceGroupConfig;
 }
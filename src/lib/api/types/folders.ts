@@ -1,7 +1,9 @@
 import type { DeepPartial } from "$lib/types";
 
 import type { Uuid } from "./shared";
+import type { DeviceId } from "./devices";
 import type { ProfileId } from "./profiles";
+import type { TileId, TilePosition } from "./tiles";
 
 export type FolderId = Uuid;
 
@@ -12,15 +14,35 @@ export interface FolderModel {
   profile_id: ProfileId;
   default: boolean;
   order: number;
+  deleted_at: string | null;
 }
 
 export interface FolderConfig {
   rows: number;
   columns: number;
+  pages: number;
+  layouts: FolderLayout[];
 }
 
-export type CreateFolder = Omit<FolderModel, "id" | "config"> & {
+export enum ScreenSizeClass {
+  Small = "Small",
+  Medium = "Medium",
+  Large = "Large",
+}
+
+export type FolderLayoutTarget =
+  | { type: "Device"; device_id: DeviceId }
+  | { type: "SizeClass"; size_class: ScreenSizeClass };
+
+export interface FolderLayout {
+  target: FolderLayoutTarget;
+  rows: number;
+  columns: number;
+  positions: Record<TileId, TilePosition>;
+}
+
+export type CreateFolder = Omit<FolderModel, "id" | "config" | "deleted_at"> & {
   config: Partial<FolderConfig>;
 };
 
-export type UpdateFolder = DeepPartial<Omit<FolderModel, "id">>;
+export type UpdateFolder = DeepPartial<Omit<FolderModel, "id" | "deleted_at">>;
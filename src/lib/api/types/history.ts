@@ -0,0 +1,4 @@
+export interface HistoryStatus {
+  can_undo: boolean;
+  can_redo: boolean;
+}
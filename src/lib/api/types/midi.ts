@@ -0,0 +1,34 @@
+import type { Uuid } from "./shared";
+import type { TileId } from "./tiles";
+
+export type MidiBindingId = Uuid;
+
+export type MidiTrigger =
+  | { type: "Note"; channel: number | null; note: number }
+  | { type: "ControlChange"; channel: number | null; control: number };
+
+export interface MidiFeedback {
+  success: number;
+  error: number;
+  warning: number;
+  loading: number;
+}
+
+export interface MidiBindingConfig {
+  trigger: MidiTrigger;
+  feedback: MidiFeedback | null;
+}
+
+export interface MidiBindingModel {
+  id: MidiBindingId;
+  port_name: string;
+  config: MidiBindingConfig;
+  tile_id: TileId;
+  created_at: string;
+}
+
+export interface CreateMidiBinding {
+  port_name: string;
+  config: MidiBindingConfig;
+  tile_id: TileId;
+}
@@ -8,10 +8,29 @@ export interface ProfileModel {
   default: boolean;
   config: ProfileConfig;
   order: number;
+  deleted_at: string | null;
 }
 
 export type ProfileConfig = object;
 
-export type CreateProfile = Omit<ProfileModel, "id">;
+export type CreateProfile = Omit<ProfileModel, "id" | "deleted_at">;
 
-export type UpdateProfile = Partial<Omit<ProfileModel, "id">>;
+export type UpdateProfile = Partial<Omit<ProfileModel, "id" | "deleted_at">>;
+
+export interface ArchiveDependency {
+  id: string;
+  version: string | null;
+}
+
+export interface UnresolvedReference {
+  tile_id: Uuid;
+  property: string;
+  value: Uuid;
+}
+
+export interface ProfileImportResult {
+  profile: ProfileModel;
+  missing_plugins: ArchiveDependency[];
+  missing_icon_packs: ArchiveDependency[];
+  unresolved_references: UnresolvedReference[];
+}
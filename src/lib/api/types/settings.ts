@@ -6,4 +6,42 @@ export interface SettingsConfig {
   minimize_tray: boolean;
   start_minimized: boolean;
   port: number;
+  pairing: PairingSettings;
+  keep_alive: KeepAliveSettings;
+  serial: SerialSettings;
+  backup: BackupSettings;
+  trash: TrashSettings;
+}
+
+export interface PairingSettings {
+  max_requests_per_address: number;
+  max_requests_global: number;
+  max_pending_requests: number;
+  request_expiry: number;
+  reject_unknown_devices: boolean;
+}
+
+export interface KeepAliveSettings {
+  interval: number;
+  timeout: number;
+}
+
+export interface SerialSettings {
+  ports: SerialPortSettings[];
+  rescan_interval: number;
+}
+
+export interface SerialPortSettings {
+  path: string;
+  baud_rate: number;
+}
+
+export interface BackupSettings {
+  enabled: boolean;
+  interval: number;
+  retention: number;
+}
+
+export interface TrashSettings {
+  retention: number;
 }
@@ -22,9 +22,12 @@ export interface TileModel {
   plugin_id: PluginId;
   action_id: ActionId;
   position: TilePosition;
+  value: number;
+  deleted_at: string | null;
 }
 
 export interface TilePosition {
+  page: number;
   row: number;
   column: number;
   row_span: number;
@@ -35,9 +38,33 @@ export interface TileConfig {
   icon: TileIcon;
   icon_options: TileIconOptions;
   label: TileLabel;
+  control: TileControl;
+  states: TileState[];
+  state: number;
+  cycle_states: boolean;
   user_flags: UserFlags;
 }
 
+export interface TileState {
+  icon: TileIcon;
+  label: TileLabel;
+  icon_options: TileIconOptions;
+}
+
+export enum TileControlKind {
+  Button = "Button",
+  Slider = "Slider",
+  Dial = "Dial",
+  Encoder = "Encoder",
+}
+
+export interface TileControl {
+  kind: TileControlKind;
+  min: number;
+  max: number;
+  step: number;
+}
+
 export interface TileIconOptions {
   padding: number;
   background_color: string;
@@ -82,11 +109,13 @@ export type TileIcon =
   | ({ type: TileIconType.Url } & TileIconUrl)
   | ({ type: TileIconType.Display } & TileIconDisplay);
 
-export type CreateTile = Omit<TileModel, "id" | "config"> & {
+export type CreateTile = Omit<TileModel, "id" | "config" | "deleted_at"> & {
   config: DeepPartial<TileConfig>;
 };
 
-export type UpdateTile = Partial<Omit<TileModel, "id" | "config">> & {
+export type UpdateTile = Partial<
+  Omit<TileModel, "id" | "config" | "deleted_at">
+> & {
   config: DeepPartial<TileConfig>;
 };
 
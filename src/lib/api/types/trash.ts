@@ -0,0 +1,9 @@
+import type { TileModel } from "./tiles";
+import type { FolderModel } from "./folders";
+import type { ProfileModel } from "./profiles";
+
+export interface TrashContents {
+  profiles: ProfileModel[];
+  folders: FolderModel[];
+  tiles: TileModel[];
+}